@@ -10,120 +10,481 @@
 //! 
 //! - `ASTNode` - The root node of the tree, which can be either a `Procedure` or a `ControlFlow`.
 //! - `Procedure` - Represents a single procedure in the language, such as `FORWARD 10` or `MAKE "x 10`.
-//! - `ControlFlow` - Represents a control flow structure in the language, such as an `IF` statement or a `WHILE` loop.
+//! - `ControlFlow` - Represents a control flow structure in the language, such as an `IF` statement, a `WHILE` loop, or a `TO` definition.
 //! - `Condition` - Represents a boolean condition in the language, such as `EQ 1 2` or `AND EQ 1 1 EQ 2 2`.
 //! - `Expression` - Represents an expression in the language, such as a float, a variable, or a math operation.
 //! - `Math` - Represents a math operation in the language, such as `ADD 1 2` or `MUL 3 4`.
 //! - `Query` - Represents a query in the language, such as `XCOR` or `YCOR`.
 //! 
 //! The AST is used by the `Turtle` module to execute the parsed code and draw the resulting image.
+//! Execution is generic over the `TurtleOps` trait, so the AST can also be run against a mock turtle.
 
 
-use crate::{turtle::Turtle, uncertain_bool::is_option_eq};
-use unsvg;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
-/// The root node of the AST, which can be either a `Procedure` or a `ControlFlow`.
+use crate::{error::RuntimeError, ops::TurtleOps, turtle::color_index, uncertain_bool::{is_option_eq, is_option_near}};
+use crate::value::{power, remainder, Value};
+
+/// The range of byte offsets in the source code that a node was parsed from.
+pub type Span = std::ops::Range<usize>;
+
+/// Converts a byte offset into `source` to a line and column, both counting from 1.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::ast::line_column;
+/// 
+/// assert_eq!(line_column("PENDOWN\nFORWARD \"x", 16), (2, 9));
+/// ```
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |line| line.chars().count()) + 1;
+    (line, column)
+}
+
+/// The root node of the AST, which can be either a `Procedure` or a `ControlFlow`, along with the `Span`
+/// of the source code it was parsed from.
 /// 
 /// The `execute` method is used to execute the AST.
-/// It takes a mutable reference to a `Turtle` and recursively executes all the nodes in the tree.
+/// It takes a mutable reference to a `Turtle`, or any other `TurtleOps`, and recursively executes all the nodes in the tree.
 /// 
 /// # Methods
 /// 
 /// * `execute`: Executes the AST using the given `Turtle` state.
+/// * `span`: Gets the span of the source code the node was parsed from.
+/// 
+/// Nodes are compared and hashed by what they do, ignoring their spans, so the same program parsed from
+/// differently laid out source code is equal, and can be deduplicated or cached by its content.
 /// 
 /// # Example
 /// 
 /// ```
 /// use rslogo::ast::{ASTNode, Procedure, Expression};
 /// use rslogo::turtle::Turtle;
-/// use unsvg::Image;
 /// 
-/// let mut image = Image::new(100, 100);
-/// let mut turtle = Turtle::new(&mut image);
+/// let mut turtle = Turtle::new(100, 100);
 /// 
-/// let ast = ASTNode::Procedure(Procedure::Forward(Expression::Float(10.0)));
+/// let ast = ASTNode::Procedure(Procedure::Forward(Expression::Float(10.0)), 0..10);
 /// ast.execute(&mut turtle);
 /// ast.execute(&mut turtle);
 /// 
 /// assert_eq!(turtle.get_y(), 30.0);
 /// 
 /// ```
+/// 
+/// ```
+/// use std::collections::HashSet;
+/// use rslogo::parser::parse_content;
+/// 
+/// let one_line = parse_content("REPEAT \"4 [ FORWARD \"10 TURN \"90 ]").unwrap();
+/// let laid_out = parse_content("REPEAT \"4 [\n    FORWARD \"10\n    TURN \"90\n]\n").unwrap();
+/// assert_eq!(one_line, laid_out);
+/// 
+/// let other = parse_content("REPEAT \"3 [ FORWARD \"10 TURN \"120 ]").unwrap();
+/// let submissions: HashSet<_> = [one_line, laid_out, other].into_iter().collect();
+/// assert_eq!(submissions.len(), 2);
+/// ```
 #[derive(Debug, Clone)]
 pub enum ASTNode {
     /// Represents a single procedure in the language, such as `FORWARD 10` or `MAKE "x 10`.
-    Procedure(Procedure),
+    Procedure(Procedure, Span),
 
     /// Represents a control flow structure in the language, such as an `IF` statement or a `WHILE` loop.
-    ControlFlow(ControlFlow),
+    ControlFlow(ControlFlow, Span),
+}
+
+impl PartialEq for ASTNode {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ASTNode::Procedure(a, _), ASTNode::Procedure(b, _)) => a == b,
+            (ASTNode::ControlFlow(a, _), ASTNode::ControlFlow(b, _)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ASTNode {}
+
+impl Hash for ASTNode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            ASTNode::Procedure(procedure, _) => procedure.hash(state),
+            ASTNode::ControlFlow(flow, _) => flow.hash(state),
+        }
+    }
 }
 impl ASTNode {
-    pub fn execute(&self, turtle: &mut Turtle) -> Result<(), unsvg::Error>{
-        if let ASTNode::Procedure(proceedure) = self {
+    /// Executes the node. If it fails, the span of the innermost node that raised the error is recorded on the turtle.
+    pub fn execute<T: TurtleOps + ?Sized>(&self, turtle: &mut T) -> Result<(), RuntimeError>{
+        turtle.clear_shared_values();
+        let result = turtle.step().and_then(|()| self.execute_node(turtle));
+        if result.is_err() {
+            turtle.locate_error(self.span());
+        }
+        self.skip_recoverable(result, turtle)
+    }
+
+    /// Handles the result of executing the node, skipping it and recording the error as a diagnostic rather
+    /// than failing if the error is recoverable and the turtle continues on errors.
+    pub(crate) fn skip_recoverable<T: TurtleOps + ?Sized>(
+        &self,
+        result: Result<(), RuntimeError>,
+        turtle: &mut T,
+    ) -> Result<(), RuntimeError> {
+        match result {
+            Err(e) if e.is_recoverable() && turtle.is_continue_on_error() => {
+                tracing::debug!(error = %e, "skipping instruction");
+                let span = turtle.take_error_span().unwrap_or_else(|| self.span().clone());
+                turtle.add_diagnostic(e, span);
+                Ok(())
+            },
+            result => result,
+        }
+    }
+
+    /// Gets the span of the source code the node was parsed from.
+    pub fn span(&self) -> &Span {
+        match self {
+            ASTNode::Procedure(_, span) | ASTNode::ControlFlow(_, span) => span,
+        }
+    }
+
+    fn execute_node<T: TurtleOps + ?Sized>(&self, turtle: &mut T) -> Result<(), RuntimeError> {
+        if let ASTNode::Procedure(proceedure, _) = self {
+            tracing::trace!(procedure = ?proceedure, "executing procedure");
             match proceedure {
-                // Only the pen up and pen down procedures do not require an expression
+                // Only the pen and state stack procedures do not require an expression
                 Procedure::PenUp => turtle.pen_up(),
                 Procedure::PenDown => turtle.pen_down(),
+                Procedure::PushState => turtle.push_state()?,
+                Procedure::PopState => turtle.pop_state()?,
+
+                Procedure::Forward(s) => turtle.forward(s.require_float(turtle)?)?,
+                Procedure::Back(s) => turtle.back(s.require_float(turtle)?)?,
+                Procedure::Left(s) => turtle.left(s.require_float(turtle)?)?,
+                Procedure::Right(s) => turtle.right(s.require_float(turtle)?)?,
+                Procedure::MovePolar(angle, distance) => {
+                    let angle = angle.require_float(turtle)?;
+                    let distance = distance.require_float(turtle)?;
+                    turtle.move_polar(angle, distance)?;
+                },
+                Procedure::FillAt(x, y) => turtle.fill_at(x.require_float(turtle)?, y.require_float(turtle)?)?,
+                Procedure::Toot(frequency, duration) => {
+                    let frequency = frequency.require_float(turtle)?;
+                    let duration = duration.require_float(turtle)?;
+                    turtle.toot(frequency, duration)?;
+                },
+                Procedure::Turn(s) => turtle.turn(s.require_float(turtle)?),
+                Procedure::SetHeading(s) => turtle.set_heading(s.require_float(turtle)?),
+                Procedure::SetPenColor(s) => turtle.set_pen_color(s.require_color(turtle)?)?,
+                Procedure::SetFillColor(s) => turtle.set_fill_color(s.require_color(turtle)?)?,
+                Procedure::SetX(s) => turtle.set_x(s.require_float(turtle)?),
+                Procedure::SetY(s) => turtle.set_y(s.require_float(turtle)?),
+                Procedure::SetSeed(s) => turtle.set_seed(s.require_float(turtle)? as i64 as u64),
+                Procedure::SetSpeed(s) => turtle.set_speed(s.require_float(turtle)?)?,
+                Procedure::Wait(s) => turtle.wait(s.require_float(turtle)?)?,
+                Procedure::SetPenSize(s) => turtle.set_pen_size(s.require_float(turtle)?)?,
+                Procedure::Throw(tag) => return Err(RuntimeError::Thrown(tag.require_word(turtle)?)),
 
-                Procedure::Forward(s) => turtle.forward(s.to_float(turtle).expect("Invalid value"))?,
-                Procedure::Back(s) => turtle.back(s.to_float(turtle).expect("Invalid value"))?,
-                Procedure::Left(s) => turtle.left(s.to_float(turtle).expect("Invalid value"))?,
-                Procedure::Right(s) => turtle.right(s.to_float(turtle).expect("Invalid value"))?,
-                Procedure::Turn(s) => turtle.turn(s.to_float(turtle).expect("Invalid value")),
-                Procedure::SetHeading(s) => turtle.set_heading(s.to_float(turtle).expect("Invalid value")),
-                Procedure::SetPenColor(s) => turtle.set_pen_color(s.to_float(turtle).expect("Invalid value")),
-                Procedure::SetX(s) => turtle.set_x(s.to_float(turtle).expect("Invalid value")),
-                Procedure::SetY(s) => turtle.set_y(s.to_float(turtle).expect("Invalid value")),
+                Procedure::Mark(name) => turtle.mark(name)?,
+                Procedure::Goto(name) => turtle.goto(name)?,
+                Procedure::Snapshot(name) => turtle.snapshot(name)?,
+                Procedure::RestoreImage(name) => turtle.restore_image(name)?,
 
                 Procedure::Make(s, s2) => {
-                    let name = match s {
-                        Expression::Variable(var) => var,
-                        _ => panic!("First argument of MAKE should be a variable"),
-                    };
-                    let val = match s2 {
-                        Expression::Math(_) => s2.eval_math(turtle),
-                        _ => s2.clone(),
-                    };
-                    turtle.add_variable(name, val);
+                    let name = variable_name(s, turtle)?;
+                    let val = s2.evaluate(turtle)?;
+                    turtle.add_variable(&name, val)?;
                 },
-                Procedure::AddAssign(s, s2) => {
-                    let name = match s {
-                        Expression::Variable(var) => var,
-                        _ => panic!("First argument of ADDASSIGN should be a variable"),
-                    };
-                    let cur = turtle.get_variable(name).to_float(turtle).expect("Variable not a float");
-                    let add = s2.to_float(turtle).expect("Second argument can't be turned into a float");
-                    turtle.add_variable(name, Expression::Float(cur + add));
+                Procedure::Local(s) => turtle.add_local_variable(&variable_name(s, turtle)?)?,
+                Procedure::LocalMake(s, s2) => {
+                    let name = variable_name(s, turtle)?;
+                    let val = s2.evaluate(turtle)?;
+                    turtle.add_local_variable(&name)?;
+                    turtle.add_variable(&name, val)?;
+                },
+                Procedure::AddAssign(s, s2) => compound_assign(s, s2, turtle, |cur, val| cur + val)?,
+                Procedure::SubAssign(s, s2) => compound_assign(s, s2, turtle, |cur, val| cur - val)?,
+                Procedure::MulAssign(s, s2) => compound_assign(s, s2, turtle, |cur, val| cur * val)?,
+                Procedure::DivAssign(s, s2) => compound_assign(s, s2, turtle, |cur, val| cur / val)?,
+                Procedure::Call(name, args) => {
+                    if let Some(definition) = turtle.get_definition(name) {
+                        let args = args.iter()
+                            .map(|arg| arg.evaluate(turtle))
+                            .collect::<Result<Vec<Value>, RuntimeError>>()?;
+                        definition.call(args, turtle)?;
+                        turtle.audit(proceedure)?;
+                        return Ok(());
+                    }
+                    let args = args.iter()
+                        .map(|arg| arg.require_float(turtle))
+                        .collect::<Result<Vec<f32>, RuntimeError>>()?;
+                    turtle.call_primitive(name, &args)?;
                 },
             }
+            turtle.audit(proceedure)?;
         };
-        if let ASTNode::ControlFlow(flow) = self {
+        if let ASTNode::ControlFlow(flow, _) = self {
             match flow {
                 ControlFlow::If { condition, block } => {
-                    let condition = condition.to_bool(turtle).expect("Control flow condition must be able to evaluate into a boolean");
-                    if condition {
-                        for instruction in block {
-                            let _ = instruction.execute(turtle);
-                        }
+                    tracing::trace!(?condition, "executing IF");
+                    if eval_condition(condition, turtle)? {
+                        execute_block(block, turtle)?;
                     }
                 },
                 ControlFlow::While { condition, block } => {
-                    let mut cond = condition.to_bool(turtle).expect("Control flow condition must be able to evaluate into a boolean");
-                    while cond {
-                        for instruction in block {
-                            let _ = instruction.execute(turtle);
+                    tracing::trace!(?condition, "executing WHILE");
+                    let mut iterations = 0;
+                    while eval_condition(condition, turtle)? {
+                        iterations += 1;
+                        turtle.loop_iteration("WHILE", iterations)?;
+                        execute_block(block, turtle)?;
+                    }
+                },
+                ControlFlow::DoWhile { block, condition } | ControlFlow::Until { block, condition } => {
+                    tracing::trace!(?condition, "executing DO.WHILE or DO.UNTIL");
+                    let until = matches!(flow, ControlFlow::Until { .. });
+                    let keyword = if until { "DO.UNTIL" } else { "DO.WHILE" };
+                    let mut iterations = 0;
+                    loop {
+                        iterations += 1;
+                        turtle.loop_iteration(keyword, iterations)?;
+                        execute_block(block, turtle)?;
+                        if eval_condition(condition, turtle)? == until {
+                            break;
                         }
-                        cond = condition.to_bool(turtle).expect("Control flow condition must be able to evaluate into a boolean");
                     }
                 },
+                ControlFlow::Repeat { count, block } => {
+                    tracing::trace!(?count, "executing REPEAT");
+                    let count = count.require_float(turtle)?;
+                    let outer = turtle.set_repcount(None);
+                    let result = (1..=count as usize).try_for_each(|repcount| {
+                        turtle.set_repcount(Some(repcount));
+                        execute_block(block, turtle)
+                    });
+                    turtle.set_repcount(outer);
+                    result?;
+                },
+                ControlFlow::For { block, .. } | ControlFlow::DoTimes { block, .. } => {
+                    let (name, values) = counter(flow, turtle)?;
+                    tracing::trace!(name, "executing FOR or DOTIMES");
+                    turtle.push_scope(false);
+                    let result = turtle.add_local_variable(&name).and_then(|()| {
+                        values.into_iter().try_for_each(|value| {
+                            turtle.add_variable(&name, value)?;
+                            execute_block(block, turtle)
+                        })
+                    });
+                    turtle.pop_scope();
+                    result?;
+                },
+                ControlFlow::To { name, parameters, block } => {
+                    tracing::trace!(name, "defining procedure");
+                    turtle.define(name, Definition { parameters: parameters.clone(), block: block.clone() });
+                },
+                ControlFlow::WhenKey { key, block } => {
+                    let key = as_word(key.evaluate(turtle)?).map_err(|found| found.mismatch("word"))?;
+                    tracing::trace!(key, "handling key");
+                    turtle.on_key(&key, Arc::new(block.clone()));
+                },
+                ControlFlow::Catch { tag, block } => {
+                    let tag = tag.require_word(turtle)?;
+                    tracing::trace!(tag, "executing CATCH");
+                    let result = execute_block(block, turtle);
+                    catch(&tag, result, turtle)?;
+                },
+                ControlFlow::WhenClick { block } => {
+                    tracing::trace!("handling clicks");
+                    turtle.on_click(Arc::new(block.clone()));
+                },
+                ControlFlow::EveryFrame { block } => {
+                    tracing::trace!("handling frames");
+                    turtle.on_frame(Arc::new(block.clone()));
+                },
             }
         }
         Ok(())
     }
 }
 
+/// Handles the result of executing the block of a `CATCH` with `tag`, returning the errors it does not stop.
+pub(crate) fn catch<T: TurtleOps + ?Sized>(
+    tag: &str,
+    result: Result<(), RuntimeError>,
+    turtle: &mut T,
+) -> Result<(), RuntimeError> {
+    match result {
+        Err(RuntimeError::Thrown(thrown)) if thrown.eq_ignore_ascii_case(tag) => {
+            tracing::debug!(tag, "caught throw");
+            turtle.take_error_span();
+            Ok(())
+        },
+        Err(e) if tag.eq_ignore_ascii_case("ERROR") && e.is_recoverable() => {
+            tracing::debug!(error = %e, "caught error");
+            turtle.take_error_span();
+            Ok(())
+        },
+        result => result,
+    }
+}
 
-/// Represents a control flow structure in the language, such as an `IF` statement or a `WHILE` loop.
-#[derive(Debug, Clone)]
+/// Evaluates the word naming a variable being created, checking it against the turtle's `NamePolicy`.
+fn variable_name<T: TurtleOps + ?Sized>(name: &Expression, turtle: &T) -> Result<String, RuntimeError> {
+    let name = name.require_word(turtle)?;
+    turtle.get_name_policy().check(&name)?;
+    Ok(name)
+}
+
+/// Sets the variable named by `name` to the result of applying `op` to its current value and `value`.
+fn compound_assign<T: TurtleOps + ?Sized>(
+    name: &Expression,
+    value: &Expression,
+    turtle: &mut T,
+    op: fn(Value, Value) -> Result<Value, RuntimeError>,
+) -> Result<(), RuntimeError> {
+    let name = name.require_word(turtle)?;
+    let cur = turtle.get_variable(&name)?;
+    let cur = cur.to_number(turtle.is_integer_mode()).ok_or_else(|| cur.clone().mismatch("number"))?;
+    let val = value.require_number(turtle)?;
+    turtle.add_variable(&name, op(cur, val)?)
+}
+
+/// Evaluates the condition of an `IF`, `WHILE`, `DO.WHILE`, or `DO.UNTIL`.
+pub(crate) fn eval_condition<T: TurtleOps + ?Sized>(
+    condition: &Expression,
+    turtle: &T,
+) -> Result<bool, RuntimeError> {
+    condition.require_bool(turtle)
+}
+
+/// Evaluates the variable of a `FOR` or `DOTIMES` loop, and the values it takes.
+pub(crate) fn counter<T: TurtleOps + ?Sized>(
+    flow: &ControlFlow,
+    turtle: &T,
+) -> Result<(String, Box<dyn Iterator<Item = Value>>), RuntimeError> {
+    let integer = turtle.is_integer_mode();
+    match flow {
+        ControlFlow::For { variable, start, end, step, .. } => {
+            let start = start.require_float(turtle)?;
+            let end = end.require_float(turtle)?;
+            let step = step.require_float(turtle)?;
+            let values = for_values(start, end, step, integer)?;
+            Ok((variable_name(variable, turtle)?, Box::new(values)))
+        },
+        ControlFlow::DoTimes { variable, count, .. } => {
+            let values = dotimes_values(count.require_float(turtle)?, integer);
+            Ok((variable_name(variable, turtle)?, Box::new(values)))
+        },
+        _ => unreachable!("only FOR and DOTIMES loops have a counter"),
+    }
+}
+
+// The iterators are built outside of `counter`, as closures defined in a function generic over the turtle
+// live only as long as the turtle's type, and so cannot be boxed as a `dyn Iterator`.
+
+/// Gives the values the variable of a `FOR` loop takes, counting from `start` by `step` for as long as they
+/// have not passed `end`.
+fn for_values(start: f32, end: f32, step: f32, integer: bool) -> Result<impl Iterator<Item = Value>, RuntimeError> {
+    if step == 0.0 || !step.is_finite() {
+        return Err(RuntimeError::Undefined { operation: "FOR", value: step });
+    }
+    // Each value is computed from the start, rather than by adding up steps, so it does not drift.
+    Ok((0u64..)
+        .map(move |count| start + step * count as f32)
+        .take_while(move |&value| if step > 0.0 { value <= end } else { value >= end })
+        .map(move |value| Value::number(value, integer)))
+}
+
+/// Gives the values the variable of a `DOTIMES` loop takes, counting from 0 up to, but not including, `count`.
+fn dotimes_values(count: f32, integer: bool) -> impl Iterator<Item = Value> {
+    (0..count as usize).map(move |value| Value::number(value as f32, integer))
+}
+
+/// Executes `block` in a new procedure scope, with each name in `bindings` bound to a local variable of it.
+pub(crate) fn call_block<'a, T: TurtleOps + ?Sized>(
+    block: &[ASTNode],
+    bindings: impl IntoIterator<Item = (&'a str, Value)>,
+    turtle: &mut T,
+) -> Result<(), RuntimeError> {
+    turtle.push_scope(true);
+    let result = bindings.into_iter()
+        .try_for_each(|(name, value)| {
+            turtle.add_local_variable(name)?;
+            turtle.add_variable(name, value)
+        })
+        .and_then(|()| execute_block(block, turtle));
+    turtle.pop_scope();
+    result
+}
+
+/// Executes each instruction in `block` in order, one level of nesting deeper than the caller, in a new
+/// scope for the local variables it declares.
+fn execute_block<T: TurtleOps + ?Sized>(block: &[ASTNode], turtle: &mut T) -> Result<(), RuntimeError> {
+    turtle.enter_block()?;
+    turtle.push_scope(false);
+    let result = block.iter().try_for_each(|instruction| instruction.execute(turtle));
+    turtle.pop_scope();
+    turtle.exit_block();
+    result
+}
+
+
+/// Represents a control flow structure in the language, such as an `IF` statement, a `WHILE` loop, or a `TO` definition.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("MAKE \"sum \"0 REPEAT \"4 [ ADDASSIGN \"sum REPCOUNT ]").unwrap();
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_variable("sum").unwrap().to_float(), Some(10.0));
+/// assert_eq!(turtle.get_repcount(), -1.0);
+/// 
+/// let asts = parse_content("MAKE \"sum \"0 FOR \"i 1 10 3 [ ADDASSIGN \"sum :i ] FOR \"i 5 1 \"-2 [ SUBASSIGN \"sum :i ]").unwrap();
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_variable("sum").unwrap().to_float(), Some(13.0));
+/// assert!(turtle.get_variable("i").is_err());
+/// 
+/// let asts = parse_content("MAKE \"sum \"0 DOTIMES \"i \"4 [ ADDASSIGN \"sum :i ] DO.UNTIL [ ADDASSIGN \"sum \"100 ] GT :sum \"0").unwrap();
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_variable("sum").unwrap().to_float(), Some(106.0));
+/// ```
+/// 
+/// `CATCH "ERROR` stops a recoverable error raised in its block, and the program carries on after it.
+/// `THROW "tag` stops everything up to the innermost `CATCH "tag`, such as the loops it is in.
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("MAKE \"x \"1 CATCH \"ERROR [ MAKE \"x / \"1 \"0 MAKE \"x \"2 ] FORWARD :x").unwrap();
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_y(), 49.0);
+/// assert!(turtle.get_error_span().is_none());
+/// 
+/// let source = "CATCH \"found [ REPEAT \"10 [ REPEAT \"10 [ FORWARD \"1 IF EQ YCOR \"40 [ THROW \"found ] ] ] ]";
+/// run(&parse_content(source).unwrap(), &mut turtle).unwrap();
+/// assert_eq!(turtle.get_y(), 40.0);
+/// 
+/// assert!(run(&parse_content("THROW \"missing").unwrap(), &mut turtle).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ControlFlow {
     /// Represents an `IF` statement, which executes a block of code if a condition is true.
     If {
@@ -136,11 +497,141 @@ pub enum ControlFlow {
         condition: Expression,
         block: Vec<ASTNode>,
     },
+
+    /// Represents a `DO.WHILE` loop, which executes a block of code once, then again for as long as a condition
+    /// is true.
+    DoWhile {
+        block: Vec<ASTNode>,
+        condition: Expression,
+    },
+
+    /// Represents a `DO.UNTIL` loop, which executes a block of code once, then again until a condition is true.
+    Until {
+        block: Vec<ASTNode>,
+        condition: Expression,
+    },
+
+    /// Represents a `REPEAT` loop, which executes a block of code a given number of times.
+    Repeat {
+        count: Expression,
+        block: Vec<ASTNode>,
+    },
+
+    /// Represents a `FOR` loop, which executes a block of code with a variable counting from a start value to an
+    /// end value by a step. The variable is local to the loop.
+    For {
+        variable: Expression,
+        start: Expression,
+        end: Expression,
+        step: Expression,
+        block: Vec<ASTNode>,
+    },
+
+    /// Represents a `DOTIMES` loop, which executes a block of code a given number of times with a variable
+    /// counting from 0. The variable is local to the loop.
+    DoTimes {
+        variable: Expression,
+        count: Expression,
+        block: Vec<ASTNode>,
+    },
+
+    /// Represents a `TO` definition, which defines a procedure that runs a block of code with its arguments
+    /// bound to the named parameters.
+    To {
+        name: String,
+        parameters: Vec<String>,
+        block: Vec<ASTNode>,
+    },
+
+    /// Represents a `WHENKEY` handler, which runs a block of code each time the host reports that a key is pressed.
+    WhenKey {
+        key: Expression,
+        block: Vec<ASTNode>,
+    },
+
+    /// Represents a `CATCH` block, which executes a block of code and stops a `THROW` in it with the same tag. The
+    /// tag `ERROR` also matches every recoverable `RuntimeError`, such as a division by zero.
+    Catch {
+        tag: Expression,
+        block: Vec<ASTNode>,
+    },
+
+    /// Represents a `WHENCLICK` handler, which runs a block of code each time the host reports a click on the canvas.
+    WhenClick {
+        block: Vec<ASTNode>,
+    },
+
+    /// Represents an `EVERYFRAME` handler, which runs a block of code each time the host draws a frame of an animation.
+    EveryFrame {
+        block: Vec<ASTNode>,
+    },
+}
+
+/// A procedure defined with `TO`, as stored by the `Turtle` once the definition has run.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("TO SIDE :length FORWARD :length TURN \"90 END SIDE \"10 SIDE \"20").unwrap();
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!((turtle.get_x(), turtle.get_y()), (70.0, 40.0));
+/// assert!(turtle.get_variable("length").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Definition {
+    /// The names that the arguments are bound to, in order.
+    pub parameters: Vec<String>,
+
+    /// The instructions run when the procedure is called.
+    pub block: Vec<ASTNode>,
+}
+
+impl Definition {
+    /// Runs the procedure with each parameter bound to the matching value in `args`.
+    /// 
+    /// Parameters are local variables of the call, so they hide global variables with the same name until it
+    /// returns, but are not visible to the procedures it calls.
+    /// 
+    /// Procedures may call themselves. Once the turtle's `max_call_depth` calls are in progress, another call
+    /// returns `RuntimeError::StackOverflow`.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::error::RuntimeError;
+    /// use rslogo::limits::Limits;
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("TO DOWN :n IF GT :n \"0 [ FORWARD \"1 DOWN - :n \"1 ] END DOWN \"10").unwrap();
+    /// let mut turtle = Turtle::with_limits(100, 100, Limits { max_call_depth: Some(20), ..Limits::default() });
+    /// run(&asts, &mut turtle).unwrap();
+    /// assert_eq!(turtle.get_y(), 40.0);
+    /// 
+    /// let asts = parse_content("TO FOREVER FOREVER END FOREVER").unwrap();
+    /// assert!(matches!(run(&asts, &mut turtle), Err(RuntimeError::StackOverflow(20))));
+    /// ```
+    pub fn call<T: TurtleOps + ?Sized>(&self, args: Vec<Value>, turtle: &mut T) -> Result<(), RuntimeError> {
+        turtle.enter_call()?;
+        let result = call_block(&self.block, self.parameters.iter().map(String::as_str).zip(args), turtle);
+        turtle.exit_call();
+        result
+    }
 }
 
 /// `Condition` is an enum representing the conditional expressions in a programming language.
 /// 
-/// It currently supports six variants: `Equal`, `NotEqual`, `LessThan`, `GreaterThan`, `And`, and `Or`.
+/// It currently supports fifteen variants: `Equal`, `NotEqual`, `NearlyEqual`, `LessThan`, `GreaterThan`, `LessEqual`,
+/// `GreaterEqual`, `And`, `Or`, `Not`, `Inside`, `IsNumber`, `IsWord`, `IsList`, and `IsEmpty`.
+/// 
+/// Floats are compared with the turtle's epsilon, which is 0 unless it is set with `Turtle::set_epsilon`.
 ///
 /// # Methods
 ///
@@ -149,19 +640,46 @@ pub enum ControlFlow {
 /// # Example
 ///
 /// ```
-/// use unsvg::Image;
 /// use rslogo::turtle::Turtle;
 /// use rslogo::ast::{Condition, Expression};
 /// 
-/// let mut image = Image::new(100, 100);
-/// let turtle = Turtle::new(&mut image);
+/// let turtle = Turtle::new(100, 100);
 /// 
 /// let equal_condition = Condition::Equal(Expression::Float(1.0), Expression::Float(1.0));
 /// let equal_expression = Expression::Bool(Box::new(equal_condition));
-/// let equal_val = equal_expression.to_bool(&turtle).unwrap();
+/// let equal_val = equal_expression.to_bool(&turtle).unwrap().unwrap();
 /// assert_eq!(equal_val, true);
+/// 
+/// let tenth_ten_times = (0..10).map(|_| 0.1).sum();
+/// let drifted = Condition::Equal(Expression::Float(tenth_ten_times), Expression::Float(1.0));
+/// let drifted = Expression::Bool(Box::new(drifted));
+/// assert_eq!(drifted.to_bool(&turtle).unwrap(), Some(false));
+/// 
+/// let mut turtle = Turtle::new(100, 100);
+/// turtle.set_epsilon(0.001);
+/// assert_eq!(drifted.to_bool(&turtle).unwrap(), Some(true));
+/// 
+/// let at_most = Condition::LessEqual(Expression::Float(2.0), Expression::Float(2.0));
+/// let not_at_most = Expression::Bool(Box::new(Condition::Not(Box::new(at_most))));
+/// assert_eq!(not_at_most.to_bool(&turtle).unwrap(), Some(false));
+/// 
+/// let rect = Expression::List([40.0, 40.0, 20.0, 20.0].map(Expression::Float).to_vec());
+/// let inside = Expression::Bool(Box::new(Condition::Inside(rect)));
+/// assert_eq!(inside.to_bool(&turtle).unwrap(), Some(true));
+/// 
+/// let alphabetical = Condition::LessThan(Expression::String("apple".to_string()), Expression::String("banana".to_string()));
+/// assert_eq!(Expression::Bool(Box::new(alphabetical)).to_bool(&turtle).unwrap(), Some(true));
+/// 
+/// let mixed = Condition::GreaterThan(Expression::String("apple".to_string()), Expression::Float(1.0));
+/// assert!(Expression::Bool(Box::new(mixed)).to_bool(&turtle).is_err());
+/// 
+/// let is_number = Condition::IsNumber(Expression::String("apple".to_string()));
+/// assert_eq!(Expression::Bool(Box::new(is_number)).to_bool(&turtle).unwrap(), Some(false));
+/// 
+/// let is_empty = Condition::IsEmpty(Expression::List(Vec::new()));
+/// assert_eq!(Expression::Bool(Box::new(is_empty)).to_bool(&turtle).unwrap(), Some(true));
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Condition {
     /// Represents an equality comparison between two `Expression`s.
 	Equal(Expression, Expression),
@@ -169,60 +687,206 @@ pub enum Condition {
     /// Represents a non-equality comparison between two `Expression`s.
 	NotEqual(Expression, Expression),
 
-    /// Represents a less-than comparison between two `Expression`s.
+    /// Represents a comparison between two `Expression`s that is true if they differ by at most the turtle's
+    /// epsilon, or by at most `NEARLY_EQUAL_EPSILON` if that is larger.
+	NearlyEqual(Expression, Expression),
+
+    /// Represents a less-than comparison between two `Expression`s, which are both numbers or both words.
+    /// Words are compared alphabetically, so `LT "apple "banana` is true.
 	LessThan(Expression, Expression),
 
     /// Represents a greater-than comparison between two `Expression`s.
 	GreaterThan(Expression, Expression),
 
+    /// Represents a less-than-or-equal comparison between two `Expression`s.
+	LessEqual(Expression, Expression),
+
+    /// Represents a greater-than-or-equal comparison between two `Expression`s.
+	GreaterEqual(Expression, Expression),
+
     /// Represents a logical AND operation between two `Condition`s.
 	And(Box<Condition>, Box<Condition>),
 
     /// Represents a logical OR operation between two `Condition`s.
 	Or(Box<Condition>, Box<Condition>),
+
+    /// Represents a logical NOT operation on a `Condition`.
+	Not(Box<Condition>),
+
+    /// Represents a test of whether the turtle is inside the rectangle given by an `Expression` for the list of
+    /// its x, y, width, and height, including its edges.
+	Inside(Expression),
+
+    /// Represents a test of whether an `Expression` is a number.
+	IsNumber(Expression),
+
+    /// Represents a test of whether an `Expression` is a word, which numbers are too.
+	IsWord(Expression),
+
+    /// Represents a test of whether an `Expression` is a list.
+	IsList(Expression),
+
+    /// Represents a test of whether an `Expression` is the empty list or the empty word.
+	IsEmpty(Expression),
 }
 
+/// The smallest tolerance `NEARLYEQ` compares with, so it is useful even when the turtle's epsilon is 0.
+pub const NEARLY_EQUAL_EPSILON: f32 = 1e-4;
+
 impl Condition {
-    fn eval(&self, turtle: &Turtle) -> bool {
-        match self {
+    fn eval<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<bool, RuntimeError> {
+        let result = match self {
             Condition::Equal(expr1, expr2) => {
-                let float = is_option_eq(expr1.to_float(turtle), expr2.to_float(turtle));
-                let bool = is_option_eq(expr1.to_bool(turtle), expr2.to_bool(turtle));
-                let string = is_option_eq(expr1.to_string(turtle), expr2.to_string(turtle));
+                let (val1, val2) = (expr1.evaluate(turtle)?, expr2.evaluate(turtle)?);
+                if let Some((int1, int2)) = to_integers(&val1, &val2, turtle) {
+                    return Ok(int1 == int2);
+                }
+                let float = is_option_near(val1.to_float(), val2.to_float(), turtle.get_epsilon());
+                let bool = is_option_eq(val1.to_bool(), val2.to_bool());
+                let string = is_option_eq(val1.as_string(), val2.as_string());
                 float.is_true() || bool.is_true() || string.is_true()
             }
             Condition::NotEqual(expr1, expr2) => {
-                let float = is_option_eq(expr1.to_float(turtle), expr2.to_float(turtle));
-                let bool = is_option_eq(expr1.to_bool(turtle), expr2.to_bool(turtle));
-                let string = is_option_eq(expr1.to_string(turtle), expr2.to_string(turtle));
+                let (val1, val2) = (expr1.evaluate(turtle)?, expr2.evaluate(turtle)?);
+                if let Some((int1, int2)) = to_integers(&val1, &val2, turtle) {
+                    return Ok(int1 != int2);
+                }
+                let float = is_option_near(val1.to_float(), val2.to_float(), turtle.get_epsilon());
+                let bool = is_option_eq(val1.to_bool(), val2.to_bool());
+                let string = is_option_eq(val1.as_string(), val2.as_string());
                 float.is_false() && bool.is_false() && string.is_false()
             }
-            Condition::LessThan(expr1, expr2) => {
-                let val1 = expr1.to_float(turtle).expect("Can only compare floats");
-                let val2 = expr2.to_float(turtle).expect("Can only compare floats");
-                val1 < val2
-            }
-            Condition::GreaterThan(expr1, expr2) => {
-                let val1 = expr1.to_float(turtle).expect("Can only compare floats");
-                let val2 = expr2.to_float(turtle).expect("Can only compare floats");
-                val1 > val2
+            Condition::LessThan(expr1, expr2) => compare(expr1, expr2, turtle)? == Some(Ordering::Less),
+            Condition::GreaterThan(expr1, expr2) => compare(expr1, expr2, turtle)? == Some(Ordering::Greater),
+            Condition::LessEqual(expr1, expr2) => {
+                matches!(compare(expr1, expr2, turtle)?, Some(Ordering::Less | Ordering::Equal))
+            },
+            Condition::GreaterEqual(expr1, expr2) => {
+                matches!(compare(expr1, expr2, turtle)?, Some(Ordering::Greater | Ordering::Equal))
+            },
+            Condition::NearlyEqual(expr1, expr2) => {
+                let val1 = expr1.require_float(turtle)?;
+                let val2 = expr2.require_float(turtle)?;
+                (val1 - val2).abs() <= turtle.get_epsilon().max(NEARLY_EQUAL_EPSILON)
             }
             Condition::And(cond1, cond2) => {
-                let val1 = cond1.eval(turtle);
-                let val2 = cond2.eval(turtle);
+                let val1 = cond1.eval(turtle)?;
+                let val2 = cond2.eval(turtle)?;
                 val1 && val2
             }
             Condition::Or(cond1, cond2) => {
-                let val1 = cond1.eval(turtle);
-                let val2 = cond2.eval(turtle);
+                let val1 = cond1.eval(turtle)?;
+                let val2 = cond2.eval(turtle)?;
                 val1 || val2
             }
+            Condition::Not(cond) => !cond.eval(turtle)?,
+            Condition::Inside(rect) => {
+                let rect = rect.evaluate(turtle)?;
+                let bounds = match &rect {
+                    Value::List(items) => items.iter().map(Value::to_float).collect::<Option<Vec<f32>>>(),
+                    _ => None,
+                };
+                let Some(&[x, y, width, height]) = bounds.as_deref() else {
+                    return Err(rect.mismatch("list of x, y, width, and height"));
+                };
+                (x..=x + width).contains(&turtle.get_x()) && (y..=y + height).contains(&turtle.get_y())
+            },
+            Condition::IsNumber(expr) => expr.evaluate(turtle)?.to_float().is_some(),
+            Condition::IsWord(expr) => !matches!(expr.evaluate(turtle)?, Value::List(_)),
+            Condition::IsList(expr) => matches!(expr.evaluate(turtle)?, Value::List(_)),
+            Condition::IsEmpty(expr) => match expr.evaluate(turtle)? {
+                Value::List(items) => items.is_empty(),
+                Value::String(word) => word.is_empty(),
+                _ => false,
+            },
+        };
+        Ok(result)
+    }
+}
+
+/// Evaluates both sides of an ordering comparison and compares them, returning `None` if either is NaN.
+/// 
+/// Numbers are compared exactly in integer mode, and otherwise are equal if they differ by at most the turtle's
+/// epsilon. Words that are not numbers are compared alphabetically, by their characters. Any other pair of
+/// values, such as a number and a word, cannot be compared.
+fn compare<T: TurtleOps + ?Sized>(
+    expr1: &Expression,
+    expr2: &Expression,
+    turtle: &T,
+) -> Result<Option<Ordering>, RuntimeError> {
+    let (val1, val2) = (expr1.evaluate(turtle)?, expr2.evaluate(turtle)?);
+    if let Some((int1, int2)) = to_integers(&val1, &val2, turtle) {
+        return Ok(Some(int1.cmp(&int2)));
+    }
+    if let (Value::String(word1), Value::String(word2)) = (&val1, &val2) {
+        return Ok(Some(word1.cmp(word2)));
+    }
+    if let (Some(float1), Some(float2)) = (val1.to_float(), val2.to_float()) {
+        if (float1 - float2).abs() <= turtle.get_epsilon() {
+            return Ok(Some(Ordering::Equal));
         }
+        return Ok(float1.partial_cmp(&float2));
+    }
+    let orderable = |value: &Value| value.to_float().is_some() || matches!(value, Value::String(_));
+    match (orderable(&val1), orderable(&val2)) {
+        (false, _) => Err(val1.mismatch("number or word")),
+        (_, false) => Err(val2.mismatch("number or word")),
+        (true, true) => Err(RuntimeError::Incomparable(val1, val2)),
     }
 }
 
+/// Gets both sides of a comparison as exact integers, if integer mode is enabled and both are whole numbers.
+fn to_integers<T: TurtleOps + ?Sized>(val1: &Value, val2: &Value, turtle: &T) -> Option<(i64, i64)> {
+    if !turtle.is_integer_mode() {
+        return None;
+    }
+    match (val1.to_number(true)?, val2.to_number(true)?) {
+        (Value::Int(int1), Value::Int(int2)) => Some((int1, int2)),
+        _ => None,
+    }
+}
+
+/// Gets an evaluated value as a float, returning a `TypeMismatch` error if it is not a number.
+fn require_float(value: Value) -> Result<f32, RuntimeError> {
+    value.to_float().ok_or_else(|| value.mismatch("number"))
+}
+
 /// Represents a single procedure in the language, such as `FORWARD 10` or `MAKE "x 10`.
-#[derive(Debug, Clone)]
+/// 
+/// # Example
+/// 
+/// The variable set by `MAKE` or a compound assignment can be named by any expression that evaluates to a word.
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("MAKE \"name \"size MAKE :name \"10 MULASSIGN :name \"3 SUBASSIGN \"size \"5 FORWARD :size").unwrap();
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_y(), 25.0);
+/// ```
+/// 
+/// `LOCAL` and `LOCALMAKE` declare a variable of the innermost block or procedure, which hides any variable with
+/// the same name until the block or procedure ends. `MAKE` sets the innermost variable with the name that can be
+/// seen, or else a global variable. A procedure cannot see the local variables of the procedure that called it.
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let source = "MAKE \"x \"1 TO SHOW LOCALMAKE \"x \"5 IF EQ :x \"5 [ LOCAL \"y MAKE \"y \"2 MAKE \"x \"7 ] SETX :x END SHOW";
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&parse_content(source).unwrap(), &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_x(), 7.0);
+/// assert_eq!(turtle.get_variable("x").unwrap().to_float(), Some(1.0));
+/// assert!(turtle.get_variable("y").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Procedure {
     /// Lifts the pen up, so the turtle does not draw.
     PenUp,
@@ -230,6 +894,12 @@ pub enum Procedure {
     /// Puts the pen down, so the turtle draws.
     PenDown,
 
+    /// Saves the turtle's position, heading, and pen configuration onto its state stack.
+    PushState,
+
+    /// Restores the position, heading, and pen configuration most recently saved with `PushState`.
+    PopState,
+
     /// Moves the turtle forward by a given distance.
     Forward(Expression),
 
@@ -242,9 +912,22 @@ pub enum Procedure {
     /// Turns the turtle right by a given angle.
     Right(Expression),
 
+    /// Moves the turtle a given distance towards a given absolute angle, without changing its heading.
+    MovePolar(Expression, Expression),
+
+    /// Plays a tone of a given frequency, in hertz, for a given duration, in milliseconds, on the turtle's audio sink.
+    Toot(Expression, Expression),
+
+    /// Represents the `FILLAT` procedure, which fills the region enclosed by lines around the given coordinates
+    /// with the fill color.
+    FillAt(Expression, Expression),
+
     /// Sets the pen color to a given value.
     SetPenColor(Expression),
 
+    /// Sets the fill color to a given value, without changing the pen color.
+    SetFillColor(Expression),
+
     /// Turns the turtle to a given angle.
     Turn(Expression),
 
@@ -257,11 +940,57 @@ pub enum Procedure {
     /// Sets the y-coordinate of the turtle to a given value.
     SetY(Expression),
 
-    /// Creates a new variable with a given name and value.
+    /// Seeds the turtle's random number generator, so the numbers `RANDOM` returns can be reproduced.
+    SetSeed(Expression),
+
+    /// Sets the speed, in pixels per second, that animated exporters draw the following lines at.
+    SetSpeed(Expression),
+
+    /// Pauses for a given number of ticks, 60 to a second, on the turtle's clock, and records the pause on the
+    /// next line drawn for animated exporters.
+    Wait(Expression),
+
+    /// Sets the width, in pixels, of the lines drawn from now on.
+    SetPenSize(Expression),
+
+    /// Stops the innermost `CATCH` with the tag the expression evaluates to, and everything running inside it.
+    Throw(Expression),
+
+    /// Remembers the turtle's position and heading under a given name.
+    Mark(String),
+
+    /// Moves the turtle, without drawing, to the position and heading remembered under a given name.
+    Goto(String),
+
+    /// Saves a copy of the lines drawn so far under a given name.
+    Snapshot(String),
+
+    /// Replaces the lines drawn so far with the copy saved under a given name.
+    RestoreImage(String),
+
+    /// Creates a new variable with a given value, named by the word the first expression evaluates to.
     Make(Expression, Expression),
 
-    /// Adds a value to an existing variable.
+    /// Declares a local variable of the innermost block or procedure, named by the word the expression evaluates to.
+    Local(Expression),
+
+    /// Declares a local variable, named by the word the first expression evaluates to, with a given value.
+    LocalMake(Expression, Expression),
+
+    /// Adds a value to an existing variable, named by the word the first expression evaluates to.
     AddAssign(Expression, Expression),
+
+    /// Subtracts a value from an existing variable, named by the word the first expression evaluates to.
+    SubAssign(Expression, Expression),
+
+    /// Multiplies an existing variable by a value, named by the word the first expression evaluates to.
+    MulAssign(Expression, Expression),
+
+    /// Divides an existing variable by a value, named by the word the first expression evaluates to.
+    DivAssign(Expression, Expression),
+
+    /// Runs a procedure defined with `TO`, or else a command registered in the turtle's `Primitives`, with the given arguments.
+    Call(String, Vec<Expression>),
 }
 
 
@@ -269,12 +998,31 @@ pub enum Procedure {
 /// 
 /// # Methods
 /// 
-/// - `to_float` - Converts the expression to a float, if possible.
-/// - `to_string` - Converts the expression to a string, if possible.
-/// - `to_bool` - Converts the expression to a boolean, if possible.
-/// - `eval_math` - Evaluates the math operation in the expression and returns the result.
+/// - `evaluate` - Evaluates the expression into a `Value`.
+/// - `to_float` - Evaluates the expression to a float, if it is a number.
+/// - `to_string` - Evaluates the expression to a string, if it is a word that is not a number.
+/// - `to_bool` - Evaluates the expression to a boolean, if it is a condition.
+/// - `to_number` - Evaluates the expression to an `Int` or `Float`, if it is a number.
+/// 
+/// # Example
+/// 
+/// In integer mode, whole numbers stay exact where an `f32` would have drifted.
+/// 
+/// ```
+/// use rslogo::ast::{Expression, Math};
+/// use rslogo::turtle::Turtle;
+/// use rslogo::value::Value;
+/// 
+/// let mut turtle = Turtle::new(100, 100);
+/// let sum = Expression::Math(Box::new(Math::Add(Expression::Float(16777216.0), Expression::Float(1.0))));
+/// assert_eq!(sum.evaluate(&turtle).unwrap(), Value::Float(16777216.0));
 /// 
+/// turtle.set_integer_mode(true);
+/// assert_eq!(sum.evaluate(&turtle).unwrap(), Value::Int(16777217));
+/// ```
 /// 
+/// Floats are compared and hashed by value, except that every NaN is equal to every other, so expressions can
+/// be used as keys of a `HashMap`.
 #[derive(Debug, Clone)]
 pub enum Expression {
     /// Represents a floating point number.
@@ -294,123 +1042,262 @@ pub enum Expression {
 
     /// Represents a boolean condition.
 	Bool(Box<Condition>),
+
+    /// Represents a query registered in the turtle's `Primitives`, with its arguments.
+    Call(String, Vec<Expression>),
+
+    /// Represents a list of values, such as `[1 2 3]`.
+    List(Vec<Expression>),
+
+    /// Represents an operation that takes apart a list or word, such as `FIRST :list`.
+    ListOp(Box<ListOp>),
+
+    /// Represents `READWORD`, which reads the next word from the turtle's input source.
+    ReadWord,
+
+    /// Represents a math expression that appears more than once in an instruction, as rewritten by the
+    /// optimizer. Its first occurrence evaluates it, and the others reuse that value.
+    Shared(Box<Shared>),
 }
 
-impl Expression {
-    pub fn to_float(&self, turtle: &Turtle) -> Option<f32> {
+/// An occurrence of a math expression shared between the arguments of an instruction, created by
+/// `optimizer::eliminate_common_subexpressions`.
+///
+/// Every occurrence of the expression has the same slot, numbered from 0 within the instruction. The value is
+/// kept in that slot on the turtle, which forgets every slot as each instruction starts, so it is only reused
+/// within one run of one instruction. The first occurrence stores it, as the arguments of an instruction are
+/// evaluated in order, and the rest read it back, evaluating the expression themselves if it was skipped.
+#[derive(Debug, Clone)]
+pub struct Shared {
+    /// The expression that is shared.
+    pub expression: Expression,
+
+    /// Whether this is the first occurrence, which evaluates the expression rather than reusing its value.
+    pub first: bool,
+
+    /// The slot the value is kept in while the instruction runs.
+    pub slot: usize,
+}
+
+impl Shared {
+    /// Creates the first occurrence of `expression`, keeping its value in `slot`.
+    pub(crate) fn first(expression: Expression, slot: usize) -> Shared {
+        Shared { expression, first: true, slot }
+    }
+
+    /// Creates a later occurrence of the same expression, which reuses the value of the first.
+    pub(crate) fn repeat(&self) -> Shared {
+        Shared { first: false, ..self.clone() }
+    }
+
+    fn evaluate<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Value, RuntimeError> {
+        if !self.first {
+            if let Some(value) = turtle.shared_value(self.slot) {
+                return Ok(value);
+            }
+        }
+        let value = self.expression.evaluate(turtle)?;
+        turtle.set_shared_value(self.slot, value.clone());
+        Ok(value)
+    }
+}
+
+/// The bits of `value`, with `-0.0` as `0.0` and every NaN as the same NaN, so floats that are equal as
+/// expressions have the same bits.
+fn canonical_bits(value: f32) -> u32 {
+    if value.is_nan() {
+        f32::NAN.to_bits()
+    } else if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expression::Float(a), Expression::Float(b)) => canonical_bits(*a) == canonical_bits(*b),
+            (Expression::Query(a), Expression::Query(b)) => a == b,
+            (Expression::Variable(a), Expression::Variable(b)) => a == b,
+            (Expression::String(a), Expression::String(b)) => a == b,
+            (Expression::Math(a), Expression::Math(b)) => a == b,
+            (Expression::Bool(a), Expression::Bool(b)) => a == b,
+            (Expression::Call(a, a_args), Expression::Call(b, b_args)) => a == b && a_args == b_args,
+            (Expression::List(a), Expression::List(b)) => a == b,
+            (Expression::ListOp(a), Expression::ListOp(b)) => a == b,
+            (Expression::ReadWord, Expression::ReadWord) => true,
+            (Expression::Shared(a), Expression::Shared(b)) => {
+                a.expression == b.expression && a.first == b.first && a.slot == b.slot
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Expression {}
+
+impl Hash for Expression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
         match self {
-            Expression::Float(val) => Some(*val),
-            Expression::Variable(var) => Some(turtle.get_variable(var).to_float(turtle)?),
-            Expression::Math(_) => Some(self.eval_math(turtle).to_float(turtle)?),
+            Expression::Float(value) => canonical_bits(*value).hash(state),
+            Expression::Query(query) => query.hash(state),
+            Expression::Variable(name) | Expression::String(name) => name.hash(state),
+            Expression::Math(math) => math.hash(state),
+            Expression::Bool(condition) => condition.hash(state),
+            Expression::Call(name, args) => {
+                name.hash(state);
+                args.hash(state);
+            },
+            Expression::List(items) => items.hash(state),
+            Expression::ListOp(op) => op.hash(state),
+            Expression::ReadWord => {},
+            Expression::Shared(shared) => {
+                shared.expression.hash(state);
+                shared.first.hash(state);
+                shared.slot.hash(state);
+            },
+        }
+    }
+}
+
+impl Expression {
+    /// Evaluates the expression into the value it stands for, reading variables, queries, and input as they are now.
+    pub fn evaluate<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Value, RuntimeError> {
+        let value = match self {
+            Expression::Float(val) => Value::Float(*val),
+            Expression::String(val) => Value::String(val.clone()),
+            Expression::Variable(var) => turtle.get_variable(var)?.clone(),
             Expression::Query(query) => {
                 let float = match query {
                     Query::XCOR => turtle.get_x(),
                     Query::YCOR => turtle.get_y(),
                     Query::COLOR => turtle.get_pen_color(),
+                    Query::FILLCOLOR => turtle.get_fill_color(),
+                    Query::SCREENWIDTH => turtle.get_screen_width(),
+                    Query::SCREENHEIGHT => turtle.get_screen_height(),
                     Query::HEADING => turtle.get_heading(),
+                    Query::REPCOUNT => turtle.get_repcount(),
+                    Query::PixelColor(x, y) => {
+                        turtle.get_pixel_color(x.require_float(turtle)?, y.require_float(turtle)?)
+                    },
+                    Query::Random(max) => {
+                        let max = max.require_float(turtle)?.floor();
+                        if max < 1.0 {
+                            return Err(RuntimeError::InvalidRandomRange(max));
+                        }
+                        (turtle.next_random() * max).floor()
+                    },
                 };
-                Some(float)
+                Value::Float(float)
             },
-            _ => None,
-        }
+            Expression::Math(math) => math.eval(turtle)?,
+            Expression::Bool(condition) => Value::Bool(condition.eval(turtle)?),
+            Expression::Call(name, args) => {
+                let args = args.iter()
+                    .map(|arg| arg.require_float(turtle))
+                    .collect::<Result<Vec<f32>, RuntimeError>>()?;
+                match turtle.evaluate_primitive(name, &args)? {
+                    Some(val) => Value::Float(val),
+                    None => return Err(Value::String(name.clone()).mismatch("number")),
+                }
+            },
+            Expression::List(items) => Value::List(
+                items.iter().map(|item| item.evaluate(turtle)).collect::<Result<_, _>>()?,
+            ),
+            Expression::ListOp(op) => op.eval(turtle)?,
+            Expression::ReadWord => turtle.read_word()?,
+            Expression::Shared(shared) => shared.evaluate(turtle)?,
+        };
+        Ok(value)
     }
 
-    pub fn to_string(&self, turtle: &Turtle) -> Option<String> {
-        match self {
-            Expression::String(val) => Some(val.clone()),
-            Expression::Variable(var) => Some(turtle.get_variable(var).to_string(turtle)?),
-            _ => None,
-        }
+    pub fn to_float<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Option<f32>, RuntimeError> {
+        Ok(self.evaluate(turtle)?.to_float())
     }
 
-    pub fn to_bool(&self, turtle: &Turtle) -> Option<bool> {
-        match self {
-            Expression::Bool(val) => Some(val.eval(turtle)),
-            Expression::Variable(var) => turtle.get_variable(var).to_bool(turtle),
-            _ => None,
-        }
+    pub fn to_string<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Option<String>, RuntimeError> {
+        Ok(self.evaluate(turtle)?.as_string().map(str::to_string))
     }
 
-    pub fn eval_math(&self, turtle: &Turtle) -> Expression {
-        match self {
-            Expression::Math(math) => {
-                match math.as_ref() {
-                    Math::Add(expr1, expr2) => {
-                        let val1 = expr1.eval_math(turtle);
-                        let val2 = expr2.eval_math(turtle);
-                        val1 + val2
-                    },
-                    Math::Sub(expr1, expr2) => {
-                        let val1 = expr1.eval_math(turtle);
-                        let val2 = expr2.eval_math(turtle);
-                        val1 - val2
-                    },
-                    Math::Mul(expr1, expr2) => {
-                        let val1 = expr1.eval_math(turtle);
-                        let val2 = expr2.eval_math(turtle);
-                        val1 * val2
-                    }
-                    Math::Div(expr1, expr2) => {
-                        let val1 = expr1.eval_math(turtle);
-                        let val2 = expr2.eval_math(turtle);
-                        val1 / val2
-                    }
-                }
-            },
-            _ => Expression::Float(self.to_float(turtle).expect("Cannot perform math on this type")),
-        }
+    pub fn to_bool<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Option<bool>, RuntimeError> {
+        Ok(self.evaluate(turtle)?.to_bool())
     }
-}
 
-/// Operator overloading for math operations on `Expression`.
-/// Note: Only supports math operations on `Expression::Float`.
-impl std::ops::Add for Expression {
-    type Output = Self;
-    fn add(self, other: Self) -> Self {
-        match (self, other) {
-            (Expression::Float(val1), Expression::Float(val2)) => {Expression::Float(val1 + val2)},
-            _ => panic!("Can only add expressions that are floats"),
-        }
+    pub fn to_number<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Option<Value>, RuntimeError> {
+        Ok(self.evaluate(turtle)?.to_number(turtle.is_integer_mode()))
     }
-}
 
-impl std::ops::Sub for Expression {
-    type Output = Self;
-    fn sub(self, other: Self) -> Self {
-        match (self, other) {
-            (Expression::Float(val1), Expression::Float(val2)) => Expression::Float(val1 - val2),
-            _ => panic!("Can only subtract expressions that are floats"),
-        }
+    /// Evaluates the expression to a float, returning a `TypeMismatch` error if it is not a number.
+    pub(crate) fn require_float<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<f32, RuntimeError> {
+        require_float(self.evaluate(turtle)?)
     }
-}
 
-impl std::ops::Mul for Expression {
-    type Output = Self;
-    fn mul(self, other: Self) -> Self {
-        match (self, other) {
-            (Expression::Float(val1), Expression::Float(val2)) => Expression::Float(val1 * val2),
-            _ => panic!("Can only multiply expressions that are floats"),
+    /// Evaluates the expression to an `Int` or `Float`, returning a `TypeMismatch` error if it is not a number.
+    pub(crate) fn require_number<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(turtle)?;
+        value.to_number(turtle.is_integer_mode()).ok_or_else(|| value.mismatch("number"))
+    }
+
+    /// Evaluates the expression to a string, returning a `TypeMismatch` error if it is not a word.
+    pub(crate) fn require_word<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<String, RuntimeError> {
+        match self.evaluate(turtle)? {
+            Value::String(word) => Ok(word),
+            value => Err(value.mismatch("word")),
         }
     }
-}
 
-impl std::ops::Div for Expression {
-    type Output = Self;
-    fn div(self, other: Self) -> Self {
-        match (self, other) {
-            (Expression::Float(val1), Expression::Float(val2)) => {
-                if val2 == 0.0 {
-                    panic!("Division by zero")
-                }
-                Expression::Float(val1 / val2)
-            },
-            _ => panic!("Can only divide expressions that are floats"),
+    /// Evaluates the expression to the index of a color, looking up words in `COLOR_NAMES`.
+    pub(crate) fn require_color<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<f32, RuntimeError> {
+        match self.evaluate(turtle)? {
+            Value::String(name) => color_index(&name).map(|index| index as f32).ok_or(RuntimeError::UnknownColor(name)),
+            value => value.to_float().ok_or_else(|| value.mismatch("color")),
         }
     }
+
+    /// Evaluates the expression to a boolean, returning a `TypeMismatch` error if it is not a condition.
+    pub(crate) fn require_bool<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<bool, RuntimeError> {
+        let value = self.evaluate(turtle)?;
+        value.to_bool().ok_or_else(|| value.mismatch("boolean"))
+    }
 }
 
 /// Represents a math operation in the language, such as `+ 1 2` or `* 3 4`.
-#[derive(Debug, Clone)]
+/// 
+/// `SIN`, `COS`, and `TAN` take angles in degrees, like `TURN` and `SETHEADING`. `MOD` (also written `%`)
+/// gives a remainder with the sign of the divisor, so `MOD "-1 "3` is 2, while `REMAINDER` gives one with
+/// the sign of the dividend, so `REMAINDER "-1 "3` is -1. `INT` drops the fractional part of a number, so
+/// the result of a division can be used as a loop count or color index.
+/// 
+/// `CLAMP`, `LERP`, and `MAP-RANGE` are the helpers generative programs reach for most, built in so they
+/// do not have to be written as procedures. `CLAMP "12 "0 "10` is 10, `LERP "0 "10 "0.25` is 2.5, and
+/// `MAP-RANGE "5 "0 "10 "100 "200` is 150. `MAP-RANGE` extrapolates values outside the first range.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("SETX + \"50 * \"30 COS \"60 SETY POWER SQRT \"9 \"2 SETHEADING % \"-1 \"3 SETPENCOLOR INT / \"9 \"2").unwrap();
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert!((turtle.get_x() - 65.0).abs() < 1e-4);
+/// assert_eq!(turtle.get_y(), 9.0);
+/// assert_eq!(turtle.get_heading(), 2.0);
+/// assert_eq!(turtle.get_pen_color(), 4.0);
+/// 
+/// let asts = parse_content("SETX CLAMP \"120 \"0 \"100 SETY LERP \"10 \"20 \"0.5 SETHEADING MAP-RANGE \"5 \"0 \"10 \"0 \"360").unwrap();
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_x(), 100.0);
+/// assert_eq!(turtle.get_y(), 15.0);
+/// assert_eq!(turtle.get_heading(), 180.0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Math {
     /// Adds two expressions together.
 	Add(Expression, Expression),
@@ -423,10 +1310,303 @@ pub enum Math {
 
     /// Divides one expression by another.
 	Div(Expression, Expression),
+
+    /// Finds the remainder of dividing one expression by another, with the sign of the divisor.
+	Mod(Expression, Expression),
+
+    /// Finds the remainder of dividing one expression by another, with the sign of the dividend.
+	Remainder(Expression, Expression),
+
+    /// Raises one expression to the power of another.
+	Power(Expression, Expression),
+
+    /// Takes the sine of an angle in degrees.
+	Sin(Expression),
+
+    /// Takes the cosine of an angle in degrees.
+	Cos(Expression),
+
+    /// Takes the tangent of an angle in degrees.
+	Tan(Expression),
+
+    /// Takes the square root of an expression.
+	Sqrt(Expression),
+
+    /// Drops the fractional part of an expression, rounding towards zero.
+	Int(Expression),
+
+    /// Rounds an expression to the nearest whole number, rounding halves away from zero.
+	Round(Expression),
+
+    /// Takes the absolute value of an expression.
+	Abs(Expression),
+
+    /// Limits a value to lie between a low and a high bound.
+	Clamp(Expression, Expression, Expression),
+
+    /// Interpolates linearly from one value to another by a fraction, where 0 gives the first value and 1 the second.
+	Lerp(Expression, Expression, Expression),
+
+    /// Maps a value from one range onto another: the value, then the low and high of the range it is in,
+    /// then the low and high of the range to map it onto.
+	MapRange(Expression, Expression, Expression, Expression, Expression),
+}
+
+impl Math {
+    /// Evaluates the operation into the number it gives.
+    pub fn eval<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Value, RuntimeError> {
+        let result = match self {
+            Math::Add(expr1, expr2) => (expr1.require_number(turtle)? + expr2.require_number(turtle)?)?,
+            Math::Sub(expr1, expr2) => (expr1.require_number(turtle)? - expr2.require_number(turtle)?)?,
+            Math::Mul(expr1, expr2) => (expr1.require_number(turtle)? * expr2.require_number(turtle)?)?,
+            Math::Div(expr1, expr2) => (expr1.require_number(turtle)? / expr2.require_number(turtle)?)?,
+            Math::Mod(expr1, expr2) => remainder(expr1.require_number(turtle)?, expr2.require_number(turtle)?, true)?,
+            Math::Remainder(expr1, expr2) => {
+                remainder(expr1.require_number(turtle)?, expr2.require_number(turtle)?, false)?
+            },
+            Math::Power(base, exponent) => power(base.require_number(turtle)?, exponent.require_number(turtle)?)?,
+            Math::Sin(angle) => Value::Float(angle.require_float(turtle)?.to_radians().sin()),
+            Math::Cos(angle) => Value::Float(angle.require_float(turtle)?.to_radians().cos()),
+            Math::Tan(angle) => Value::Float(angle.require_float(turtle)?.to_radians().tan()),
+            Math::Sqrt(expr) => {
+                let val = expr.require_float(turtle)?;
+                if val < 0.0 {
+                    return Err(RuntimeError::Undefined { operation: "SQRT", value: val });
+                }
+                Value::Float(val.sqrt())
+            },
+            Math::Int(expr) => match expr.require_number(turtle)? {
+                Value::Float(val) => Value::Float(val.trunc()),
+                val => val,
+            },
+            Math::Round(expr) => match expr.require_number(turtle)? {
+                Value::Float(val) => Value::Float(val.round()),
+                val => val,
+            },
+            Math::Abs(expr) => match expr.require_number(turtle)? {
+                Value::Float(val) => Value::Float(val.abs()),
+                Value::Int(val) => match val.checked_abs() {
+                    Some(val) => Value::Int(val),
+                    None => Value::Float((val as f32).abs()),
+                },
+                val => val,
+            },
+            Math::Clamp(value, low, high) => {
+                let value = value.require_float(turtle)?;
+                let low = low.require_float(turtle)?;
+                let high = high.require_float(turtle)?;
+                // Unlike `f32::clamp`, this does not panic when the bounds are the wrong way round.
+                Value::Float(value.max(low).min(high))
+            },
+            Math::Lerp(from, to, fraction) => {
+                let from = from.require_float(turtle)?;
+                let to = to.require_float(turtle)?;
+                let fraction = fraction.require_float(turtle)?;
+                Value::Float(from + (to - from) * fraction)
+            },
+            Math::MapRange(value, in_low, in_high, out_low, out_high) => {
+                let value = value.require_float(turtle)?;
+                let in_low = in_low.require_float(turtle)?;
+                let in_high = in_high.require_float(turtle)?;
+                let out_low = out_low.require_float(turtle)?;
+                let out_high = out_high.require_float(turtle)?;
+                if in_low == in_high {
+                    return Err(RuntimeError::DivisionByZero);
+                }
+                Value::Float(out_low + (value - in_low) * (out_high - out_low) / (in_high - in_low))
+            },
+        };
+        Ok(result)
+    }
+}
+
+/// Represents an operation that takes apart a list, or a word as a list of its characters, or puts lists and
+/// words together.
+/// 
+/// Lists are written in square brackets, such as `[1 2 3]`. Their items are evaluated when the list is, and bare
+/// words in them are taken as words, so `[a b]` is the same as `["a "b]`. Items are counted from 1, and taking an
+/// item that is not there, such as the `FIRST` of an empty list, is an error.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("MAKE \"sides [3 4 6] SETX FIRST :sides SETY ITEM 2 :sides SETHEADING COUNT BUTFIRST :sides").unwrap();
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_x(), 3.0);
+/// assert_eq!(turtle.get_y(), 4.0);
+/// assert_eq!(turtle.get_heading(), 2.0);
+/// 
+/// let asts = parse_content("SETX LAST \"1234 SETY ITEM 4 [1 2 3]").unwrap();
+/// let error = run(&asts, &mut turtle).unwrap_err();
+/// assert_eq!(turtle.get_x(), 4.0);
+/// assert_eq!(error.to_string(), "ITEM 4 is out of range for 3 items");
+/// ```
+/// 
+/// `WORD` joins two words, so variable names can be built while the program runs, and `SENTENCE` joins words and
+/// lists into one list. Numbers are words too, so `WORD "var 1` is `"var1`.
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run;
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("MAKE \"i \"2 MAKE WORD \"side :i \"30 SETX :side2 SETY COUNT SENTENCE [1 2] \"3").unwrap();
+/// let mut turtle = Turtle::new(100, 100);
+/// run(&asts, &mut turtle).unwrap();
+/// 
+/// assert_eq!(turtle.get_x(), 30.0);
+/// assert_eq!(turtle.get_y(), 3.0);
+/// 
+/// let asts = parse_content("MAKE \"a CHAR \"65 MAKE WORD :a \"b \"1 SETX :Ab").unwrap();
+/// run(&asts, &mut turtle).unwrap();
+/// assert_eq!(turtle.get_x(), 1.0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ListOp {
+    /// Takes the first item.
+    First(Expression),
+
+    /// Takes every item but the first.
+    ButFirst(Expression),
+
+    /// Takes the last item.
+    Last(Expression),
+
+    /// Takes the item at an index, counting from 1.
+    Item(Expression, Expression),
+
+    /// Counts the items.
+    Count(Expression),
+
+    /// Joins two words into one.
+    Word(Expression, Expression),
+
+    /// Joins words and lists into one list, with the items of each list rather than the list itself.
+    Sentence(Expression, Expression),
+
+    /// Takes the character with a Unicode code point.
+    Char(Expression),
+}
+
+impl ListOp {
+    /// Evaluates the operation into the item or list it takes.
+    pub fn eval<T: TurtleOps + ?Sized>(&self, turtle: &T) -> Result<Value, RuntimeError> {
+        let (operation, list) = match self {
+            ListOp::Count(list) => return Ok(Value::Float(Sequence::new(list, turtle)?.count() as f32)),
+            ListOp::Word(first, second) => {
+                let mismatch = |found: Value| found.mismatch("word");
+                let first = as_word(first.evaluate(turtle)?).map_err(mismatch)?;
+                let second = as_word(second.evaluate(turtle)?).map_err(mismatch)?;
+                return Ok(word(first + &second));
+            },
+            ListOp::Sentence(first, second) => {
+                let mut items = Vec::new();
+                for value in [first.evaluate(turtle)?, second.evaluate(turtle)?] {
+                    match value {
+                        Value::List(list) => items.extend(list),
+                        value => items.push(value),
+                    }
+                }
+                return Ok(Value::List(items));
+            },
+            ListOp::Char(code) => {
+                let value = code.require_float(turtle)?;
+                return match char::from_u32(value as u32) {
+                    Some(c) if value.fract() == 0.0 && value >= 0.0 => Ok(word(c.to_string())),
+                    _ => Err(RuntimeError::Undefined { operation: "CHAR", value }),
+                };
+            },
+            ListOp::Item(index, list) => {
+                let index = index.require_float(turtle)?;
+                let sequence = Sequence::new(list, turtle)?;
+                let count = sequence.count();
+                if index.fract() != 0.0 || index < 1.0 || index as usize > count {
+                    return Err(RuntimeError::ItemOutOfRange { index, count });
+                }
+                return Ok(sequence.item(index as usize - 1));
+            },
+            ListOp::First(list) => ("FIRST", list),
+            ListOp::ButFirst(list) => ("BUTFIRST", list),
+            ListOp::Last(list) => ("LAST", list),
+        };
+        let sequence = Sequence::new(list, turtle)?;
+        let count = sequence.count();
+        if count == 0 {
+            return Err(RuntimeError::EmptyList(operation));
+        }
+        Ok(match self {
+            ListOp::First(_) => sequence.item(0),
+            ListOp::Last(_) => sequence.item(count - 1),
+            _ => sequence.butfirst(),
+        })
+    }
+}
+
+/// A list, or a word taken apart into its characters, that a `ListOp` works on.
+enum Sequence {
+    List(Vec<Value>),
+    Word(Vec<char>),
+}
+
+impl Sequence {
+    fn new<T: TurtleOps + ?Sized>(expression: &Expression, turtle: &T) -> Result<Self, RuntimeError> {
+        match expression.evaluate(turtle)? {
+            Value::List(items) => Ok(Sequence::List(items)),
+            // Numbers are words too, so `FIRST "123` is 1.
+            value => as_word(value)
+                .map(|word| Sequence::Word(word.chars().collect()))
+                .map_err(|found| found.mismatch("list")),
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            Sequence::List(items) => items.len(),
+            Sequence::Word(chars) => chars.len(),
+        }
+    }
+
+    fn item(&self, index: usize) -> Value {
+        match self {
+            Sequence::List(items) => items[index].clone(),
+            Sequence::Word(chars) => word(chars[index].to_string()),
+        }
+    }
+
+    fn butfirst(self) -> Value {
+        match self {
+            Sequence::List(items) => Value::List(items[1..].to_vec()),
+            Sequence::Word(chars) => word(chars[1..].iter().collect()),
+        }
+    }
+}
+
+/// Gets the text of an evaluated word or number, or gives back any other value.
+fn as_word(value: Value) -> Result<String, Value> {
+    match value {
+        Value::String(word) => Ok(word),
+        Value::Float(val) => Ok(val.to_string()),
+        Value::Int(val) => Ok(val.to_string()),
+        value => Err(value),
+    }
+}
+
+/// Wraps a piece of a word as a number if it is one, so `FIRST "123` can be used in math, or as a word otherwise.
+pub(crate) fn word(word: String) -> Value {
+    match word.parse() {
+        Ok(number) => Value::Float(number),
+        Err(_) => Value::String(word),
+    }
 }
 
 /// Represents a query in the language, such as `XCOR` or `YCOR`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Query {
 
     /// Returns the x-coordinate of the turtle.
@@ -437,4 +1617,16 @@ pub enum Query {
 	HEADING,
     /// Returns the pen color of the turtle.
 	COLOR,
+    /// Returns the fill color of the turtle.
+	FILLCOLOR,
+    /// Returns the width of the canvas.
+	SCREENWIDTH,
+    /// Returns the height of the canvas.
+	SCREENHEIGHT,
+    /// Returns the iteration of the innermost `REPEAT`, counting from 1.
+	REPCOUNT,
+    /// Returns a random whole number from 0 up to, but not including, the given number.
+	Random(Box<Expression>),
+    /// Returns the color drawn at the given coordinates, or -1 if nothing has been drawn there.
+	PixelColor(Box<Expression>, Box<Expression>),
 }
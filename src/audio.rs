@@ -0,0 +1,40 @@
+//! # Audio
+//!
+//! This module contains the `AudioSink` trait, which `TOOT` asks to play a tone, so programs can make sounds
+//! without this crate depending on any audio library.
+//!
+//! An `AudioSink` is attached to a turtle with `Turtle::set_audio_sink`. Any closure taking the frequency in
+//! hertz and the duration in milliseconds is an `AudioSink`. Without one, `TOOT` is silent.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//!
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let played = Arc::new(Mutex::new(Vec::new()));
+//! let sink = played.clone();
+//! let mut turtle = Turtle::new(100, 100);
+//! turtle.set_audio_sink(Some(Box::new(move |frequency, duration| {
+//!     sink.lock().unwrap().push((frequency, duration));
+//!     Ok(())
+//! })));
+//!
+//! run(&parse_content("TOOT \"440 \"250 REPEAT \"2 [ TOOT * REPCOUNT \"220 \"100 ]").unwrap(), &mut turtle).unwrap();
+//! assert_eq!(*played.lock().unwrap(), [(440.0, 250.0), (220.0, 100.0), (440.0, 100.0)]);
+//! ```
+
+/// Plays the tones of `TOOT`.
+pub trait AudioSink: Send + Sync {
+    /// Plays a tone of `frequency` hertz for `duration` milliseconds. An error stops the program.
+    fn toot(&mut self, frequency: f32, duration: f32) -> std::io::Result<()>;
+}
+
+impl<F: FnMut(f32, f32) -> std::io::Result<()> + Send + Sync> AudioSink for F {
+    fn toot(&mut self, frequency: f32, duration: f32) -> std::io::Result<()> {
+        self(frequency, duration)
+    }
+}
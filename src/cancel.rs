@@ -0,0 +1,52 @@
+//! # Cancel
+//!
+//! This module contains the `CancelToken`, which lets a host stop a running program from another thread,
+//! such as when the user presses a stop button in a GUI or a client disconnects from a server.
+//!
+//! A token is attached to a turtle with `Turtle::set_cancel_token`, and a clone of it kept by the host.
+//! The turtle checks the token before each instruction, so cancelling stops the program with
+//! `RuntimeError::Cancelled` at the next instruction, leaving the turtle as it was.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::cancel::CancelToken;
+//! use rslogo::error::RuntimeError;
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let token = CancelToken::new();
+//! let mut turtle = Turtle::new(100, 100);
+//! turtle.set_cancel_token(Some(token.clone()));
+//!
+//! let asts = parse_content("WHILE EQ \"1 \"1 [ TURN \"1 ]").unwrap();
+//! let worker = std::thread::spawn(move || run(&asts, &mut turtle));
+//!
+//! token.cancel();
+//! assert!(matches!(worker.join().unwrap(), Err(RuntimeError::Cancelled)));
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag shared between a host and the turtles it is attached to, which stops them once it is cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, stopping every program running on a turtle it is attached to.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
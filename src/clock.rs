@@ -0,0 +1,61 @@
+//! # Clock
+//!
+//! This module contains the `Clock` trait, which `WAIT` asks to delay the program, so hosts that show the
+//! drawing as it happens can pace it in real time, and hosts that only save the finished image do not wait.
+//!
+//! A `Clock` is attached to a turtle with `Turtle::set_clock`. Any closure taking a `Duration` is a `Clock`,
+//! and `SystemClock` sleeps the current thread. Without a clock, `WAIT` returns at once. Either way, the ticks
+//! waited are recorded on the next line drawn as its `delay`, for animated outputs to pause for.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use std::time::Duration;
+//!
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let waited = Arc::new(Mutex::new(Duration::ZERO));
+//! let clock = waited.clone();
+//! let mut turtle = Turtle::new(100, 100);
+//! turtle.set_clock(Some(Box::new(move |duration| {
+//!     *clock.lock().unwrap() += duration;
+//!     Ok(())
+//! })));
+//!
+//! run(&parse_content("PENDOWN FORWARD \"10 WAIT \"30 WAIT \"30 FORWARD \"10").unwrap(), &mut turtle).unwrap();
+//! assert_eq!(*waited.lock().unwrap(), Duration::from_secs(1));
+//!
+//! let delays: Vec<f32> = turtle.get_display_list().lines().iter().map(|line| line.delay).collect();
+//! assert_eq!(delays, [0.0, 60.0]);
+//! ```
+
+use std::time::Duration;
+
+/// The number of ticks in a second, the unit `WAIT` is given in.
+pub const TICKS_PER_SECOND: f32 = 60.0;
+
+/// Delays the program for `WAIT`.
+pub trait Clock: Send + Sync {
+    /// Waits for `duration` to pass. An error stops the program.
+    fn wait(&mut self, duration: Duration) -> std::io::Result<()>;
+}
+
+impl<F: FnMut(Duration) -> std::io::Result<()> + Send + Sync> Clock for F {
+    fn wait(&mut self, duration: Duration) -> std::io::Result<()> {
+        self(duration)
+    }
+}
+
+/// A `Clock` that waits in real time, by sleeping the current thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn wait(&mut self, duration: Duration) -> std::io::Result<()> {
+        std::thread::sleep(duration);
+        Ok(())
+    }
+}
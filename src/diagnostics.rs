@@ -0,0 +1,140 @@
+//! # Diagnostics
+//!
+//! This module turns the errors found while parsing or running a Logo program into `Diagnostic`s, which
+//! can be rendered as reports that quote the offending source with a caret under the problem.
+//!
+//! The plain renderer has no dependencies. With the `ariadne` feature, `Diagnostic::report` also builds an
+//! `ariadne` report, which can be printed with colors.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::diagnostics::Diagnostic;
+//! use rslogo::parser::parse_content;
+//!
+//! let source = "FORWARD \"1 \"2";
+//! let errors = parse_content(source).unwrap_err();
+//! let report = Diagnostic::from_parse_error(&errors[0]).render(source);
+//! assert!(report.starts_with("error: Unexpected Value(\"2\")\n --> 1:12\n"));
+//! assert!(report.contains("1 | FORWARD \"1 \"2\n  |            ^^\n"));
+//! ```
+
+use std::fmt::Write;
+
+use chumsky::error::{Simple, SimpleReason};
+
+use crate::ast::{line_column, Span};
+use crate::error::RuntimeError;
+use crate::tokenizer::Token;
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The program could not be parsed, or stopped running.
+    Error,
+
+    /// The program kept running, but skipped an instruction.
+    Warning,
+}
+
+/// A problem found in a program, with where in the source it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+
+    /// A description of the problem.
+    pub message: String,
+
+    /// The byte offsets in the source the problem was found at, if it is known.
+    pub span: Option<Span>,
+
+    /// The tokens the parser would have accepted instead, sorted, if this is a syntax error.
+    pub expected: Vec<String>,
+
+    /// Whether the program could have skipped the problem with `--continue-on-error`.
+    pub recoverable: bool,
+}
+
+impl Diagnostic {
+    /// Describes a syntax error found by the parser.
+    pub fn from_parse_error(error: &Simple<Token>) -> Self {
+        let message = match (error.reason(), error.found()) {
+            (SimpleReason::Custom(message), _) => message.clone(),
+            (_, Some(token)) => format!("Unexpected {token:?}"),
+            (_, None) => "Unexpected end of input".to_string(),
+        };
+        let mut expected: Vec<String> = error.expected()
+            .map(|token| match token {
+                Some(token) => format!("{token:?}"),
+                None => "end of input".to_string(),
+            })
+            .collect();
+        expected.sort();
+        expected.dedup();
+        Diagnostic { severity: Severity::Error, message, span: Some(error.span()), expected, recoverable: false }
+    }
+
+    /// Describes an error raised while executing the instruction at `span`, if it is known.
+    pub fn from_runtime_error(error: &RuntimeError, span: Option<&Span>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: error.to_string(),
+            span: span.cloned(),
+            expected: Vec::new(),
+            recoverable: error.is_recoverable(),
+        }
+    }
+
+    /// Renders the diagnostic as a report quoting the line of `source` it was found on.
+    pub fn render(&self, source: &str) -> String {
+        let mut report = match self.severity {
+            Severity::Error => format!("error: {}\n", self.message),
+            Severity::Warning => format!("warning: {}\n", self.message),
+        };
+        if let Some(span) = &self.span {
+            let (line, column) = line_column(source, span.start);
+            let text = source.lines().nth(line - 1).unwrap_or("");
+            let gutter = " ".repeat(line.to_string().len());
+            let width = text.chars().skip(column - 1).take(span.len()).count().max(1);
+            let _ = writeln!(report, "{gutter}--> {line}:{column}");
+            let _ = writeln!(report, "{gutter} |");
+            let _ = writeln!(report, "{line} | {text}");
+            let _ = writeln!(report, "{gutter} | {}{}", " ".repeat(column - 1), "^".repeat(width));
+        }
+        if let Some(expectation) = self.expectation() {
+            let _ = writeln!(report, "  = {expectation}");
+        }
+        report
+    }
+
+    /// Builds an `ariadne` report of the diagnostic, which can be printed with the program's source.
+    #[cfg(feature = "ariadne")]
+    pub fn report(&self) -> ariadne::Report<'static, Span> {
+        use ariadne::{Config, IndexType, Label, Report, ReportKind};
+
+        let kind = match self.severity {
+            Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+        };
+        let span = self.span.clone().unwrap_or(0..0);
+        let mut report = Report::build(kind, span.clone())
+            .with_config(Config::default().with_index_type(IndexType::Byte))
+            .with_message(&self.message);
+        if self.span.is_some() {
+            report = report.with_label(Label::new(span).with_message("here"));
+        }
+        if let Some(expectation) = self.expectation() {
+            report = report.with_note(expectation);
+        }
+        report.finish()
+    }
+
+    fn expectation(&self) -> Option<String> {
+        match self.expected.as_slice() {
+            [] => None,
+            [token] => Some(format!("expected {token}")),
+            tokens => Some(format!("expected one of {}", tokens.join(", "))),
+        }
+    }
+}
@@ -0,0 +1,614 @@
+//! # Display
+//!
+//! This module contains the `DisplayList`, which records the lines drawn by a `Turtle`.
+//!
+//! Executing a program only records lines, and rendering them onto an `Image` is deferred until
+//! `DisplayList::render` is called. This keeps the turtle's state `Send + Sync`, so programs can be executed
+//! on worker threads and the result rendered on the main thread.
+//!
+//! `Image` only draws lines 1 pixel wide. `DisplayList::save_svg` and `DisplayList::to_svg` write each line as
+//! a single path with its own stroke width, while `render` draws a wider line as parallel lines, which is as
+//! close as a PNG can get.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//! use unsvg::Image;
+//!
+//! let asts = parse_content("PENDOWN FORWARD \"10 TURN \"90 FORWARD \"10").unwrap();
+//!
+//! let turtle = std::thread::spawn(move || {
+//!     let mut turtle = Turtle::new(100, 100);
+//!     run(&asts, &mut turtle).unwrap();
+//!     turtle
+//! }).join().unwrap();
+//!
+//! assert_eq!(turtle.get_display_list().lines().len(), 2);
+//!
+//! let mut image = Image::new(100, 100);
+//! turtle.get_display_list().render(&mut image).unwrap();
+//!
+//! let svg = turtle.get_display_list().to_svg(100, 100).unwrap();
+//! assert!(svg.contains("<svg"));
+//! ```
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use unsvg::{get_end_coordinates, Color, Image};
+
+/// A single line recorded in a `DisplayList`, using the same parameters as `Image::draw_simple_line`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Line {
+    /// The x-coordinate the line starts at.
+    pub x: f32,
+
+    /// The y-coordinate the line starts at.
+    pub y: f32,
+
+    /// The direction of the line in degrees, where 0 is straight up.
+    pub direction: i32,
+
+    /// The length of the line.
+    pub length: f32,
+
+    /// The color of the line.
+    pub color: Color,
+
+    /// The width of the line in pixels, set with `SETPENSIZE`.
+    pub width: f32,
+
+    /// The speed set with `SETSPEED` when the line was drawn, in pixels per second, which animated exporters
+    /// pace their playback with. `None` if no speed was set, so exporters choose their own.
+    pub speed: Option<f32>,
+
+    /// The ticks waited with `WAIT` since the line before was drawn, which animated exporters pause for before
+    /// drawing this line. 0 if the program did not wait.
+    pub delay: f32,
+
+    /// The point the line ends at, if it does not lie exactly along `direction`, such as when it was snapped
+    /// to a grid. `None` if the line ends where `direction` and `length` take it.
+    pub exact_end: Option<(f32, f32)>,
+}
+
+impl Line {
+    /// Gets the coordinates the line ends at.
+    pub fn end(&self) -> (f32, f32) {
+        self.exact_end.unwrap_or_else(|| get_end_coordinates(self.x, self.y, self.direction, self.length))
+    }
+}
+
+/// The widest a line may be, in pixels. `SETPENSIZE` refuses wider pens, and wider lines, such as those scaled
+/// up by a `Transform`, are drawn this wide.
+pub const MAX_LINE_WIDTH: f32 = 100.0;
+
+/// How far apart, in pixels, the end of one line and the start of the next may be while still being joined.
+const JOIN_TOLERANCE: f32 = 1e-3;
+
+/// A uniform scale followed by a translation, applied to lines as they are drawn onto an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// The distance to move every line right by, after scaling.
+    pub x_offset: f32,
+
+    /// The distance to move every line down by, after scaling.
+    pub y_offset: f32,
+
+    /// The factor to scale coordinates and lengths by.
+    pub scale: f32,
+}
+
+impl Transform {
+    /// The transform that leaves every line where it is.
+    pub const IDENTITY: Self = Self { x_offset: 0.0, y_offset: 0.0, scale: 1.0 };
+
+    /// Applies the transform to `line`.
+    pub fn apply(&self, line: &Line) -> Line {
+        Line {
+            x: line.x * self.scale + self.x_offset,
+            y: line.y * self.scale + self.y_offset,
+            length: line.length * self.scale,
+            width: line.width * self.scale,
+            exact_end: line.exact_end.map(|(x, y)| (x * self.scale + self.x_offset, y * self.scale + self.y_offset)),
+            ..*line
+        }
+    }
+
+    /// Finds the transform that scales `bounds` up or down as far as it fits on a canvas of the given size,
+    /// leaving at least `margin` pixels around it, and centers it.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::display::{Bounds, Transform};
+    /// 
+    /// let bounds = Bounds { min_x: 0.0, min_y: 0.0, max_x: 20.0, max_y: 10.0 };
+    /// let transform = Transform::fit(bounds, 100, 100, 10.0);
+    /// assert_eq!(transform, Transform { x_offset: 10.0, y_offset: 30.0, scale: 4.0 });
+    /// ```
+    pub fn fit(bounds: Bounds, width: u32, height: u32, margin: f32) -> Self {
+        let (available_width, available_height) = (width as f32 - 2.0 * margin, height as f32 - 2.0 * margin);
+        let (bounds_width, bounds_height) = (bounds.max_x - bounds.min_x, bounds.max_y - bounds.min_y);
+        let scale = [(available_width, bounds_width), (available_height, bounds_height)]
+            .into_iter()
+            .filter(|&(_, size)| size > 0.0)
+            .map(|(available, size)| available / size)
+            .reduce(f32::min)
+            .filter(|&scale| scale > 0.0)
+            .unwrap_or(1.0);
+        Self {
+            x_offset: (width as f32 - bounds_width * scale) / 2.0 - bounds.min_x * scale,
+            y_offset: (height as f32 - bounds_height * scale) / 2.0 - bounds.min_y * scale,
+            scale,
+        }
+    }
+}
+
+/// The smallest rectangle containing every line of a `DisplayList`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    /// The smallest x-coordinate of any line.
+    pub min_x: f32,
+
+    /// The smallest y-coordinate of any line.
+    pub min_y: f32,
+
+    /// The largest x-coordinate of any line.
+    pub max_x: f32,
+
+    /// The largest y-coordinate of any line.
+    pub max_y: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Whether `next` continues the path that `line` is part of.
+fn joins(line: &Line, next: &Line) -> bool {
+    let (x, y) = line.end();
+    line.color == next.color && line.width == next.width && line.speed == next.speed && next.delay == 0.0 && (x - next.x).abs() <= JOIN_TOLERANCE && (y - next.y).abs() <= JOIN_TOLERANCE
+}
+
+/// Adds the simplified lines of `path`, a run of lines that each start where the one before ended, to `simplified`.
+fn simplify_path(path: &[Line], tolerance: f32, simplified: &mut DisplayList) {
+    let mut points = vec![(path[0].x, path[0].y)];
+    points.extend(path.iter().map(Line::end));
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance_to_line(points[i], points[first], points[last])))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                ranges.push((first, i));
+                ranges.push((i, last));
+            }
+        }
+    }
+
+    let kept: Vec<usize> = (0..points.len()).filter(|&i| keep[i]).collect();
+    for pair in kept.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        if to == from + 1 {
+            simplified.push(path[from]);
+            continue;
+        }
+        let ((x, y), (end_x, end_y)) = (points[from], points[to]);
+        let direction = (end_y - y).atan2(end_x - x).to_degrees() + 90.0;
+        simplified.push(Line {
+            x,
+            y,
+            direction: direction.round() as i32,
+            length: (end_x - x).hypot(end_y - y),
+            color: path[from].color,
+            width: path[from].width,
+            speed: path[from].speed,
+            delay: path[from].delay,
+            exact_end: Some((end_x, end_y)),
+        });
+    }
+}
+
+/// Draws `line` onto `image`. `Image::draw_simple_line` only draws lines 1 pixel wide, so a wider line is drawn
+/// as parallel lines spread evenly across its width, at most 1 pixel apart, with square ends.
+fn draw(image: &mut Image, line: &Line) -> Result<(), unsvg::Error> {
+    let width = line.width.min(MAX_LINE_WIDTH);
+    let strokes = width.ceil().max(1.0) as u32;
+    let spacing = if strokes > 1 { (width - 1.0) / (strokes - 1) as f32 } else { 0.0 };
+    let (sin, cos) = (line.direction as f32).to_radians().sin_cos();
+    for stroke in 0..strokes {
+        let offset = (stroke as f32 - (strokes - 1) as f32 / 2.0) * spacing;
+        image.draw_simple_line(line.x + offset * cos, line.y + offset * sin, line.direction, line.length, line.color)?;
+    }
+    Ok(())
+}
+
+/// Rewrites the paths `Image::save_svg` wrote for `lines` in `svg`, which are the last paths with no fill, so
+/// each is as wide as its line and ends at its exact end. Paths for lines 1 pixel wide that end where their
+/// direction takes them are left as they are.
+fn restroke(svg: &str, lines: &[Line]) -> String {
+    let strokes: Vec<usize> = svg.match_indices("<path")
+        .map(|(start, _)| start)
+        .filter(|&start| svg[start..].split('>').next().is_some_and(|tag| tag.contains("fill=\"none\"")))
+        .collect();
+    // Each edit replaces the text between two offsets into `svg`, in order.
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    for (&start, line) in strokes.iter().rev().zip(lines.iter().rev()).rev() {
+        let Some(length) = svg[start..].find('>') else {
+            continue;
+        };
+        let tag = &svg[start..start + length];
+        if let (Some((x, y)), Some(path)) = (line.exact_end, tag.find(" d=\"")) {
+            let value = start + path + " d=\"".len();
+            let value_end = value + svg[value..].find('"').unwrap_or(0);
+            edits.push((value, value_end, format!("M {} {} L {x} {y}", line.x, line.y)));
+        }
+        if line.width != 1.0 {
+            let end = if tag.ends_with('/') { start + length - 1 } else { start + length };
+            let width = line.width.min(MAX_LINE_WIDTH);
+            edits.push((end, end, format!(" stroke-width=\"{width}\" stroke-linecap=\"round\"")));
+        }
+    }
+
+    let mut rewritten = String::with_capacity(svg.len());
+    let mut position = 0;
+    for (from, to, text) in edits {
+        rewritten.push_str(&svg[position..from]);
+        rewritten.push_str(&text);
+        position = to;
+    }
+    rewritten.push_str(&svg[position..]);
+    rewritten
+}
+
+/// A path in the temporary directory for an image being exported, unique within the process.
+fn temporary_file(extension: &str) -> PathBuf {
+    static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "rslogo-{}-{}.{extension}",
+        std::process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Finds the distance from `point` to the line through `start` and `end`.
+fn distance_to_line(point: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        return (point.0 - start.0).hypot(point.1 - start.1);
+    }
+    ((point.0 - start.0) * dy - (point.1 - start.1) * dx).abs() / length
+}
+
+/// Finds the distance from `point` to the nearest point between `start` and `end`.
+fn distance_to_segment(point: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    (point.0 - start.0 - t * dx).hypot(point.1 - start.1 - t * dy)
+}
+
+/// Gets the pixels on a `width` by `height` canvas in the 3 by 3 square centred on column `x` and row `y`.
+fn neighbours(x: f32, y: f32, width: usize, height: usize) -> impl Iterator<Item = (usize, usize)> {
+    (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx as f32, y + dy as f32)))
+        .filter(move |&(i, j)| i >= 0.0 && j >= 0.0 && i < width as f32 && j < height as f32)
+        .map(|(i, j)| (i as usize, j as usize))
+}
+
+/// Receives each line as soon as a `Turtle` draws it, while the program is still executing.
+pub trait LineSink: Send + Sync {
+    /// Receives `line`. An error stops the program, for example when the receiver has gone away.
+    fn draw(&mut self, line: &Line) -> std::io::Result<()>;
+}
+
+/// The lines drawn by a `Turtle`, in the order they were drawn.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DisplayList {
+    lines: Vec<Line>,
+}
+
+impl DisplayList {
+    /// Creates an empty display list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a line at the end of the display list.
+    pub fn push(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    /// Removes every line, keeping the memory they used so the display list can be refilled without reallocating.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Gets the lines in the display list.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Finds the smallest rectangle containing every line, or `None` if there are no lines.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.lines.iter()
+            .flat_map(|line| [(line.x, line.y), line.end()])
+            .map(|(x, y)| Bounds { min_x: x, min_y: y, max_x: x, max_y: y })
+            .reduce(|a, b| Bounds {
+                min_x: a.min_x.min(b.min_x),
+                min_y: a.min_y.min(b.min_y),
+                max_x: a.max_x.max(b.max_x),
+                max_y: a.max_y.max(b.max_y),
+            })
+    }
+
+    /// Finds the color of the last line drawn through the pixel centred on `x` and `y`, or `None` if no line
+    /// passes within half a pixel of it.
+    pub fn color_at(&self, x: f32, y: f32) -> Option<Color> {
+        self.lines.iter()
+            .rev()
+            .find(|line| distance_to_segment((x, y), (line.x, line.y), line.end()) <= 0.5)
+            .map(|line| line.color)
+    }
+
+    /// Finds the pixels of a `width` by `height` canvas that can be reached from the pixel containing `x` and
+    /// `y` without crossing a line, returning each row of them as a line in `color`, or no lines if that pixel
+    /// is off the canvas or has a line drawn through it. A pixel is crossed by a line when its centre is within
+    /// half a pixel of it, and pixels are reached through their edges, so diagonal lines still enclose a region.
+    pub fn flood_fill(&self, x: f32, y: f32, width: u32, height: u32, color: Color) -> Vec<Line> {
+        let (width, height) = (width as usize, height as usize);
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            return Vec::new();
+        }
+
+        let mut blocked = vec![false; width * height];
+        for line in &self.lines {
+            let (start, end) = ((line.x, line.y), line.end());
+            // Every pixel within half a pixel of the line is next to a point sampled every quarter pixel.
+            let samples = (line.length.abs() * 4.0).ceil() as usize;
+            for sample in 0..=samples {
+                let t = if samples == 0 { 0.0 } else { sample as f32 / samples as f32 };
+                let (px, py) = (start.0 + (end.0 - start.0) * t, start.1 + (end.1 - start.1) * t);
+                for (i, j) in neighbours(px.floor(), py.floor(), width, height) {
+                    let centre = (i as f32 + 0.5, j as f32 + 0.5);
+                    if distance_to_segment(centre, start, end) <= 0.5 {
+                        blocked[j * width + i] = true;
+                    }
+                }
+            }
+        }
+
+        let (i, j) = (x as usize, y as usize);
+        if blocked[j * width + i] {
+            return Vec::new();
+        }
+        let mut filled = vec![false; width * height];
+        filled[j * width + i] = true;
+        let mut pending = vec![(i, j)];
+        while let Some((i, j)) = pending.pop() {
+            let edges = [
+                (i.wrapping_sub(1), j),
+                (i + 1, j),
+                (i, j.wrapping_sub(1)),
+                (i, j + 1),
+            ];
+            for (i, j) in edges {
+                if i < width && j < height && !blocked[j * width + i] && !filled[j * width + i] {
+                    filled[j * width + i] = true;
+                    pending.push((i, j));
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (j, row) in filled.chunks(width).enumerate() {
+            let mut i = 0;
+            while i < width {
+                if !row[i] {
+                    i += 1;
+                    continue;
+                }
+                let start = i;
+                while i < width && row[i] {
+                    i += 1;
+                }
+                lines.push(Line {
+                    x: start as f32,
+                    y: j as f32 + 0.5,
+                    direction: 90,
+                    length: (i - start) as f32,
+                    color,
+                    width: 1.0,
+                    speed: None,
+                    delay: 0.0,
+                    exact_end: None,
+                });
+            }
+        }
+        lines
+    }
+
+    /// Moves and scales every line by `transform`.
+    pub fn transformed(&self, transform: Transform) -> DisplayList {
+        DisplayList { lines: self.lines.iter().map(|line| transform.apply(line)).collect() }
+    }
+
+    /// Simplifies every path in the display list with the Douglas-Peucker algorithm, dropping the points
+    /// that lie within `tolerance` pixels of the line through the points kept on either side of them.
+    /// 
+    /// A path is a run of consecutive lines of the same color, each starting where the one before ended,
+    /// such as the many short lines a program draws to approximate a curve. Lines whose ends are both kept
+    /// are copied unchanged. Merged lines point in whole degrees, so their ends may move by up to about
+    /// 1% of their length.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("PENDOWN REPEAT \"10 [ FORWARD \"5 ] TURN \"90 REPEAT \"10 [ FORWARD \"5 ]").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// let display_list = turtle.get_display_list();
+    /// assert_eq!(display_list.lines().len(), 20);
+    /// assert_eq!(display_list.simplify(0.5).lines().len(), 2);
+    /// ```
+    pub fn simplify(&self, tolerance: f32) -> DisplayList {
+        let mut simplified = DisplayList::new();
+        let mut start = 0;
+        while start < self.lines.len() {
+            let mut end = start + 1;
+            while end < self.lines.len() && joins(&self.lines[end - 1], &self.lines[end]) {
+                end += 1;
+            }
+            simplify_path(&self.lines[start..end], tolerance, &mut simplified);
+            start = end;
+        }
+        simplified
+    }
+
+    /// Removes every line that retraces an earlier line of the same color, in either direction, keeping
+    /// the first. Ends are compared to the nearest hundredth of a pixel. Lines that only partly overlap
+    /// are kept.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("PENDOWN FORWARD \"10 BACK \"10 FORWARD \"10 SETPENCOLOR \"1 BACK \"10").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// assert_eq!(turtle.get_display_list().deduplicate().lines().len(), 2);
+    /// ```
+    pub fn deduplicate(&self) -> DisplayList {
+        let quantize = |(x, y): (f32, f32)| ((x * 100.0).round() as i64, (y * 100.0).round() as i64);
+        let mut seen = HashSet::new();
+        let lines = self.lines.iter()
+            .filter(|line| {
+                let start = quantize((line.x, line.y));
+                let end = quantize(line.end());
+                let color = (line.color.red, line.color.green, line.color.blue);
+                seen.insert((start.min(end), start.max(end), color, line.width.to_bits()))
+            })
+            .copied()
+            .collect();
+        DisplayList { lines }
+    }
+
+    /// Fills `image` with `color` by drawing a line along each row of pixels, so the lines drawn
+    /// afterwards appear on top of it.
+    pub fn fill_background(image: &mut Image, color: Color) -> Result<(), unsvg::Error> {
+        let (width, height) = image.get_dimensions();
+        for row in 0..height {
+            image.draw_simple_line(0.0, row as f32 + 0.5, 90, width as f32, color)?;
+        }
+        Ok(())
+    }
+
+    /// Draws every line in the display list onto `image`.
+    pub fn render(&self, image: &mut Image) -> Result<(), unsvg::Error> {
+        self.render_from(0, image)
+    }
+
+    /// Draws every line in the display list onto `image`, moved and scaled by `transform`.
+    pub fn render_with(&self, image: &mut Image, transform: Transform) -> Result<(), unsvg::Error> {
+        let _span = tracing::info_span!("render", lines = self.lines.len()).entered();
+        for line in self.lines.iter().map(|line| transform.apply(line)) {
+            draw(image, &line)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the lines from index `start` onwards onto `image`, skipping those that were already drawn.
+    pub fn render_from(&self, start: usize, image: &mut Image) -> Result<(), unsvg::Error> {
+        let lines = self.lines.get(start..).unwrap_or_default();
+        let _span = tracing::info_span!("render", lines = lines.len()).entered();
+        for line in lines {
+            draw(image, line)?;
+        }
+        Ok(())
+    }
+
+    /// Draws every line onto `image` and saves it to `path` as SVG, with each line a single path as wide as
+    /// the line, with round ends, ending exactly at the line's end. Anything already drawn on `image`, such as a background, stays beneath them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// use unsvg::Image;
+    ///
+    /// let asts = parse_content("PENDOWN FORWARD \"10 SETPENSIZE \"2.5 FORWARD \"10").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    ///
+    /// let path = std::env::temp_dir().join("rslogo-save-svg-example.svg");
+    /// turtle.get_display_list().save_svg(&mut Image::new(100, 100), &path).unwrap();
+    /// let svg = std::fs::read_to_string(&path).unwrap();
+    /// std::fs::remove_file(&path).unwrap();
+    ///
+    /// assert_eq!(svg.matches("fill=\"none\"").count(), 2);
+    /// assert_eq!(svg.matches("stroke-width=\"2.5\"").count(), 1);
+    /// ```
+    pub fn save_svg(&self, image: &mut Image, path: &Path) -> Result<(), String> {
+        let _span = tracing::info_span!("render", lines = self.lines.len()).entered();
+        for line in &self.lines {
+            image.draw_simple_line(line.x, line.y, line.direction, line.length, line.color).map_err(|e| e.to_string())?;
+        }
+        image.save_svg(path).map_err(|e| e.to_string())?;
+        if self.lines.iter().all(|line| line.width == 1.0 && line.exact_end.is_none()) {
+            return Ok(());
+        }
+        let svg = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        std::fs::write(path, restroke(&svg, &self.lines)).map_err(|e| e.to_string())
+    }
+
+    /// Draws every line onto a new image of the given size, returning the image as SVG source, with each line
+    /// as wide as it was drawn, as `save_svg` writes it.
+    pub fn to_svg(&self, width: u32, height: u32) -> Result<String, String> {
+        // `unsvg` can only save images to a file, so the image is written to a temporary file and read back.
+        let file = temporary_file("svg");
+        let svg = self.save_svg(&mut Image::new(width, height), &file)
+            .and_then(|()| std::fs::read_to_string(&file).map_err(|e| e.to_string()));
+        let _ = std::fs::remove_file(&file);
+        svg
+    }
+
+    /// Renders every line onto a new image of the given size, returning the image encoded as PNG.
+    pub fn to_png(&self, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let mut image = Image::new(width, height);
+        self.render(&mut image).map_err(|e| e.to_string())?;
+
+        // `unsvg` can only save images to a file, so the image is written to a temporary file and read back.
+        let file = temporary_file("png");
+        image.save_png(&file).map_err(|e| e.to_string())?;
+        let bytes = std::fs::read(&file).map_err(|e| e.to_string());
+        let _ = std::fs::remove_file(&file);
+        bytes
+    }
+}
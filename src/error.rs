@@ -0,0 +1,325 @@
+//! # Error
+//!
+//! This module contains the `RuntimeError` enum, which is returned when a Logo program fails while it is executed.
+//!
+//! Every failure is reported as a `RuntimeError` rather than a panic, so a program with a mistake in it
+//! cannot abort the host application that is running it.
+//!
+//! The `Error` enum, exported as `rslogo::Error`, also covers the text that is not a token, the syntax errors
+//! found before a program runs, and the errors saving an image, so callers that go from source code to a file
+//! can use `?` throughout.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::error::RuntimeError;
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let asts = parse_content("MAKE \"x / \"1 \"0").unwrap();
+//! let result = run(&asts, &mut Turtle::new(100, 100));
+//! assert!(matches!(result, Err(RuntimeError::DivisionByZero)));
+//!
+//! let asts = parse_content("FORWARD \"ten").unwrap();
+//! let error = run(&asts, &mut Turtle::new(100, 100)).unwrap_err();
+//! assert_eq!(error.to_string(), "\"ten is not a number");
+//! ```
+//!
+//! ```
+//! use rslogo::{interpret, Error};
+//!
+//! fn draw(source: &str, path: &std::path::Path) -> Result<(), Error> {
+//!     interpret(source, 100, 100)?.save_svg(path)?;
+//!     Ok(())
+//! }
+//!
+//! let path = std::env::temp_dir().join("rslogo-error-example.svg");
+//! assert!(draw("PENDOWN FORWARD \"10", &path).is_ok());
+//! assert!(matches!(draw("FORWARD \"10 !", &path), Err(Error::Tokenize(_))));
+//! assert!(matches!(draw("PENDOWN FORWARD \"10", std::path::Path::new("/")), Err(Error::Image(_))));
+//! ```
+
+use std::fmt;
+
+use chumsky::error::Simple;
+use unsvg::COLORS;
+
+use crate::diagnostics::Diagnostic;
+use crate::value::Value;
+use crate::limits::Limit;
+use crate::tokenizer::{InvalidToken, Token};
+
+/// An error raised while executing a Logo program.
+#[derive(Debug)]
+pub enum RuntimeError {
+    /// One of the turtle's `Limits` was exceeded.
+    LimitExceeded(Limit),
+
+    /// A variable was used before it was given a value with `MAKE`.
+    UndefinedVariable {
+        /// The name of the variable.
+        name: String,
+        /// The variable that can be seen whose name is closest to it, if one is close enough to be a likely typo.
+        suggestion: Option<String>,
+    },
+
+    /// A variable was created with a name its turtle's `NamePolicy` does not allow.
+    InvalidVariableName {
+        /// The name of the variable.
+        name: String,
+        /// Why the name is not allowed, such as `"it starts with a digit"`.
+        reason: &'static str,
+    },
+
+    /// An expression was used where a value of a different type was expected, such as a word given to `FORWARD`.
+    TypeMismatch {
+        /// The type of value that was expected, such as `"number"`.
+        expected: &'static str,
+        /// The value that was not of the expected type.
+        found: Value,
+    },
+
+    /// `LT`, `GT`, `LE`, or `GE` was given a number and a word, which have no order between them.
+    Incomparable(Value, Value),
+
+    /// A number was divided by zero.
+    DivisionByZero,
+
+    /// `SETPENCOLOR` or `SETFILLCOLOR` was given a number that is not the index of a color in `unsvg::COLORS`,
+    /// such as a negative or fractional number.
+    InvalidColor(f32),
+
+    /// The program was stopped by cancelling the turtle's `CancelToken`.
+    Cancelled,
+
+    /// Every step of the fuel given with `Turtle::set_fuel` was used up before the program finished.
+    FuelExhausted,
+
+    /// A procedure call would have exceeded the `max_call_depth` of the turtle's `Limits`, which is the number
+    /// of calls that were already in progress.
+    StackOverflow(usize),
+
+    /// A `WHILE`, `DO.WHILE`, or `DO.UNTIL` loop ran its block more times than the `max_loop_iterations` of the
+    /// turtle's `Limits` allows.
+    TooManyIterations {
+        /// The keyword of the loop, such as `"WHILE"`.
+        keyword: &'static str,
+        /// The number of iterations the loop was allowed.
+        max: u64,
+    },
+
+    /// A math operation was given a number it is not defined for, such as the square root of a negative number.
+    Undefined {
+        /// The name of the operation, such as `"SQRT"`.
+        operation: &'static str,
+        /// The number the operation is not defined for.
+        value: f32,
+    },
+
+    /// `RANDOM` was given a number with no whole numbers below it to choose from.
+    InvalidRandomRange(f32),
+
+    /// `SETPENCOLOR` was given a word that is not the name of a color.
+    UnknownColor(String),
+
+    /// A list operation such as `FIRST` was given an empty list or word, which has no item to take.
+    EmptyList(&'static str),
+
+    /// `ITEM` was given an index that is not the position of an item in the list.
+    ItemOutOfRange {
+        /// The index that was asked for, counting from 1.
+        index: f32,
+        /// The number of items in the list.
+        count: usize,
+    },
+
+    /// `GOTO` was given a name that was not marked with `MARK`.
+    UndefinedMark(String),
+
+    /// `RESTOREIMAGE` was given a name that was not saved with `SNAPSHOT`.
+    UndefinedSnapshot(String),
+
+    /// A procedure was called before it was defined with `TO`, and no primitive has its name.
+    UndefinedProcedure(String),
+
+    /// `THROW` was used with a tag that no `CATCH` around it has. It is not recoverable, so continue-on-error mode
+    /// cannot skip the `THROW` before a `CATCH` stops it.
+    Thrown(String),
+
+    /// `POPSTATE` was used without a matching `PUSHSTATE`.
+    EmptyStateStack,
+
+    /// An executed command could not be recorded to the audit sink.
+    Audit(std::io::Error),
+
+    /// A drawn line could not be sent to the line sink.
+    LineSink(std::io::Error),
+
+    /// The lines drawn by a program could not be drawn onto an image.
+    Render(unsvg::Error),
+
+    /// `READWORD` could not read from the turtle's input source.
+    Input(std::io::Error),
+
+    /// A tone could not be played by the audio sink.
+    Audio(std::io::Error),
+
+    /// `WAIT` could not wait on the turtle's clock.
+    Clock(std::io::Error),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::LimitExceeded(limit) => write!(f, "Exceeded the {limit}"),
+            RuntimeError::UndefinedVariable { name, suggestion: None } => write!(f, "Variable :{name} is not defined"),
+            RuntimeError::UndefinedVariable { name, suggestion: Some(suggestion) } => {
+                write!(f, "Variable :{name} is not defined, did you mean :{suggestion}?")
+            },
+            RuntimeError::InvalidVariableName { name, reason } => {
+                write!(f, "\"{name} cannot be the name of a variable, because {reason}")
+            },
+            RuntimeError::TypeMismatch { expected, found } => write!(f, "{found} is not a {expected}"),
+            RuntimeError::Incomparable(val1, val2) => write!(f, "{val1} and {val2} cannot be compared"),
+            RuntimeError::DivisionByZero => write!(f, "Division by zero"),
+            RuntimeError::InvalidColor(color) => {
+                write!(f, "{color} is not a valid color, which must be a whole number from 0 to {}", COLORS.len() - 1)
+            },
+            RuntimeError::Cancelled => write!(f, "The program was cancelled"),
+            RuntimeError::FuelExhausted => write!(f, "Ran out of fuel before the program finished"),
+            RuntimeError::StackOverflow(depth) => write!(f, "Stack overflow: {depth} procedure calls were already in progress"),
+            RuntimeError::TooManyIterations { keyword, max } => {
+                write!(f, "{keyword} exceeded {} iterations", thousands(*max))
+            },
+            RuntimeError::Undefined { operation, value } => write!(f, "{operation} is not defined for {value}"),
+            RuntimeError::InvalidRandomRange(max) => write!(f, "RANDOM {max} has no numbers to choose from"),
+            RuntimeError::UnknownColor(name) => write!(f, "\"{name} is not the name of a color"),
+            RuntimeError::EmptyList(operation) => write!(f, "{operation} was given an empty list"),
+            RuntimeError::ItemOutOfRange { index, count } => write!(f, "ITEM {index} is out of range for {count} items"),
+            RuntimeError::UndefinedMark(name) => write!(f, "No position is marked \"{name}"),
+            RuntimeError::UndefinedSnapshot(name) => write!(f, "No image is saved as \"{name}"),
+            RuntimeError::UndefinedProcedure(name) => write!(f, "Procedure {name} is not defined"),
+            RuntimeError::Thrown(tag) => write!(f, "THROW \"{tag} was not caught by a CATCH \"{tag}"),
+            RuntimeError::EmptyStateStack => write!(f, "POPSTATE has no saved state to restore"),
+            RuntimeError::Audit(e) => write!(f, "Error writing audit log: {e}"),
+            RuntimeError::LineSink(e) => write!(f, "Error sending line: {e}"),
+            RuntimeError::Render(e) => write!(f, "Error drawing image: {e}"),
+            RuntimeError::Input(e) => write!(f, "Error reading input: {e}"),
+            RuntimeError::Audio(e) => write!(f, "Error playing tone: {e}"),
+            RuntimeError::Clock(e) => write!(f, "Error waiting: {e}"),
+        }
+    }
+}
+
+impl RuntimeError {
+    /// Whether execution can sensibly continue after skipping the instruction that raised this error.
+    pub fn is_recoverable(&self) -> bool {
+        !matches!(
+            self,
+            RuntimeError::LimitExceeded(_)
+                | RuntimeError::Cancelled
+                | RuntimeError::FuelExhausted
+                | RuntimeError::StackOverflow(_)
+                | RuntimeError::TooManyIterations { .. }
+                | RuntimeError::Thrown(_)
+                | RuntimeError::Audit(_)
+                | RuntimeError::LineSink(_)
+                | RuntimeError::Audio(_)
+                | RuntimeError::Clock(_)
+                | RuntimeError::Render(_)
+        )
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Writes `number` with a comma between each group of three digits, such as `1,000,000`.
+fn thousands(number: u64) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl From<Limit> for RuntimeError {
+    fn from(limit: Limit) -> Self {
+        RuntimeError::LimitExceeded(limit)
+    }
+}
+
+/// Any error raised while turning Logo source code into an image, as returned by `interpret`, or saving it.
+#[derive(Debug)]
+pub enum Error {
+    /// The source code contains text that is not a token.
+    Tokenize(InvalidToken),
+
+    /// The source code could not be parsed, with every syntax error the parser found.
+    Parse(Vec<Simple<Token>>),
+
+    /// The program stopped with an error while it was executed or drawn.
+    Runtime(RuntimeError),
+
+    /// The image could not be saved.
+    Image(unsvg::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Tokenize(e) => write!(f, "Syntax error: {e}"),
+            Error::Parse(errors) => {
+                let Some(first) = errors.first() else {
+                    return write!(f, "Syntax error");
+                };
+                write!(f, "Syntax error: {}", Diagnostic::from_parse_error(first).message)?;
+                if errors.len() > 1 {
+                    write!(f, " (and {} more)", errors.len() - 1)?;
+                }
+                Ok(())
+            },
+            Error::Runtime(e) => write!(f, "{e}"),
+            Error::Image(e) => write!(f, "Error saving image: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Tokenize(e) => Some(e),
+            Error::Parse(_) => None,
+            Error::Runtime(e) => Some(e),
+            Error::Image(e) => Some(e),
+        }
+    }
+}
+
+impl From<InvalidToken> for Error {
+    fn from(error: InvalidToken) -> Self {
+        Error::Tokenize(error)
+    }
+}
+
+impl From<Vec<Simple<Token>>> for Error {
+    fn from(errors: Vec<Simple<Token>>) -> Self {
+        Error::Parse(errors)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        Error::Runtime(error)
+    }
+}
+
+impl From<unsvg::Error> for Error {
+    fn from(error: unsvg::Error) -> Self {
+        Error::Image(error)
+    }
+}
@@ -0,0 +1,181 @@
+//! # Formatter
+//!
+//! This module formats Logo source code, as `rslogo fmt` does. Formatting only changes the layout: each
+//! instruction is written on a line of its own, in the notation it was written in, with any run of spaces
+//! or line breaks inside it written as a single space. Infix operators, parentheses, and bare numbers are
+//! kept as they are. The instructions in a block are indented by four spaces, with the closing `]` or `END`
+//! on a line of its own.
+//!
+//! Comments and pragmas between instructions are kept on their own lines, and comments after an instruction
+//! stay at the end of its line. Single blank lines between instructions are kept, and runs of them are
+//! collapsed into one. An instruction with a comment inside it, such as in the block of a `REPEAT`, is left
+//! as it was written, as moving its lines apart could move the comment away from what it describes.
+//!
+//! Formatting never changes what a program does: if the formatted source would not parse back into the same
+//! instructions, the source is returned unchanged.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::formatter::format_source;
+//!
+//! let source = ";#size 200 200\n// A square\nPENDOWN   REPEAT \"4 [FORWARD \"10\n  TURN \"90 ]   // turn right\n\n\n\nPENUP\n";
+//! assert_eq!(
+//!     format_source(source).unwrap(),
+//!     ";#size 200 200\n// A square\nPENDOWN\nREPEAT \"4 [\n    FORWARD \"10\n    TURN \"90\n] // turn right\n\nPENUP\n",
+//! );
+//!
+//! let source = "MAKE \"x   (+ 1 (* 2 3))\nIF GT :x 5 [ FORWARD :x * 2 + 10 ]\n";
+//! assert_eq!(
+//!     format_source(source).unwrap(),
+//!     "MAKE \"x (+ 1 (* 2 3))\nIF GT :x 5 [\n    FORWARD :x * 2 + 10\n]\n",
+//! );
+//!
+//! assert!(format_source("FORWARD").is_err());
+//! ```
+
+use crate::ast::{ASTNode, ControlFlow, Span};
+use crate::error::Error;
+use crate::parser::parse_tokens;
+use crate::primitive::Primitives;
+use crate::tokenizer::try_tokenize;
+
+/// Formats `source`, returning the formatted source code, or the error that stopped it being parsed.
+pub fn format_source(source: &str) -> Result<String, Error> {
+    format_source_with(source, &Primitives::new())
+}
+
+/// Formats `source` as `format_source` does, recognising the commands and queries in `primitives` in
+/// addition to the built-in ones.
+pub fn format_source_with(source: &str, primitives: &Primitives) -> Result<String, Error> {
+    let tokens = try_tokenize(source)?;
+    // Text the tokenizer skipped that is not whitespace is a comment.
+    let mut comments: Vec<Span> = Vec::new();
+    let mut position = 0;
+    for (_, span) in &tokens {
+        if !source[position..span.start].trim().is_empty() {
+            comments.push(position..span.start);
+        }
+        position = span.end;
+    }
+    let instructions = parse_tokens(tokens, source.len(), primitives)?;
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut position = 0;
+    for instruction in &instructions {
+        let span = instruction.span();
+        push_gap(&mut lines, &source[position..span.start]);
+        let commented = comments.iter().any(|comment| comment.start >= span.start && comment.end <= span.end);
+        if commented {
+            lines.push(source[span.clone()].to_string());
+        } else {
+            layout(source, instruction, 0, &mut lines);
+        }
+        position = span.end;
+    }
+    push_gap(&mut lines, &source[position..]);
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let formatted: String = lines.iter().map(|line| format!("{line}\n")).collect();
+
+    if reparse(&formatted, primitives).as_deref() != Some(instructions.as_slice()) {
+        tracing::warn!("formatting would change the program, so it was left unchanged");
+        return Ok(source.to_string());
+    }
+    Ok(formatted)
+}
+
+/// Adds `instruction` to `lines`, indented `depth` levels, with each instruction in its blocks on a line of
+/// its own one level deeper. The text of each line is taken from `source`, so only the layout changes.
+fn layout(source: &str, instruction: &ASTNode, depth: usize, lines: &mut Vec<String>) {
+    let indent = "    ".repeat(depth);
+    let span = instruction.span();
+    let text = &source[span.clone()];
+    let ASTNode::ControlFlow(flow, _) = instruction else {
+        lines.push(format!("{indent}{}", compact(text)));
+        return;
+    };
+    let (header, block, footer) = match flow {
+        ControlFlow::DoWhile { block, .. } | ControlFlow::Until { block, .. } => {
+            // The block comes straight after the keyword, and the condition after the block.
+            let open = text.find('[').unwrap_or(text.len());
+            let rest = match block.last() {
+                Some(last) => &source[last.span().end..span.end],
+                None => text[open + 1..].trim_start(),
+            };
+            let condition = rest.trim_start().strip_prefix(']').unwrap_or(rest);
+            (format!("{} [", compact(&text[..open])), block, format!("] {}", compact(condition)))
+        },
+        ControlFlow::To { block, .. } => {
+            let header = match block.first() {
+                Some(first) => &source[span.start..first.span().start],
+                None => text.trim_end().get(..text.trim_end().len() - "END".len()).unwrap_or(text),
+            };
+            (compact(header), block, "END".to_string())
+        },
+        ControlFlow::If { block, .. }
+        | ControlFlow::While { block, .. }
+        | ControlFlow::Repeat { block, .. }
+        | ControlFlow::WhenKey { block, .. }
+        | ControlFlow::Catch { block, .. }
+        | ControlFlow::DoTimes { block, .. }
+        | ControlFlow::For { block, .. }
+        | ControlFlow::WhenClick { block }
+        | ControlFlow::EveryFrame { block } => {
+            // The block is last, and its `[` is the last one before its first instruction, or in the whole
+            // instruction if the block is empty.
+            let header = match block.first() {
+                Some(first) => &source[span.start..first.span().start],
+                None => &text[..text.rfind('[').unwrap_or(text.len())],
+            };
+            let header = header.trim_end();
+            let header = header.strip_suffix('[').unwrap_or(header);
+            (format!("{} [", compact(header)), block, "]".to_string())
+        },
+    };
+    if block.is_empty() {
+        lines.push(format!("{indent}{header} {footer}"));
+        return;
+    }
+    lines.push(format!("{indent}{header}"));
+    for instruction in block {
+        layout(source, instruction, depth + 1, lines);
+    }
+    lines.push(format!("{indent}{footer}"));
+}
+
+/// Writes `text` on one line, with each run of spaces and line breaks in it as a single space.
+fn compact(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Adds the comments, pragmas, and blank lines in `gap`, the text between two instructions, to `lines`.
+fn push_gap(lines: &mut Vec<String>, gap: &str) {
+    let segments: Vec<&str> = gap.split('\n').collect();
+    for (i, segment) in segments.iter().enumerate() {
+        let segment = segment.trim();
+        if i == 0 {
+            // The rest of the line the last instruction ended on.
+            if !segment.is_empty() {
+                match lines.last_mut() {
+                    Some(line) => {
+                        line.push(' ');
+                        line.push_str(segment);
+                    },
+                    None => lines.push(segment.to_string()),
+                }
+            }
+        } else if !segment.is_empty() {
+            lines.push(segment.to_string());
+        } else if i < segments.len() - 1 && lines.last().is_some_and(|line| !line.is_empty()) {
+            // The last segment is the indentation of the next instruction, not a blank line.
+            lines.push(String::new());
+        }
+    }
+}
+
+/// Parses the formatted source, to check that it is the same program.
+fn reparse(formatted: &str, primitives: &Primitives) -> Option<Vec<ASTNode>> {
+    parse_tokens(try_tokenize(formatted).ok()?, formatted.len(), primitives).ok()
+}
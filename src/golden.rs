@@ -0,0 +1,222 @@
+//! # Golden
+//!
+//! This module contains helpers for golden-image tests, which render a Logo program to SVG and compare it
+//! against an SVG stored alongside the tests.
+//!
+//! Comparing SVG files byte for byte breaks whenever the renderer prints a coordinate with another rounding or
+//! writes attributes in another order, so both files are put through `normalize_svg` first. It writes one tag
+//! per line with its attributes sorted by name, and rounds every number to two decimal places. A mismatch is
+//! reported as the first line that differs, rather than as two walls of SVG.
+//!
+//! Programs are rendered with `RANDOM` seeded by `SEED`, so a program that draws random shapes renders the same
+//! SVG on every run and can still be checked against a golden file.
+//!
+//! Setting the `RSLOGO_UPDATE_GOLDEN` environment variable makes `check_golden` write the rendered SVG to the
+//! golden file instead of comparing against it, for accepting an intended change to the output.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::golden::{diff, normalize_svg};
+//!
+//! let svg = "<svg width=\"100\" height=\"100\">\n    <path stroke=\"#ffffff\" d=\"M 50 50 L 50.0001 40\" fill=\"none\"/>\n</svg>";
+//! assert_eq!(
+//!     normalize_svg(svg),
+//!     "<svg height=\"100\" width=\"100\">\n<path d=\"M 50 50 L 50 40\" fill=\"none\" stroke=\"#ffffff\"/>\n</svg>\n",
+//! );
+//!
+//! let moved = svg.replace("L 50.0001 40", "L 60 40");
+//! let mismatch = diff(svg, &moved).unwrap();
+//! assert_eq!(mismatch.line, 2);
+//! assert!(diff(svg, &svg.replace("50.0001", "50.002")).is_none());
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use regex::{Captures, Regex};
+
+use crate::error::Error;
+use crate::interpreter::Interpreter;
+use crate::parser::parse_tokens;
+use crate::primitive::Primitives;
+use crate::runner::run;
+use crate::tokenizer::try_tokenize;
+
+/// The environment variable that makes `check_golden` update golden files rather than compare against them.
+pub const UPDATE_VARIABLE: &str = "RSLOGO_UPDATE_GOLDEN";
+
+/// The seed of `RANDOM` in every program `render_svg` runs.
+pub const SEED: u64 = 0;
+
+/// The first line at which two normalized SVGs differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The line that differs, counting from 1.
+    pub line: usize,
+
+    /// The line of the golden SVG, or `None` if it ended before this line.
+    pub expected: Option<String>,
+
+    /// The line of the rendered SVG, or `None` if it ended before this line.
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |line: &Option<String>| line.clone().unwrap_or_else(|| "(end of file)".to_string());
+        write!(
+            f,
+            "line {} differs\n  expected: {}\n    actual: {}",
+            self.line,
+            show(&self.expected),
+            show(&self.actual),
+        )
+    }
+}
+
+/// An error raised while checking a program against a golden file.
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The program could not be tokenized, parsed, executed, or saved as SVG.
+    Logo(Error),
+
+    /// The rendered SVG or the golden file could not be read or written.
+    Io(std::io::Error),
+
+    /// The rendered SVG differs from the golden file at the given path.
+    Mismatch(PathBuf, Mismatch),
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Logo(e) => write!(f, "{e}"),
+            GoldenError::Io(e) => write!(f, "{e}"),
+            GoldenError::Mismatch(path, mismatch) => write!(
+                f,
+                "{} does not match the rendered SVG: {mismatch}\nSet {UPDATE_VARIABLE} to accept the new output",
+                path.display(),
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<Error> for GoldenError {
+    fn from(e: Error) -> Self {
+        GoldenError::Logo(e)
+    }
+}
+
+impl From<std::io::Error> for GoldenError {
+    fn from(e: std::io::Error) -> Self {
+        GoldenError::Io(e)
+    }
+}
+
+/// Runs `source` on a canvas of the given size, with `RANDOM` seeded by `SEED`, and returns the SVG it renders to.
+///
+/// # Example
+///
+/// ```
+/// use rslogo::golden::render_svg;
+///
+/// let scribble = "PENDOWN REPEAT \"20 [ TURN RANDOM \"360 FORWARD RANDOM \"20 ]";
+/// assert_eq!(render_svg(scribble, 100, 100).unwrap(), render_svg(scribble, 100, 100).unwrap());
+/// ```
+pub fn render_svg(source: &str, width: u32, height: u32) -> Result<String, GoldenError> {
+    let tokens = try_tokenize(source).map_err(Error::from)?;
+    let instructions = parse_tokens(tokens, source.len(), &Primitives::new()).map_err(Error::from)?;
+    let mut turtle = Interpreter::builder(width, height).seed(SEED).build().turtle();
+    run(&instructions, &mut turtle).map_err(Error::from)?;
+    turtle.get_display_list().to_svg(width, height).map_err(|e| GoldenError::Io(std::io::Error::other(e)))
+}
+
+/// Rewrites `svg` with one tag per line, attributes sorted by name, and every number rounded to two decimal
+/// places, so SVGs that draw the same thing compare equal. Text between tags is dropped.
+pub fn normalize_svg(svg: &str) -> String {
+    let tag = Regex::new(r#"<(/?[\w:-]+)((?:\s+[\w:-]+\s*=\s*"[^"]*")*)\s*(/?)>"#).unwrap();
+    let attribute = Regex::new(r#"([\w:-]+)\s*=\s*"([^"]*)""#).unwrap();
+    // Digits inside a word, such as those of a hex color, are not numbers.
+    let number = Regex::new(r"(^|[^#\w.])(-?\d+(?:\.\d+)?)").unwrap();
+
+    let mut normalized = String::new();
+    for tag in tag.captures_iter(svg) {
+        let mut attributes: Vec<(&str, String)> = attribute.captures_iter(tag.get(2).map_or("", |m| m.as_str()))
+            .map(|attribute| {
+                let (_, [name, value]) = attribute.extract();
+                let value = number.replace_all(value, |number: &Captures| {
+                    format!("{}{}", &number[1], round(number[2].parse().unwrap_or_default()))
+                });
+                (name, value.into_owned())
+            })
+            .collect();
+        attributes.sort();
+
+        normalized.push('<');
+        normalized.push_str(&tag[1]);
+        for (name, value) in attributes {
+            normalized.push_str(&format!(" {name}=\"{value}\""));
+        }
+        normalized.push_str(if tag[3].is_empty() { ">\n" } else { "/>\n" });
+    }
+    normalized
+}
+
+/// Rounds `value` to two decimal places, without a sign on zero.
+fn round(value: f64) -> f64 {
+    let rounded = (value * 100.0).round() / 100.0;
+    if rounded == 0.0 { 0.0 } else { rounded }
+}
+
+/// Normalizes both SVGs and finds the first line at which they differ, or `None` if they are the same.
+pub fn diff(expected: &str, actual: &str) -> Option<Mismatch> {
+    let (expected, actual) = (normalize_svg(expected), normalize_svg(actual));
+    let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (expected, actual) if expected != actual => {
+                return Some(Mismatch {
+                    line,
+                    expected: expected.map(str::to_string),
+                    actual: actual.map(str::to_string),
+                });
+            },
+            _ => {},
+        }
+    }
+    unreachable!("the lines of a string run out")
+}
+
+/// Renders `source` on a canvas of the given size and compares it against the SVG in the file at `golden`.
+/// If `RSLOGO_UPDATE_GOLDEN` is set, the normalized SVG is written to `golden` instead.
+///
+/// # Example
+///
+/// ```
+/// use rslogo::golden::{check_golden, render_svg, GoldenError};
+///
+/// let square = "PENDOWN REPEAT \"4 [ FORWARD \"10 TURN \"90 ]";
+/// let golden = std::env::temp_dir().join("rslogo-square.svg");
+/// std::fs::write(&golden, render_svg(square, 100, 100).unwrap()).unwrap();
+///
+/// assert!(check_golden(square, 100, 100, &golden).is_ok());
+/// let result = check_golden("PENDOWN FORWARD \"10", 100, 100, &golden);
+/// assert!(matches!(result, Err(GoldenError::Mismatch(..))));
+/// ```
+pub fn check_golden(source: &str, width: u32, height: u32, golden: impl AsRef<Path>) -> Result<(), GoldenError> {
+    let golden = golden.as_ref();
+    let actual = render_svg(source, width, height)?;
+    if std::env::var_os(UPDATE_VARIABLE).is_some() {
+        std::fs::write(golden, normalize_svg(&actual))?;
+        return Ok(());
+    }
+    let expected = std::fs::read_to_string(golden)?;
+    match diff(&expected, &actual) {
+        Some(mismatch) => Err(GoldenError::Mismatch(golden.to_path_buf(), mismatch)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,197 @@
+//! # Heatmap
+//!
+//! This module contains the `Heatmap` struct, which counts how many lines of a `DisplayList` pass through
+//! each cell of a grid laid over the canvas.
+//!
+//! A line is counted at most once per cell, however long it runs inside it, so the counts show how often
+//! each region was traversed. This is useful for analyzing space-filling curves, which should cover every
+//! cell evenly, and for estimating where a pen plotter's pen wears the paper the most.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::heatmap::Heatmap;
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let asts = parse_content("PENDOWN FORWARD \"20 BACK \"20 RIGHT \"90 FORWARD \"20").unwrap();
+//! let mut turtle = Turtle::new(100, 100);
+//! run(&asts, &mut turtle).unwrap();
+//!
+//! let heatmap = Heatmap::new(turtle.get_display_list(), 100, 100, 10.0);
+//! assert_eq!(heatmap.count(50.0, 45.0), 2);
+//! assert_eq!(heatmap.count(65.0, 50.0), 1);
+//! assert_eq!(heatmap.count(5.0, 5.0), 0);
+//! assert_eq!(heatmap.max(), 3);
+//! assert!(heatmap.to_svg().contains("<rect"));
+//! ```
+
+use std::fmt::Write;
+
+use crate::display::DisplayList;
+
+/// The number of lines that passed through each cell of a grid laid over the canvas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    width: u32,
+    height: u32,
+    cell_size: f32,
+    columns: usize,
+    counts: Vec<u32>,
+}
+
+impl Heatmap {
+    /// Counts the lines in `display_list` that pass through each `cell_size` by `cell_size` cell of a
+    /// canvas of the given size. Parts of lines that leave the canvas are not counted.
+    ///
+    /// Each line is clipped to the canvas and then walked from cell to cell, so the work for a line is
+    /// bounded by the number of cells it crosses, however far it runs off the canvas.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rslogo::heatmap::Heatmap;
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    ///
+    /// let asts = parse_content("PENDOWN TURN \"45 FORWARD \"1000000000").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    ///
+    /// let heatmap = Heatmap::new(turtle.get_display_list(), 100, 100, 10.0);
+    /// assert_eq!(heatmap.count(55.0, 45.0), 1);
+    /// assert_eq!(heatmap.count(95.0, 5.0), 1);
+    /// assert_eq!(heatmap.count(45.0, 45.0), 0);
+    /// ```
+    pub fn new(display_list: &DisplayList, width: u32, height: u32, cell_size: f32) -> Self {
+        let cell_size = cell_size.max(1.0);
+        let columns = (width as f32 / cell_size).ceil() as usize;
+        let rows = (height as f32 / cell_size).ceil() as usize;
+        let mut heatmap = Self { width, height, cell_size, columns, counts: vec![0; columns * rows] };
+
+        // Rounding to a thousandth of a pixel keeps the rounding error of a turn from tipping a line that runs
+        // along a cell boundary into the cells beside it.
+        let round = |(x, y): (f32, f32)| ((x * 1000.0).round() / 1000.0, (y * 1000.0).round() / 1000.0);
+        for line in display_list.lines() {
+            if let Some((start, end)) = heatmap.clip(round((line.x, line.y)), round(line.end())) {
+                heatmap.traverse(start, end);
+            }
+        }
+        heatmap
+    }
+
+    /// Clips the segment from `start` to `end` to the canvas, or returns `None` if none of it lies on the canvas.
+    fn clip(&self, start: (f32, f32), end: (f32, f32)) -> Option<((f32, f32), (f32, f32))> {
+        if self.counts.is_empty() || ![start.0, start.1, end.0, end.1].iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let (mut enter, mut leave) = (0.0f32, 1.0f32);
+        // Each edge as the direction towards it and the distance inside it, as in Liang-Barsky clipping.
+        // The far edges are exclusive, like the cells, so a line running along one is off the canvas.
+        let edges = [
+            (-dx, start.0, false),
+            (dx, self.width as f32 - start.0, true),
+            (-dy, start.1, false),
+            (dy, self.height as f32 - start.1, true),
+        ];
+        for (towards, inside, exclusive) in edges {
+            if towards == 0.0 {
+                if inside < 0.0 || (exclusive && inside == 0.0) {
+                    return None;
+                }
+            } else if towards < 0.0 {
+                enter = enter.max(inside / towards);
+            } else {
+                leave = leave.min(inside / towards);
+            }
+        }
+        (enter <= leave).then(|| {
+            let at = |t: f32| (start.0 + dx * t, start.1 + dy * t);
+            (at(enter), at(leave))
+        })
+    }
+
+    /// Adds one to the count of each cell crossed by the segment from `start` to `end`, which lies on the canvas,
+    /// stepping into whichever neighbouring cell the segment reaches first.
+    fn traverse(&mut self, start: (f32, f32), end: (f32, f32)) {
+        let rows = self.counts.len() / self.columns;
+        let index = |v: f32, cells: usize| ((v / self.cell_size) as usize).min(cells - 1);
+        let (mut column, mut row) = (index(start.0, self.columns), index(start.1, rows));
+        let (last_column, last_row) = (index(end.0, self.columns), index(end.1, rows));
+
+        // The fraction of the segment at which it next crosses a column or row boundary, and between crossings.
+        let boundary = |from: f32, delta: f32, cell: usize| {
+            if delta > 0.0 {
+                ((cell + 1) as f32 * self.cell_size - from) / delta
+            } else if delta < 0.0 {
+                (cell as f32 * self.cell_size - from) / delta
+            } else {
+                f32::INFINITY
+            }
+        };
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let (mut next_x, mut next_y) = (boundary(start.0, dx, column), boundary(start.1, dy, row));
+        let (step_x, step_y) = (self.cell_size / dx.abs(), self.cell_size / dy.abs());
+
+        self.counts[row * self.columns + column] += 1;
+        // Stepping exactly as many times as there are boundaries between the end cells keeps rounding
+        // from overshooting the last cell.
+        for _ in 0..column.abs_diff(last_column) + row.abs_diff(last_row) {
+            if row == last_row || (column != last_column && next_x < next_y) {
+                column = if last_column > column { column + 1 } else { column - 1 };
+                next_x += step_x;
+            } else {
+                row = if last_row > row { row + 1 } else { row - 1 };
+                next_y += step_y;
+            }
+            self.counts[row * self.columns + column] += 1;
+        }
+    }
+
+    /// Finds the index of the cell containing `(x, y)`, or `None` if it is off the canvas.
+    fn cell(&self, x: f32, y: f32) -> Option<usize> {
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        let column = (x / self.cell_size) as usize;
+        let row = (y / self.cell_size) as usize;
+        Some(row * self.columns + column)
+    }
+
+    /// Gets the number of lines that passed through the cell containing `(x, y)`.
+    pub fn count(&self, x: f32, y: f32) -> u32 {
+        self.cell(x, y).map_or(0, |cell| self.counts[cell])
+    }
+
+    /// Gets the highest count of any cell.
+    pub fn max(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Renders the heatmap as SVG source, shading each cell from blue towards red in proportion to its count,
+    /// so the cells with the highest count are pure red. Cells that were never traversed are left blank.
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+            self.width, self.height
+        );
+        let max = self.max();
+        for (cell, &count) in self.counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+            let heat = count as f32 / max as f32;
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{size}\" height=\"{size}\" fill=\"rgb({},0,{})\"/>",
+                (cell % self.columns) as f32 * self.cell_size,
+                (cell / self.columns) as f32 * self.cell_size,
+                (heat * 255.0).round(),
+                ((1.0 - heat) * 255.0).round(),
+                size = self.cell_size,
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
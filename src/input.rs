@@ -0,0 +1,60 @@
+//! # Input
+//!
+//! This module contains the `InputSource` trait, which `READWORD` asks for the next word the user typed in,
+//! so Logo programs can be interactive.
+//!
+//! A turtle reads from standard input until an `InputSource` is attached with `Turtle::set_input_source`.
+//! Any closure returning the next word, or `None` once there is no more input, is an `InputSource`. Once
+//! there is no more input, `READWORD` gives the empty word.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Mutex;
+//!
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let answers = Mutex::new(vec!["30".to_string(), "red".to_string()]);
+//! let mut turtle = Turtle::new(100, 100);
+//! turtle.set_input_source(Box::new(move || Ok(answers.lock().unwrap().pop())));
+//!
+//! run(&parse_content("SETPENCOLOR READWORD MAKE \"size READWORD SETX :size").unwrap(), &mut turtle).unwrap();
+//! assert_eq!(turtle.get_pen_color(), 4.0);
+//! assert_eq!(turtle.get_x(), 30.0);
+//! ```
+
+/// Gives `READWORD` the next word typed in.
+pub trait InputSource: Send + Sync {
+    /// Reads the next word, or returns `None` once there is no more input.
+    fn read_word(&self) -> std::io::Result<Option<String>>;
+}
+
+impl<F: Fn() -> std::io::Result<Option<String>> + Send + Sync> InputSource for F {
+    fn read_word(&self) -> std::io::Result<Option<String>> {
+        self()
+    }
+}
+
+/// An `InputSource` that reads a line of standard input for each word, which turtles use by default.
+pub struct StdinSource;
+
+impl InputSource for StdinSource {
+    fn read_word(&self) -> std::io::Result<Option<String>> {
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
+/// An `InputSource` with no input, for services that have no user to ask.
+pub struct NoInput;
+
+impl InputSource for NoInput {
+    fn read_word(&self) -> std::io::Result<Option<String>> {
+        Ok(None)
+    }
+}
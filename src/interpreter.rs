@@ -0,0 +1,189 @@
+//! # Interpreter
+//!
+//! This module contains the `Interpreter` struct, which gathers everything that configures how a Logo program
+//! is run: the canvas size, the `Limits`, the `Dialect`, the seed of `RANDOM`, the registered primitives, the
+//! arguments passed to programs, the sink that drawn lines are streamed to, and the `Transform` used to render
+//! them. Each run starts from a
+//! fresh turtle set up from this configuration, so the same interpreter can run any number of programs.
+//!
+//! Interpreters are put together with `Interpreter::builder`, where every setting has a default, so new
+//! settings can be added without changing the code of every host.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::interpreter::{Dialect, Interpreter};
+//! use rslogo::limits::Limits;
+//!
+//! let mut interpreter = Interpreter::builder(100, 100)
+//!     .limits(Limits { max_steps: Some(1000), ..Limits::default() })
+//!     .dialect(Dialect { integer_mode: true, ..Dialect::default() })
+//!     .seed(42)
+//!     .build();
+//!
+//! let image = interpreter.interpret("PENDOWN REPEAT \"4 [ FORWARD \"10 TURN \"90 ]").unwrap();
+//! assert_eq!(image.get_dimensions(), (100, 100));
+//!
+//! assert!(interpreter.interpret("WHILE EQ \"1 \"1 [ TURN \"1 ]").is_err());
+//! ```
+
+use unsvg::Image;
+
+use crate::ast::ASTNode;
+use crate::display::{LineSink, Transform};
+use crate::error::{Error, RuntimeError};
+use crate::limits::Limits;
+use crate::parser::parse_tokens;
+use crate::primitive::Primitives;
+use crate::runner::run;
+use crate::tokenizer::try_tokenize;
+use crate::turtle::{HeadingConvention, NamePolicy, Turtle};
+
+/// The rules of the Logo dialect a program is written in, where dialects disagree.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Dialect {
+    /// Whether numbers are truncated to whole numbers, as in dialects without fractions.
+    pub integer_mode: bool,
+
+    /// How far apart two numbers may be while still being compared as equal.
+    pub epsilon: f32,
+
+    /// Whether distances and coordinates are percentages of the canvas size rather than pixels.
+    pub percentage_mode: bool,
+
+    /// How headings map onto directions on the canvas.
+    pub heading_convention: HeadingConvention,
+
+    /// The rules the names of variables must follow.
+    pub name_policy: NamePolicy,
+}
+
+/// The configuration programs are run with, and the means of running them.
+pub struct Interpreter {
+    width: u32,
+    height: u32,
+    limits: Limits,
+    dialect: Dialect,
+    seed: Option<u64>,
+    primitives: Primitives,
+    arguments: Vec<String>,
+    line_sink: Option<Box<dyn LineSink>>,
+    transform: Transform,
+}
+
+impl Interpreter {
+    /// Creates an interpreter for a canvas of the given size, with every other setting left at its default.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::builder(width, height).build()
+    }
+
+    /// Starts building an interpreter for a canvas of the given size.
+    pub fn builder(width: u32, height: u32) -> InterpreterBuilder {
+        InterpreterBuilder {
+            interpreter: Self {
+                width,
+                height,
+                limits: Limits::default(),
+                dialect: Dialect::default(),
+                seed: None,
+                primitives: Primitives::new(),
+                arguments: Vec::new(),
+                line_sink: None,
+                transform: Transform::IDENTITY,
+            },
+        }
+    }
+
+    /// Creates a turtle set up with the limits, dialect, seed, primitives, and arguments of the interpreter, for
+    /// hosts that drive the turtle themselves. The line sink is not attached, as only one turtle can own it.
+    pub fn turtle(&self) -> Turtle {
+        let mut turtle = Turtle::with_limits(self.width, self.height, self.limits);
+        turtle.set_integer_mode(self.dialect.integer_mode);
+        turtle.set_epsilon(self.dialect.epsilon);
+        turtle.set_percentage_mode(self.dialect.percentage_mode);
+        turtle.set_heading_convention(self.dialect.heading_convention);
+        turtle.set_name_policy(self.dialect.name_policy);
+        if let Some(seed) = self.seed {
+            turtle.set_seed(seed);
+        }
+        turtle.set_primitives(self.primitives.clone());
+        // Arguments over the memory limit are still set, and the limit is reported by the next variable set.
+        let _ = turtle.set_arguments(&self.arguments);
+        turtle
+    }
+
+    /// Executes `instructions` on a fresh turtle, streaming its lines to the line sink, and returns an image
+    /// of what it drew, rendered with the interpreter's transform.
+    pub fn execute(&mut self, instructions: &[ASTNode]) -> Result<Image, RuntimeError> {
+        let mut turtle = self.turtle();
+        turtle.set_line_sink(self.line_sink.take());
+        let result = run(instructions, &mut turtle);
+        self.line_sink = turtle.take_line_sink();
+        result?;
+        let mut image = Image::new(self.width, self.height);
+        turtle.get_display_list().render_with(&mut image, self.transform).map_err(RuntimeError::Render)?;
+        Ok(image)
+    }
+
+    /// Parses `source`, with the interpreter's primitives, and executes it as `execute` does.
+    pub fn interpret(&mut self, source: &str) -> Result<Image, Error> {
+        let tokens = try_tokenize(source)?;
+        let instructions = parse_tokens(tokens, source.len(), &self.primitives)?;
+        Ok(self.execute(&instructions)?)
+    }
+}
+
+/// Sets up an `Interpreter` one setting at a time. Created by `Interpreter::builder`.
+pub struct InterpreterBuilder {
+    interpreter: Interpreter,
+}
+
+impl InterpreterBuilder {
+    /// Sets the resources each program may use. Defaults to no limits.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.interpreter.limits = limits;
+        self
+    }
+
+    /// Sets the dialect programs are written in. Defaults to `Dialect::default()`.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.interpreter.dialect = dialect;
+        self
+    }
+
+    /// Seeds `RANDOM`, so every run gives the same numbers. Defaults to seeding from the current time.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.interpreter.seed = Some(seed);
+        self
+    }
+
+    /// Sets the primitives programs may call. Defaults to none.
+    pub fn primitives(mut self, primitives: Primitives) -> Self {
+        self.interpreter.primitives = primitives;
+        self
+    }
+
+    /// Sets the arguments passed to programs, which read them as `:arg1`, `:arg2`, and so on, or as the list
+    /// `:runargs`. Defaults to none.
+    pub fn arguments<S: Into<String>>(mut self, arguments: impl IntoIterator<Item = S>) -> Self {
+        self.interpreter.arguments = arguments.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the sink that each line is sent to as it is drawn. Defaults to none.
+    pub fn line_sink(mut self, sink: Box<dyn LineSink>) -> Self {
+        self.interpreter.line_sink = Some(sink);
+        self
+    }
+
+    /// Sets the transform lines are rendered with. Defaults to `Transform::IDENTITY`.
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.interpreter.transform = transform;
+        self
+    }
+
+    /// Finishes building the interpreter.
+    pub fn build(self) -> Interpreter {
+        self.interpreter
+    }
+}
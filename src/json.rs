@@ -0,0 +1,43 @@
+//! # JSON
+//!
+//! This module converts errors into the JSON diagnostics reported by the network services, so every
+//! service describes problems in the same shape:
+//!
+//! ```text
+//! {"message":"Unexpected end of input","start":7,"end":7,"recoverable":false}
+//! ```
+//!
+//! `start` and `end` are byte offsets into the source, and are `null` when the problem has no location.
+
+use chumsky::error::Simple;
+use serde_json::{json, Value};
+
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+use crate::error::RuntimeError;
+use crate::tokenizer::Token;
+
+/// Describes a syntax error found by the parser.
+pub(crate) fn parse_diagnostic(error: &Simple<Token>) -> Value {
+    to_json(&Diagnostic::from_parse_error(error))
+}
+
+/// Describes an error raised while executing a program by the instruction at `span`, if it is known.
+pub(crate) fn runtime_diagnostic(error: &RuntimeError, span: Option<&Span>) -> Value {
+    to_json(&Diagnostic::from_runtime_error(error, span))
+}
+
+fn to_json(diagnostic: &Diagnostic) -> Value {
+    let span = diagnostic.span.as_ref();
+    json!({
+        "message": diagnostic.message,
+        "start": span.map(|span| span.start),
+        "end": span.map(|span| span.end),
+        "recoverable": diagnostic.recoverable,
+    })
+}
+
+/// Describes a problem with a request rather than with the program in it.
+pub(crate) fn request_diagnostic(message: &str) -> Value {
+    json!({ "message": message, "start": null, "end": null, "recoverable": false })
+}
@@ -0,0 +1,214 @@
+//! # Keywords
+//!
+//! This module describes every keyword of the language, with how it is written and a one line description,
+//! so tools can offer completions and help without parsing this crate's documentation. The REPL completes
+//! keywords from it, the language server sends it as completion items, and the Node.js bindings export it
+//! for web playgrounds.
+//!
+//! Primitives registered in a `Primitives` registry are described too, although only their arity is known.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::keywords::{keywords, KeywordKind};
+//! use rslogo::primitive::{Primitive, Primitives};
+//!
+//! struct Square;
+//!
+//! impl Primitive for Square {
+//!     fn arity(&self) -> usize {
+//!         1
+//!     }
+//! }
+//!
+//! let mut primitives = Primitives::new();
+//! primitives.register("SQUARE", Square);
+//! let keywords = keywords(&primitives);
+//!
+//! let forward = keywords.iter().find(|keyword| keyword.name == "FORWARD").unwrap();
+//! assert_eq!(forward.kind, KeywordKind::Command);
+//! assert_eq!(forward.signature, "FORWARD distance");
+//!
+//! let square = keywords.last().unwrap();
+//! assert_eq!(square.kind, KeywordKind::Primitive);
+//! assert_eq!(square.signature, "SQUARE arg1");
+//! ```
+
+use std::fmt;
+
+use crate::primitive::Primitives;
+
+/// How a keyword is used in a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordKind {
+    /// An instruction that acts on the turtle or the variables, such as `FORWARD`.
+    Command,
+
+    /// An expression that looks up a value, such as `XCOR`.
+    Query,
+
+    /// An expression that computes a value from its arguments, such as `SIN` or `EQ`.
+    Operator,
+
+    /// An instruction that decides which other instructions run, such as `IF`, or defines a procedure.
+    Control,
+
+    /// A command or query registered in a `Primitives` registry.
+    Primitive,
+}
+
+impl fmt::Display for KeywordKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeywordKind::Command => "command",
+            KeywordKind::Query => "query",
+            KeywordKind::Operator => "operator",
+            KeywordKind::Control => "control",
+            KeywordKind::Primitive => "primitive",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A keyword, with how it is written and what it does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyword {
+    /// The keyword, as it is written in a program.
+    pub name: String,
+
+    /// How the keyword is used.
+    pub kind: KeywordKind,
+
+    /// The keyword followed by the names of its arguments, such as `SETXY x y`.
+    pub signature: String,
+
+    /// A one line description of what the keyword does.
+    pub doc: String,
+}
+
+/// The built in keywords, as their name, kind, signature, and description.
+const BUILTINS: &[(&str, KeywordKind, &str, &str)] = &[
+    ("PENUP", KeywordKind::Command, "PENUP", "Lifts the pen up, so the turtle does not draw."),
+    ("PENDOWN", KeywordKind::Command, "PENDOWN", "Puts the pen down, so the turtle draws."),
+    ("PUSHSTATE", KeywordKind::Command, "PUSHSTATE", "Saves the turtle's position, heading, and pen configuration."),
+    ("POPSTATE", KeywordKind::Command, "POPSTATE", "Restores the state most recently saved with PUSHSTATE."),
+    ("FORWARD", KeywordKind::Command, "FORWARD distance", "Moves the turtle forward."),
+    ("BACK", KeywordKind::Command, "BACK distance", "Moves the turtle backward."),
+    ("LEFT", KeywordKind::Command, "LEFT distance", "Moves the turtle to its left, without turning."),
+    ("RIGHT", KeywordKind::Command, "RIGHT distance", "Moves the turtle to its right, without turning."),
+    ("MOVEPOLAR", KeywordKind::Command, "MOVEPOLAR angle distance", "Moves the turtle towards an absolute angle, without turning."),
+    ("SETPENCOLOR", KeywordKind::Command, "SETPENCOLOR color", "Sets the pen color to a color index or name."),
+    ("SETFILLCOLOR", KeywordKind::Command, "SETFILLCOLOR color", "Sets the fill color, without changing the pen color."),
+    ("TURN", KeywordKind::Command, "TURN degrees", "Turns the turtle clockwise."),
+    ("SETHEADING", KeywordKind::Command, "SETHEADING degrees", "Sets the heading of the turtle."),
+    ("SETX", KeywordKind::Command, "SETX x", "Sets the x-coordinate of the turtle."),
+    ("SETY", KeywordKind::Command, "SETY y", "Sets the y-coordinate of the turtle."),
+    ("SETSEED", KeywordKind::Command, "SETSEED seed", "Seeds the random number generator, so RANDOM can be reproduced."),
+    ("SETSPEED", KeywordKind::Command, "SETSPEED speed", "Sets the speed, in pixels per second, animations draw the following lines at."),
+    ("SETPENSIZE", KeywordKind::Command, "SETPENSIZE width", "Sets the width, in pixels, of the lines drawn from now on."),
+    ("FILLAT", KeywordKind::Command, "FILLAT x y", "Fills the region enclosed by lines around a point with the fill color."),
+    ("TOOT", KeywordKind::Command, "TOOT frequency duration", "Plays a tone of a frequency in hertz for a duration in milliseconds."),
+    ("WAIT", KeywordKind::Command, "WAIT ticks", "Pauses for a number of ticks, 60 to a second, before drawing the following lines."),
+    ("MARK", KeywordKind::Command, "MARK \"name", "Remembers the turtle's position and heading under a name."),
+    ("GOTO", KeywordKind::Command, "GOTO \"name", "Moves the turtle, without drawing, to a position remembered with MARK."),
+    ("SNAPSHOT", KeywordKind::Command, "SNAPSHOT \"name", "Saves a copy of the lines drawn so far under a name."),
+    ("RESTOREIMAGE", KeywordKind::Command, "RESTOREIMAGE \"name", "Replaces the lines drawn so far with a copy saved with SNAPSHOT."),
+    ("MAKE", KeywordKind::Command, "MAKE \"name value", "Sets a variable to a value."),
+    ("LOCAL", KeywordKind::Command, "LOCAL \"name", "Declares a variable of the innermost block or procedure."),
+    ("LOCALMAKE", KeywordKind::Command, "LOCALMAKE \"name value", "Declares a variable of the innermost block or procedure with a value."),
+    ("ADDASSIGN", KeywordKind::Command, "ADDASSIGN \"name value", "Adds a value to a variable."),
+    ("SUBASSIGN", KeywordKind::Command, "SUBASSIGN \"name value", "Subtracts a value from a variable."),
+    ("MULASSIGN", KeywordKind::Command, "MULASSIGN \"name value", "Multiplies a variable by a value."),
+    ("DIVASSIGN", KeywordKind::Command, "DIVASSIGN \"name value", "Divides a variable by a value."),
+    ("XCOR", KeywordKind::Query, "XCOR", "The x-coordinate of the turtle."),
+    ("YCOR", KeywordKind::Query, "YCOR", "The y-coordinate of the turtle."),
+    ("HEADING", KeywordKind::Query, "HEADING", "The heading of the turtle."),
+    ("COLOR", KeywordKind::Query, "COLOR", "The pen color of the turtle."),
+    ("FILLCOLOR", KeywordKind::Query, "FILLCOLOR", "The fill color of the turtle."),
+    ("SCREENWIDTH", KeywordKind::Query, "SCREENWIDTH", "The width of the canvas."),
+    ("SCREENHEIGHT", KeywordKind::Query, "SCREENHEIGHT", "The height of the canvas."),
+    ("REPCOUNT", KeywordKind::Query, "REPCOUNT", "The iteration of the innermost REPEAT, counting from 1."),
+    ("RANDOM", KeywordKind::Query, "RANDOM max", "A random whole number from 0 up to, but not including, max."),
+    ("PIXELCOLOR", KeywordKind::Query, "PIXELCOLOR x y", "The color drawn at a point, or -1 if nothing has been drawn there."),
+    ("IF", KeywordKind::Control, "IF condition [ instructions ]", "Runs the instructions if the condition is true."),
+    ("WHILE", KeywordKind::Control, "WHILE condition [ instructions ]", "Runs the instructions for as long as the condition is true."),
+    ("DO.WHILE", KeywordKind::Control, "DO.WHILE [ instructions ] condition", "Runs the instructions once, then again for as long as the condition is true."),
+    ("DO.UNTIL", KeywordKind::Control, "DO.UNTIL [ instructions ] condition", "Runs the instructions once, then again until the condition is true."),
+    ("REPEAT", KeywordKind::Control, "REPEAT count [ instructions ]", "Runs the instructions a number of times."),
+    ("DOTIMES", KeywordKind::Control, "DOTIMES \"variable count [ instructions ]", "Runs the instructions a number of times, with a variable counting from 0."),
+    ("FOR", KeywordKind::Control, "FOR \"variable start end step [ instructions ]", "Runs the instructions with a variable counting from start to end by step."),
+    ("CATCH", KeywordKind::Control, "CATCH \"tag [ instructions ]", "Runs the instructions, stopping a THROW of the same tag in them, or any recoverable error if the tag is ERROR."),
+    ("THROW", KeywordKind::Control, "THROW \"tag", "Stops everything up to the innermost CATCH with the tag."),
+    ("WHENKEY", KeywordKind::Control, "WHENKEY \"key [ instructions ]", "Runs the instructions each time a key is pressed."),
+    ("WHENCLICK", KeywordKind::Control, "WHENCLICK [ instructions ]", "Runs the instructions each time the canvas is clicked, at :clickx and :clicky."),
+    ("EVERYFRAME", KeywordKind::Control, "EVERYFRAME [ instructions ]", "Runs the instructions for each frame of an animation, counted by :frame."),
+    ("TO", KeywordKind::Control, "TO name :parameter ... instructions END", "Defines a procedure."),
+    ("END", KeywordKind::Control, "END", "Ends the definition of a procedure."),
+    ("EQ", KeywordKind::Operator, "EQ a b", "Whether two values are equal."),
+    ("NE", KeywordKind::Operator, "NE a b", "Whether two values are not equal."),
+    ("NEARLYEQ", KeywordKind::Operator, "NEARLYEQ a b", "Whether two numbers differ by at most the turtle's epsilon."),
+    ("LT", KeywordKind::Operator, "LT a b", "Whether a is less than b, comparing words alphabetically."),
+    ("GT", KeywordKind::Operator, "GT a b", "Whether a is greater than b, comparing words alphabetically."),
+    ("LE", KeywordKind::Operator, "LE a b", "Whether a is less than or equal to b."),
+    ("GE", KeywordKind::Operator, "GE a b", "Whether a is greater than or equal to b."),
+    ("AND", KeywordKind::Operator, "AND a b", "Whether both conditions are true."),
+    ("OR", KeywordKind::Operator, "OR a b", "Whether either condition is true."),
+    ("NOT", KeywordKind::Operator, "NOT condition", "Whether the condition is false."),
+    ("INSIDE?", KeywordKind::Operator, "INSIDE? [ x y width height ]", "Whether the turtle is inside a rectangle."),
+    ("NUMBERP", KeywordKind::Operator, "NUMBERP value", "Whether a value is a number."),
+    ("WORDP", KeywordKind::Operator, "WORDP value", "Whether a value is a word, which numbers are too."),
+    ("LISTP", KeywordKind::Operator, "LISTP value", "Whether a value is a list."),
+    ("EMPTYP", KeywordKind::Operator, "EMPTYP value", "Whether a value is the empty list or the empty word."),
+    ("MOD", KeywordKind::Operator, "MOD a b", "The remainder of dividing a by b, with the sign of b."),
+    ("REMAINDER", KeywordKind::Operator, "REMAINDER a b", "The remainder of dividing a by b, with the sign of a."),
+    ("POWER", KeywordKind::Operator, "POWER a b", "a raised to the power of b."),
+    ("SIN", KeywordKind::Operator, "SIN degrees", "The sine of an angle."),
+    ("COS", KeywordKind::Operator, "COS degrees", "The cosine of an angle."),
+    ("TAN", KeywordKind::Operator, "TAN degrees", "The tangent of an angle."),
+    ("SQRT", KeywordKind::Operator, "SQRT number", "The square root of a number."),
+    ("INT", KeywordKind::Operator, "INT number", "A number without its fractional part."),
+    ("ROUND", KeywordKind::Operator, "ROUND number", "A number rounded to the nearest whole number."),
+    ("ABS", KeywordKind::Operator, "ABS number", "The absolute value of a number."),
+    ("CLAMP", KeywordKind::Operator, "CLAMP value low high", "A value limited to a range."),
+    ("LERP", KeywordKind::Operator, "LERP from to fraction", "The number a fraction of the way from one number to another."),
+    ("MAP-RANGE", KeywordKind::Operator, "MAP-RANGE value low high new-low new-high", "A value mapped from one range onto another."),
+    ("FIRST", KeywordKind::Operator, "FIRST list", "The first item of a list, or character of a word."),
+    ("BUTFIRST", KeywordKind::Operator, "BUTFIRST list", "Every item of a list, or character of a word, but the first."),
+    ("BF", KeywordKind::Operator, "BF list", "Short for BUTFIRST."),
+    ("LAST", KeywordKind::Operator, "LAST list", "The last item of a list, or character of a word."),
+    ("ITEM", KeywordKind::Operator, "ITEM index list", "The item of a list, or character of a word, at an index counting from 1."),
+    ("COUNT", KeywordKind::Operator, "COUNT list", "The number of items in a list, or characters in a word."),
+    ("WORD", KeywordKind::Operator, "WORD a b", "Two words joined into one."),
+    ("SENTENCE", KeywordKind::Operator, "SENTENCE a b", "Words and lists joined into one list."),
+    ("SE", KeywordKind::Operator, "SE a b", "Short for SENTENCE."),
+    ("CHAR", KeywordKind::Operator, "CHAR code", "The character with a Unicode code point."),
+    ("READWORD", KeywordKind::Query, "READWORD", "The next word the user types in."),
+    ("RW", KeywordKind::Query, "RW", "Short for READWORD."),
+];
+
+/// Describes every built in keyword, followed by the primitives registered in `primitives` in alphabetical order.
+pub fn keywords(primitives: &Primitives) -> Vec<Keyword> {
+    let builtins = BUILTINS.iter().map(|&(name, kind, signature, doc)| Keyword {
+        name: name.to_string(),
+        kind,
+        signature: signature.to_string(),
+        doc: doc.to_string(),
+    });
+    let mut names: Vec<&str> = primitives.names().collect();
+    names.sort_unstable();
+    let registered = names.into_iter().filter_map(|name| {
+        let primitive = primitives.get(name)?;
+        let mut signature = name.to_string();
+        for i in 1..=primitive.arity() {
+            signature.push_str(&format!(" arg{i}"));
+        }
+        let doc = if primitive.is_query() { "A registered query." } else { "A registered command." };
+        Some(Keyword { name: name.to_string(), kind: KeywordKind::Primitive, signature, doc: doc.to_string() })
+    });
+    builtins.chain(registered).collect()
+}
+
+/// Whether `name` is a built in keyword, as it is written in a program.
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.iter().any(|&(builtin, ..)| builtin == name)
+}
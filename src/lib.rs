@@ -3,19 +3,139 @@
 //! This crate provides a Logo interpreter written in Rust. 
 //! It includes a parser, tokenizer, and an abstract syntax tree (AST) for the Logo language.
 //! 
+//! The parse, execute and render phases emit `tracing` spans, and each executed instruction emits a
+//! `trace` level event, so embedders can observe the interpreter with their existing subscriber.
+//! 
 //! 
 
 /// The abstract syntax tree (AST) for the Logo language.
 pub mod ast;
 
+/// The sink that plays the tones of `TOOT`.
+pub mod audio;
+
+/// The audit log of executed commands.
+pub mod audit;
+
+/// The token that lets a host cancel a running program.
+pub mod cancel;
+
+/// The clock that `WAIT` delays programs with.
+pub mod clock;
+
+/// Human-readable reports of the errors found in Logo programs.
+pub mod diagnostics;
+
+/// The display list of lines drawn by the turtle.
+pub mod display;
+
+/// The errors raised while executing Logo programs.
+pub mod error;
+
+/// Formatting of Logo source code, one instruction per line.
+pub mod formatter;
+
+/// Helpers for comparing the SVG a program renders to against a stored golden file.
+pub mod golden;
+
+/// Conversion of errors into the JSON diagnostics reported by the network services.
+#[cfg(any(feature = "serve", feature = "websocket"))]
+pub(crate) mod json;
+
+/// Heatmaps of how often each region of the canvas was drawn over.
+pub mod heatmap;
+
+/// The source of the words read by `READWORD`.
+pub mod input;
+
+/// The configuration Logo programs are run with, gathered in one place.
+pub mod interpreter;
+
+/// Descriptions of the keywords of the language, for completions and help.
+pub mod keywords;
+
+/// Static checks for likely mistakes in Logo programs.
+pub mod lint;
+
+/// The resource limits placed on Logo programs.
+pub mod limits;
+
+/// The language server for editors, built with the `lsp` feature.
+#[cfg(feature = "lsp")]
+pub mod lsp;
+
+/// Measurements of the structure of Logo programs, for automated grading.
+pub mod metrics;
+
+/// Node.js bindings, built with the `node` feature.
+#[cfg(feature = "node")]
+pub mod node;
+
+/// The operations executing the AST performs on a turtle, so it can be replaced by a mock.
+pub mod ops;
+
+/// Optimization passes over the abstract syntax tree.
+pub mod optimizer;
+
 /// The parser for the Logo language.
 pub mod parser;
 
+/// The pragmas that set up the canvas a Logo program is drawn on.
+pub mod pragma;
+
+/// Sending drawings to pen plotters and turtle robots.
+pub mod plotter;
+
+/// The registry of primitives added from outside of this crate.
+pub mod primitive;
+
+/// Parsed Logo programs, ready to be run.
+pub mod program;
+
+/// The read-eval-print loop that runs Logo one line at a time.
+pub mod repl;
+
+/// Reports of the resources used by each run of a Logo program.
+pub mod report;
+
+/// Functions for running whole Logo programs, synchronously or asynchronously.
+pub mod runner;
+
+/// The HTTP rendering service, built with the `serve` feature.
+#[cfg(feature = "serve")]
+pub mod serve;
+
+/// Sessions that run a program piece by piece and render incrementally.
+pub mod session;
+
+/// Scores of how alike two Logo programs are, for finding near-duplicate submissions.
+pub mod similarity;
+
+/// The WebSocket service that streams lines as they are drawn, built with the `websocket` feature.
+#[cfg(feature = "websocket")]
+pub mod stream;
+
+/// The symbol table of the variables and procedures named in a Logo program.
+pub mod symbols;
+
+/// Property testing strategies for Logo programs, built with the `testing` feature.
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// The turtle graphics engine for the Logo language.
 pub mod turtle;
 
+/// Conversion of the abstract syntax tree back into Logo source code.
+pub mod unparser;
+
+/// The values that expressions evaluate to while a Logo program runs.
+pub mod value;
+
 /// The uncertain boolean type.
 pub(crate) mod uncertain_bool;
 
 /// The tokenizer for the Logo language.
 pub mod tokenizer;
+
+pub use error::Error;
+pub use runner::interpret;
@@ -0,0 +1,175 @@
+//! # Limits
+//!
+//! This module contains the `Limits` struct, which bounds the resources a Logo program may use.
+//! It is accepted by `Turtle::with_limits`, so hosting untrusted programs only requires configuring one object.
+//!
+//! Every limit is optional. `Limits::default()` only bounds the call depth, to `DEFAULT_MAX_CALL_DEPTH`, so that
+//! runaway recursion stops with an error instead of overflowing the host's stack, and `Limits::unlimited()` places
+//! no bounds on the program at all. When a limit is exceeded, execution stops with `RuntimeError::LimitExceeded`.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use rslogo::error::RuntimeError;
+//! use rslogo::limits::{Limit, Limits};
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let limits = Limits {
+//!     max_steps: Some(1000),
+//!     max_time: Some(Duration::from_secs(1)),
+//!     ..Limits::default()
+//! };
+//!
+//! let mut turtle = Turtle::with_limits(100, 100, limits);
+//!
+//! let asts = parse_content("WHILE EQ \"1 \"1 [ TURN \"1 ]").unwrap();
+//! let result = asts[0].execute(&mut turtle);
+//!
+//! assert!(matches!(result, Err(RuntimeError::LimitExceeded(Limit::Steps))));
+//!
+//! let mut turtle = Turtle::new(100, 100);
+//! let asts = parse_content("TO F F END F").unwrap();
+//! let result = run(&asts, &mut turtle);
+//! assert!(matches!(result, Err(RuntimeError::StackOverflow(100))));
+//! ```
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The number of procedure calls that `Limits::default()` allows to be in progress at once.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100;
+
+/// The resources a Logo program may use while it is executed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// The maximum number of instructions that may be executed.
+    pub max_steps: Option<u64>,
+
+    /// The maximum amount of wall-clock time execution may take.
+    pub max_time: Option<Duration>,
+
+    /// The maximum number of bytes that may be used to store variables, saved turtle states, marks, and snapshots.
+    pub max_memory: Option<usize>,
+
+    /// The maximum number of lines that may be drawn on the image.
+    pub max_image_operations: Option<u64>,
+
+    /// The maximum depth that blocks may be nested to while executing.
+    pub max_recursion_depth: Option<usize>,
+
+    /// The maximum number of procedure calls that may be in progress at once, such as when a procedure calls
+    /// itself. Exceeding it stops execution with `RuntimeError::StackOverflow` rather than `LimitExceeded`,
+    /// before runaway recursion can overflow the host's own stack.
+    pub max_call_depth: Option<usize>,
+
+    /// The maximum number of times a single `WHILE`, `DO.WHILE`, or `DO.UNTIL` loop may run its block, however
+    /// many steps that takes. Exceeding it stops execution with `RuntimeError::TooManyIterations`, naming the loop,
+    /// so a condition that never becomes false is reported where it is rather than as a global limit.
+    pub max_loop_iterations: Option<u64>,
+}
+
+/// Identifies which of the `Limits` was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// The `max_steps` limit.
+    Steps,
+
+    /// The `max_time` limit.
+    Time,
+
+    /// The `max_memory` limit.
+    Memory,
+
+    /// The `max_image_operations` limit.
+    ImageOperations,
+
+    /// The `max_recursion_depth` limit.
+    RecursionDepth,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps => write!(f, "step limit"),
+            Limit::Time => write!(f, "time limit"),
+            Limit::Memory => write!(f, "memory limit"),
+            Limit::ImageOperations => write!(f, "image operation limit"),
+            Limit::RecursionDepth => write!(f, "recursion depth limit"),
+        }
+    }
+}
+
+/// Tracks the resources a program has used so far, to be checked against its `Limits`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Usage {
+    pub(crate) steps: u64,
+    pub(crate) started: Option<Instant>,
+    pub(crate) image_operations: u64,
+    pub(crate) depth: usize,
+    pub(crate) calls: usize,
+    pub(crate) peak_variables: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self { max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH), ..Self::unlimited() }
+    }
+}
+
+impl Limits {
+    /// Places no bounds on the program at all, not even on the call depth, for hosts that trust the programs
+    /// they run and give them a large enough stack.
+    pub fn unlimited() -> Self {
+        Self {
+            max_steps: None,
+            max_time: None,
+            max_memory: None,
+            max_image_operations: None,
+            max_recursion_depth: None,
+            max_call_depth: None,
+            max_loop_iterations: None,
+        }
+    }
+
+    /// Records an executed instruction, checking the step and time limits.
+    pub(crate) fn step(&self, usage: &mut Usage) -> Result<(), Limit> {
+        usage.steps += 1;
+        let started = *usage.started.get_or_insert_with(Instant::now);
+        if self.max_steps.is_some_and(|max| usage.steps > max) {
+            return Err(Limit::Steps);
+        }
+        if self.max_time.is_some_and(|max| started.elapsed() > max) {
+            return Err(Limit::Time);
+        }
+        Ok(())
+    }
+
+    /// Records a line drawn on the image, checking the image operation limit.
+    pub(crate) fn image_operation(&self, usage: &mut Usage) -> Result<(), Limit> {
+        usage.image_operations += 1;
+        if self.max_image_operations.is_some_and(|max| usage.image_operations > max) {
+            return Err(Limit::ImageOperations);
+        }
+        Ok(())
+    }
+
+    /// Records entering a nested block, checking the recursion depth limit.
+    pub(crate) fn enter(&self, usage: &mut Usage) -> Result<(), Limit> {
+        usage.depth += 1;
+        if self.max_recursion_depth.is_some_and(|max| usage.depth > max) {
+            return Err(Limit::RecursionDepth);
+        }
+        Ok(())
+    }
+
+    /// Checks that `memory` bytes of variables, saved states, marks, and snapshots is within the memory limit.
+    pub(crate) fn memory(&self, memory: usize) -> Result<(), Limit> {
+        if self.max_memory.is_some_and(|max| memory > max) {
+            return Err(Limit::Memory);
+        }
+        Ok(())
+    }
+}
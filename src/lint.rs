@@ -0,0 +1,283 @@
+//! # Lint
+//!
+//! This module contains static checks that flag programs which parse correctly but are almost certainly wrong.
+//!
+//! `lint` looks for `WHILE` loops whose condition cannot change while the loop runs: it only refers to
+//! variables the body never assigns and queries the body never affects. Once such a loop is entered it can
+//! never end, which would otherwise only be noticed when the step limit trips. Registered primitives could
+//! change anything, so loops that call them, or conditions that use them, are never flagged.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::lint::lint;
+//! use rslogo::parser::parse_content;
+//!
+//! let asts = parse_content("MAKE \"i \"0 WHILE LT :i \"10 [ FORWARD \"1 ]").unwrap();
+//! let warnings = lint(&asts);
+//! assert_eq!(warnings.len(), 1);
+//!
+//! let asts = parse_content("MAKE \"i \"0 WHILE LT :i \"10 [ ADDASSIGN \"i \"1 ]").unwrap();
+//! assert!(lint(&asts).is_empty());
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::ast::{ASTNode, Condition, ControlFlow, Expression, ListOp, Math, Procedure, Query};
+
+/// A likely mistake found in a program without running it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A `WHILE` loop with this condition never changes anything the condition depends on.
+    ConstantWhileCondition(Expression),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ConstantWhileCondition(condition) => write!(
+                f,
+                "The body of WHILE {condition} never changes its condition, so the loop will never end once entered"
+            ),
+        }
+    }
+}
+
+/// Checks `instructions`, including those nested in blocks, returning every warning in program order.
+pub fn lint(instructions: &[ASTNode]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    lint_block(instructions, &mut warnings);
+    warnings
+}
+
+fn lint_block(instructions: &[ASTNode], warnings: &mut Vec<Warning>) {
+    for instruction in instructions {
+        match instruction {
+            ASTNode::Procedure(..) => {},
+            ASTNode::ControlFlow(
+                ControlFlow::If { block, .. }
+                | ControlFlow::Repeat { block, .. }
+                | ControlFlow::For { block, .. }
+                | ControlFlow::DoTimes { block, .. }
+                | ControlFlow::DoWhile { block, .. }
+                | ControlFlow::Until { block, .. }
+                | ControlFlow::To { block, .. }
+                | ControlFlow::WhenKey { block, .. }
+                | ControlFlow::Catch { block, .. }
+                | ControlFlow::WhenClick { block }
+                | ControlFlow::EveryFrame { block },
+                _,
+            ) => lint_block(block, warnings),
+            ASTNode::ControlFlow(ControlFlow::While { condition, block }, _) => {
+                let mut dependencies = State::default();
+                dependencies.read(condition);
+                let mut effects = State::default();
+                effects.write_block(block);
+                if !dependencies.overlaps(&effects) {
+                    warnings.push(Warning::ConstantWhileCondition(condition.clone()));
+                }
+                lint_block(block, warnings);
+            },
+        }
+    }
+}
+
+/// A set of the turtle's state, either read by a condition or written by a block.
+#[derive(Default)]
+struct State {
+    variables: HashSet<String>,
+    queries: Vec<Query>,
+    /// Whether the lines drawn so far are read or written.
+    drawing: bool,
+    unknown: bool,
+}
+
+impl State {
+    fn overlaps(&self, other: &State) -> bool {
+        self.unknown
+            || other.unknown
+            || self.variables.iter().any(|variable| other.variables.contains(variable))
+            || self.queries.iter().any(|query| other.queries.contains(query))
+            || self.drawing && other.drawing
+    }
+
+    fn query(&mut self, query: Query) {
+        if !self.queries.contains(&query) {
+            self.queries.push(query);
+        }
+    }
+
+    /// Adds the state `expression` reads.
+    fn read(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Float(_) | Expression::String(_) => {},
+            Expression::Variable(name) => {
+                self.variables.insert(name.clone());
+            },
+            // A different number is returned every time, so a condition using it can change on its own.
+            Expression::Query(Query::Random(_)) => self.unknown = true,
+            Expression::Query(Query::PixelColor(x, y)) => {
+                self.read(x);
+                self.read(y);
+                self.drawing = true;
+            },
+            Expression::Query(query) => self.query(query.clone()),
+            Expression::Math(math) => match math.as_ref() {
+                Math::Add(lhs, rhs)
+                | Math::Sub(lhs, rhs)
+                | Math::Mul(lhs, rhs)
+                | Math::Div(lhs, rhs)
+                | Math::Mod(lhs, rhs)
+                | Math::Remainder(lhs, rhs)
+                | Math::Power(lhs, rhs) => {
+                    self.read(lhs);
+                    self.read(rhs);
+                },
+                Math::Sin(expr)
+                | Math::Cos(expr)
+                | Math::Tan(expr)
+                | Math::Sqrt(expr)
+                | Math::Int(expr)
+                | Math::Round(expr)
+                | Math::Abs(expr) => self.read(expr),
+                Math::Clamp(a, b, c) | Math::Lerp(a, b, c) => {
+                    for expr in [a, b, c] {
+                        self.read(expr);
+                    }
+                },
+                Math::MapRange(a, b, c, d, e) => {
+                    for expr in [a, b, c, d, e] {
+                        self.read(expr);
+                    }
+                },
+            },
+            Expression::Bool(condition) => self.read_condition(condition),
+            Expression::Call(..) | Expression::ReadWord => self.unknown = true,
+            Expression::Shared(shared) => self.read(&shared.expression),
+            Expression::List(items) => items.iter().for_each(|item| self.read(item)),
+            Expression::ListOp(op) => match op.as_ref() {
+                ListOp::First(list) | ListOp::ButFirst(list) | ListOp::Last(list) | ListOp::Count(list) | ListOp::Char(list) => {
+                    self.read(list)
+                },
+                ListOp::Item(first, second) | ListOp::Word(first, second) | ListOp::Sentence(first, second) => {
+                    self.read(first);
+                    self.read(second);
+                },
+            },
+        }
+    }
+
+    fn read_condition(&mut self, condition: &Condition) {
+        match condition {
+            Condition::Equal(lhs, rhs)
+            | Condition::NotEqual(lhs, rhs)
+            | Condition::NearlyEqual(lhs, rhs)
+            | Condition::LessThan(lhs, rhs)
+            | Condition::GreaterThan(lhs, rhs)
+            | Condition::LessEqual(lhs, rhs)
+            | Condition::GreaterEqual(lhs, rhs) => {
+                self.read(lhs);
+                self.read(rhs);
+            },
+            Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+                self.read_condition(lhs);
+                self.read_condition(rhs);
+            },
+            Condition::Not(condition) => self.read_condition(condition),
+            Condition::Inside(rect) => {
+                self.read(rect);
+                self.query(Query::XCOR);
+                self.query(Query::YCOR);
+            },
+            Condition::IsNumber(value)
+            | Condition::IsWord(value)
+            | Condition::IsList(value)
+            | Condition::IsEmpty(value) => self.read(value),
+        }
+    }
+
+    /// Adds the state that executing `block` may write.
+    fn write_block(&mut self, block: &[ASTNode]) {
+        for instruction in block {
+            match instruction {
+                ASTNode::Procedure(procedure, _) => self.write(procedure),
+                ASTNode::ControlFlow(
+                    ControlFlow::If { block, .. }
+                    | ControlFlow::While { block, .. }
+                    | ControlFlow::DoWhile { block, .. }
+                    | ControlFlow::Until { block, .. }
+                    | ControlFlow::Repeat { block, .. }
+                    | ControlFlow::Catch { block, .. },
+                    _,
+                ) => self.write_block(block),
+                ASTNode::ControlFlow(
+                    ControlFlow::For { variable, block, .. } | ControlFlow::DoTimes { variable, block, .. },
+                    _,
+                ) => {
+                    self.write_variable(variable);
+                    self.write_block(block);
+                },
+                // A definition only runs its block when the procedure is called, which is a `Call`, and a
+                // handler only runs its block when the host reports an event, outside of any loop.
+                ASTNode::ControlFlow(
+                    ControlFlow::To { .. }
+                    | ControlFlow::WhenKey { .. }
+                    | ControlFlow::WhenClick { .. }
+                    | ControlFlow::EveryFrame { .. },
+                    _,
+                ) => {},
+            }
+        }
+    }
+
+    fn write(&mut self, procedure: &Procedure) {
+        match procedure {
+            Procedure::PenUp | Procedure::PenDown | Procedure::Mark(_) | Procedure::Snapshot(_) | Procedure::PushState | Procedure::SetSeed(_) | Procedure::SetSpeed(_) | Procedure::SetPenSize(_) | Procedure::Toot(..) | Procedure::Wait(_) => {},
+            Procedure::Forward(_)
+            | Procedure::Back(_)
+            | Procedure::Left(_)
+            | Procedure::Right(_)
+            | Procedure::MovePolar(..) => {
+                self.query(Query::XCOR);
+                self.query(Query::YCOR);
+                self.drawing = true;
+            },
+            Procedure::FillAt(..) | Procedure::RestoreImage(_) => self.drawing = true,
+            Procedure::SetX(_) => self.query(Query::XCOR),
+            Procedure::SetY(_) => self.query(Query::YCOR),
+            Procedure::Turn(_) | Procedure::SetHeading(_) => self.query(Query::HEADING),
+            Procedure::SetPenColor(_) => self.query(Query::COLOR),
+            Procedure::SetFillColor(_) => self.query(Query::FILLCOLOR),
+            Procedure::Goto(_) => {
+                for query in [Query::XCOR, Query::YCOR, Query::HEADING] {
+                    self.query(query);
+                }
+            },
+            Procedure::PopState => {
+                for query in [Query::XCOR, Query::YCOR, Query::HEADING, Query::COLOR, Query::FILLCOLOR] {
+                    self.query(query);
+                }
+            },
+            Procedure::Make(name, _)
+            | Procedure::Local(name)
+            | Procedure::LocalMake(name, _)
+            | Procedure::AddAssign(name, _)
+            | Procedure::SubAssign(name, _)
+            | Procedure::MulAssign(name, _)
+            | Procedure::DivAssign(name, _) => self.write_variable(name),
+            // Leaving a loop early by throwing ends it whatever its condition is.
+            Procedure::Call(..) | Procedure::Throw(_) => self.unknown = true,
+        }
+    }
+
+    /// Adds the variable named by `name`, or records that any variable may be written if the name is not known.
+    fn write_variable(&mut self, name: &Expression) {
+        match name {
+            Expression::String(name) => {
+                self.variables.insert(name.clone());
+            },
+            _ => self.unknown = true,
+        }
+    }
+}
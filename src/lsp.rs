@@ -0,0 +1,208 @@
+//! # LSP
+//!
+//! This module contains a small language server, so editors can jump around larger Logo projects. It is only
+//! compiled with the `lsp` feature, and is started with `rslogo lsp`, which speaks the Language Server
+//! Protocol over standard input and output.
+//!
+//! The server keeps the text of each open document and answers `textDocument/definition` and
+//! `textDocument/references` for variables, parameters, and procedures, using the `SymbolTable` of the
+//! document. `textDocument/completion` offers every keyword and the procedures defined in the document. Positions are converted between the protocol's UTF-16 lines and columns and byte offsets.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::lsp::serve;
+//!
+//! fn frame(message: &str) -> String {
+//!     format!("Content-Length: {}\r\n\r\n{message}", message.len())
+//! }
+//!
+//! let input = [
+//!     frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#),
+//!     frame(r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lg","text":"MAKE \"x \"1\nFORWARD :x"}}}"#),
+//!     frame(r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.lg"},"position":{"line":1,"character":9}}}"#),
+//!     frame(r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/completion","params":{"textDocument":{"uri":"file:///a.lg"},"position":{"line":1,"character":2}}}"#),
+//!     frame(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+//! ].concat();
+//!
+//! let mut output = Vec::new();
+//! serve(input.as_bytes(), &mut output).unwrap();
+//! let output = String::from_utf8(output).unwrap();
+//!
+//! assert!(output.contains(r#""definitionProvider":true"#));
+//! assert!(output.contains(r#""range":{"end":{"character":7,"line":0},"start":{"character":5,"line":0}}"#));
+//! assert!(output.contains(r#""detail":"FORWARD distance""#));
+//! ```
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::ast::Span;
+use crate::keywords::{keywords, KeywordKind};
+use crate::primitive::Primitives;
+use crate::symbols::{Symbol, SymbolKind, SymbolTable};
+
+/// Answers the requests read from `input`, writing the responses to `output`, until the client sends `exit`
+/// or closes `input`.
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> std::io::Result<()> {
+    let mut documents: HashMap<String, String> = HashMap::new();
+    while let Some(message) = read_message(&mut input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        tracing::debug!(method, "received message");
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "rslogo" },
+            })),
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                if let (Some(uri), Some(text)) = (document["uri"].as_str(), document["text"].as_str()) {
+                    documents.insert(uri.to_string(), text.to_string());
+                }
+                continue;
+            },
+            "textDocument/didChange" => {
+                // Only full synchronization is offered, so the last change holds the whole text.
+                let text = params["contentChanges"].as_array().and_then(|changes| changes.last());
+                if let (Some(uri), Some(text)) = (params["textDocument"]["uri"].as_str(), text.and_then(|text| text["text"].as_str())) {
+                    documents.insert(uri.to_string(), text.to_string());
+                }
+                continue;
+            },
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    documents.remove(uri);
+                }
+                continue;
+            },
+            "textDocument/definition" => Ok(locations(&documents, params, |symbol| symbol.definitions.clone())),
+            "textDocument/references" => {
+                let include_declaration = params["context"]["includeDeclaration"].as_bool().unwrap_or(false);
+                Ok(locations(&documents, params, |symbol| {
+                    let mut spans = symbol.uses.clone();
+                    if include_declaration {
+                        spans.extend(symbol.definitions.iter().cloned());
+                        spans.sort_by_key(|span| span.start);
+                    }
+                    spans
+                }))
+            },
+            "textDocument/completion" => Ok(completions(&documents, params)),
+            "shutdown" => Ok(Value::Null),
+            "exit" => return Ok(()),
+            _ => Err(json!({ "code": -32601, "message": format!("Unsupported method {method}") })),
+        };
+        // Notifications have no id and must not be answered.
+        let Some(id) = message.get("id") else {
+            continue;
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+        };
+        write_message(&mut output, &response)?;
+    }
+    Ok(())
+}
+
+/// Finds the symbol at the position in `params`, and the locations of the spans `spans` picks from it.
+fn locations(
+    documents: &HashMap<String, String>,
+    params: &Value,
+    spans: impl Fn(&Symbol) -> Vec<Span>,
+) -> Value {
+    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+    let Some(source) = documents.get(uri) else {
+        return Value::Null;
+    };
+    let line = params["position"]["line"].as_u64().unwrap_or_default() as usize;
+    let character = params["position"]["character"].as_u64().unwrap_or_default() as usize;
+    let symbols = SymbolTable::from_source(source);
+    let Some(symbol) = symbols.at(offset(source, line, character)) else {
+        return Value::Null;
+    };
+    spans(symbol).into_iter()
+        .map(|span| json!({
+            "uri": uri,
+            "range": { "start": position(source, span.start), "end": position(source, span.end) },
+        }))
+        .collect()
+}
+
+/// Lists the keywords, and the procedures defined in the document in `params`, as completion items.
+fn completions(documents: &HashMap<String, String>, params: &Value) -> Value {
+    let keywords = keywords(&Primitives::new()).into_iter().map(|keyword| json!({
+        "label": keyword.name,
+        // The kinds are `Keyword` and `Function`.
+        "kind": if keyword.kind == KeywordKind::Primitive { 3 } else { 14 },
+        "detail": keyword.signature,
+        "documentation": keyword.doc,
+    }));
+    let source = documents.get(params["textDocument"]["uri"].as_str().unwrap_or_default());
+    let symbols = source.map(|source| SymbolTable::from_source(source)).unwrap_or_default();
+    let procedures = symbols.symbols().iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Procedure && !symbol.definitions.is_empty())
+        .map(|symbol| json!({ "label": symbol.name, "kind": 3 }));
+    keywords.chain(procedures).collect()
+}
+
+/// Converts a zero-based line and UTF-16 column into a byte offset into `source`.
+fn offset(source: &str, line: usize, character: usize) -> usize {
+    let start: usize = source.split_inclusive('\n').take(line).map(str::len).sum();
+    let mut units = 0;
+    for (index, c) in source[start..].char_indices() {
+        if units >= character || c == '\n' {
+            return start + index;
+        }
+        units += c.len_utf16();
+    }
+    source.len()
+}
+
+/// Converts a byte offset into `source` into a zero-based line and UTF-16 column.
+fn position(source: &str, offset: usize) -> Value {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count();
+    let character: usize = before.rsplit('\n').next().unwrap_or_default().chars().map(char::len_utf16).sum();
+    json!({ "line": line, "character": character })
+}
+
+/// Reads a message framed with a `Content-Length` header, or `None` once `input` is closed.
+fn read_message(input: &mut impl BufRead) -> std::io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Missing Content-Length header"));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(std::io::Error::other)
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
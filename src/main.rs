@@ -1,69 +1,776 @@
 use clap::Parser;
-use rslogo::{parser::parse_content, turtle::Turtle};
+use rslogo::{error::Error, audit::WriterSink, display::{DisplayList, Transform}, diagnostics::{Diagnostic, Severity}, formatter::format_source, heatmap::Heatmap, limits::Limits, lint::lint, optimizer::eliminate_common_subexpressions, parser::parse_tokens, pragma::Pragmas, primitive::Primitives, report::{PhaseTimes, ResourceReport}, runner::run, symbols::SymbolTable, tokenizer::tokenize, turtle::{HeadingConvention, Turtle}};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 use unsvg::Image;
 
-/// A simple program to parse four arguments using clap.
+/// An interpreter for the Logo programming language, which saves what programs draw as images.
 #[derive(Parser)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Exit codes:\n  0  Success\n  1  The program has an error\n  2  The arguments are invalid\n  3  A file could not be read or written",
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to a file
-    file_path: std::path::PathBuf,
+    #[arg(required = true)]
+    file_path: Option<std::path::PathBuf>,
 
     /// Path to an svg or png image
-    image_path: std::path::PathBuf,
+    #[arg(required = true)]
+    image_path: Option<std::path::PathBuf>,
 
     /// Height
-    height: u32,
+    #[arg(required = true)]
+    height: Option<u32>,
 
     /// Width
-    width: u32,
+    #[arg(required = true)]
+    width: Option<u32>,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+/// The options for running a program and rendering what it draws.
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// Round all endpoints to a grid of this size before drawing
+    #[arg(long)]
+    grid: Option<f32>,
+
+    /// Interpret distances and coordinates as percentages of the canvas size
+    #[arg(long)]
+    percent: bool,
+
+    /// Make positive turns and headings counter-clockwise instead of clockwise
+    #[arg(long)]
+    counter_clockwise: bool,
+
+    /// Direction that heading 0 points in, in degrees clockwise from straight up
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    heading_zero: f32,
+
+    /// Pass an argument to the program, which reads them as :arg1, :arg2, and so on in order, or as the list :runargs
+    #[arg(long = "arg", value_name = "VALUE", allow_hyphen_values = true)]
+    arguments: Vec<String>,
+
+    /// Skip instructions that fail instead of stopping, reporting each failure as a warning
+    #[arg(long)]
+    continue_on_error: bool,
+
+    /// Keep whole numbers as exact integers in arithmetic and comparisons
+    #[arg(long)]
+    integer: bool,
+
+    /// Treat floats that differ by at most this much as equal in EQ, NE, LT, and GT
+    #[arg(long, default_value_t = 0.0)]
+    epsilon: f32,
+
+    /// Maximum number of procedure calls that may be in progress at once, such as in a recursive procedure
+    #[arg(long, default_value_t = rslogo::limits::DEFAULT_MAX_CALL_DEPTH)]
+    max_call_depth: usize,
+
+    /// Maximum number of times a single WHILE, DO.WHILE, or DO.UNTIL loop may repeat, or 0 for no limit
+    #[arg(long, default_value_t = 1_000_000)]
+    max_loop_iterations: u64,
+
+    /// Seed the random number generator used by RANDOM, so every run draws the same picture
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Record every executed command, with a timestamp and the resulting turtle state, to this file
+    #[arg(long)]
+    audit: Option<std::path::PathBuf>,
+
+    /// Draw each line only once, dropping lines that retrace an earlier line of the same color
+    #[arg(long)]
+    deduplicate: bool,
+
+    /// Merge runs of nearly collinear lines, dropping points within this many pixels of the simplified path
+    #[arg(long)]
+    simplify: Option<f32>,
+
+    /// Move and scale the drawing so it fills the image, however far the turtle wandered
+    #[arg(long)]
+    fit: bool,
+
+    /// Space to leave around the drawing when fitting it to the image, in pixels
+    #[arg(long, default_value_t = 10.0, requires = "fit")]
+    margin: f32,
+
+    /// Print the instructions executed, peak variables, lines drawn, and time spent in each phase
+    #[arg(long)]
+    report: bool,
+
+    /// Evaluate math repeated within an instruction only once each time the instruction runs
+    #[arg(long)]
+    optimize: bool,
+
+    /// Also save a heatmap of how many lines passed through each region of the canvas to this SVG file
+    #[arg(long)]
+    heatmap: Option<std::path::PathBuf>,
+
+    /// Size of the square regions the heatmap counts lines in
+    #[arg(long, default_value_t = 10.0)]
+    heatmap_cell_size: f32,
+
+    /// Also send the drawing to a pen plotter or turtle robot on this serial port
+    #[cfg(feature = "serial")]
+    #[arg(long)]
+    plotter: Option<String>,
+
+    /// Baud rate of the plotter's serial port
+    #[cfg(feature = "serial")]
+    #[arg(long, default_value_t = 115_200)]
+    plotter_baud: u32,
+
+    /// File describing the commands the plotter understands, instead of G-code
+    #[cfg(feature = "serial")]
+    #[arg(long)]
+    plotter_protocol: Option<std::path::PathBuf>,
 }
 
-fn main() -> Result<(), ()> {
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Run a program and save what it draws as an SVG or PNG image
+    Run {
+        /// Path to the program
+        file_path: std::path::PathBuf,
+
+        /// Path to save the image to, ending in .svg or .png
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+
+        /// Width of the canvas
+        #[arg(long, default_value_t = 500)]
+        width: u32,
+
+        /// Height of the canvas
+        #[arg(long, default_value_t = 500)]
+        height: u32,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Run a program and save what it draws, then do so again each time the program is saved
+    Watch {
+        /// Path to the program
+        file_path: std::path::PathBuf,
+
+        /// Path to save the image to, ending in .svg or .png
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+
+        /// Width of the canvas
+        #[arg(long, default_value_t = 500)]
+        width: u32,
+
+        /// Height of the canvas
+        #[arg(long, default_value_t = 500)]
+        height: u32,
+
+        /// Milliseconds the program must go unchanged before it is run again
+        #[arg(long, default_value_t = 200)]
+        debounce: u64,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Check programs for syntax errors and likely mistakes without running them
+    Check {
+        /// Paths to the programs
+        #[arg(required = true)]
+        file_paths: Vec<std::path::PathBuf>,
+    },
+
+    /// Format programs in place, with one instruction per line
+    Fmt {
+        /// Paths to the programs
+        #[arg(required = true)]
+        file_paths: Vec<std::path::PathBuf>,
+
+        /// List the programs that are not formatted, and fail if there are any, instead of formatting them
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Run Logo typed in one line at a time, with meta-commands such as :load, :undo, and :export
+    Repl {
+        /// Width of the canvas
+        #[arg(long, default_value_t = 500)]
+        width: u32,
+
+        /// Height of the canvas
+        #[arg(long, default_value_t = 500)]
+        height: u32,
+    },
+
+    /// Serve an HTTP endpoint that renders the Logo programs posted to it
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        address: String,
+
+        /// Number of programs to render at once; further requests wait for a free worker
+        #[arg(long, default_value = "4")]
+        workers: std::num::NonZeroUsize,
+
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+
+    /// Serve a WebSocket endpoint that streams each line of the Logo programs sent to it as it is drawn
+    #[cfg(feature = "websocket")]
+    Stream {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8081")]
+        address: String,
+
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+
+    /// Run a language server for editors over standard input and output
+    #[cfg(feature = "lsp")]
+    Lsp,
+}
+
+/// The limits a service places on each program it runs.
+#[cfg(any(feature = "serve", feature = "websocket"))]
+#[derive(clap::Args)]
+struct LimitArgs {
+    /// Maximum number of instructions each program may execute
+    #[arg(long, default_value_t = 1_000_000)]
+    max_steps: u64,
+
+    /// Maximum number of seconds each program may run for
+    #[arg(long, default_value = "5", value_parser = parse_seconds)]
+    max_time: std::time::Duration,
+
+    /// Maximum number of bytes each program may store in variables
+    #[arg(long, default_value_t = 1 << 20)]
+    max_memory: usize,
+
+    /// Maximum number of lines each program may draw
+    #[arg(long, default_value_t = 100_000)]
+    max_image_operations: u64,
+
+    /// Maximum depth each program may nest blocks to
+    #[arg(long, default_value_t = 256)]
+    max_recursion_depth: usize,
+
+    /// Maximum number of procedure calls each program may have in progress at once
+    #[arg(long, default_value_t = rslogo::limits::DEFAULT_MAX_CALL_DEPTH)]
+    max_call_depth: usize,
+
+    /// Maximum number of times a single loop in each program may repeat, or 0 for no limit
+    #[arg(long, default_value_t = 1_000_000)]
+    max_loop_iterations: u64,
+}
+
+/// Parses a non-negative number of seconds, which may be fractional.
+#[cfg(any(feature = "serve", feature = "websocket"))]
+fn parse_seconds(seconds: &str) -> Result<std::time::Duration, String> {
+    let seconds: f64 = seconds.parse().map_err(|_| format!("`{seconds}` is not a number"))?;
+    std::time::Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("`{seconds}` is not a non-negative number of seconds"))
+}
+
+#[cfg(any(feature = "serve", feature = "websocket"))]
+impl From<LimitArgs> for rslogo::limits::Limits {
+    fn from(args: LimitArgs) -> Self {
+        Self {
+            max_steps: Some(args.max_steps),
+            max_time: Some(args.max_time),
+            max_memory: Some(args.max_memory),
+            max_image_operations: Some(args.max_image_operations),
+            max_recursion_depth: Some(args.max_recursion_depth),
+            max_call_depth: Some(args.max_call_depth),
+            max_loop_iterations: Some(args.max_loop_iterations).filter(|&max| max > 0),
+        }
+    }
+}
+
+/// Why a run failed, which decides the exit code. The error itself is printed where it happens.
+enum Failure {
+    /// The program could not be parsed, or stopped with an error.
+    Program,
+
+    /// The arguments are invalid, such as an image path with an unsupported extension.
+    Usage,
+
+    /// A file could not be read or written.
+    Io,
+}
+
+impl From<Failure> for ExitCode {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Program => ExitCode::from(1),
+            // The same code clap exits with for arguments it rejects itself.
+            Failure::Usage => ExitCode::from(2),
+            Failure::Io => ExitCode::from(3),
+        }
+    }
+}
+
+/// The formats images can be saved in.
+enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    /// Chooses the format from the extension of `path`.
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("svg") => Some(ImageFormat::Svg),
+            Some("png") => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
     let args: Args = Args::parse();
 
-    // Access the parsed arguments
-    let file_path = args.file_path;
-    let image_path = args.image_path;
-    let height = args.height;
-    let width = args.width;
+    if let Some(command) = args.command {
+        let result = match command {
+            Command::Run { file_path, output, width, height, render: options } => {
+                return exit_code(render(&file_path, &output, width, height, &options));
+            },
+            Command::Watch { file_path, output, width, height, debounce, render: options } => {
+                let debounce = Duration::from_millis(debounce);
+                return exit_code(watch(&file_path, &output, width, height, debounce, &options));
+            },
+            Command::Check { file_paths } => return exit_code(check(&file_paths)),
+            Command::Fmt { file_paths, check } => return exit_code(fmt(&file_paths, check)),
+            Command::Repl { width, height } => repl(width, height),
+            #[cfg(feature = "serve")]
+            Command::Serve { address, workers, limits } => rslogo::serve::serve(&address, limits.into(), workers),
+            #[cfg(feature = "websocket")]
+            Command::Stream { address, limits } => rslogo::stream::stream(&address, limits.into()),
+            #[cfg(feature = "lsp")]
+            Command::Lsp => rslogo::lsp::serve(std::io::stdin().lock(), std::io::stdout().lock()),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {e}");
+            return Failure::Io.into();
+        }
+        return ExitCode::SUCCESS;
+    }
 
-    let content = std::fs::read_to_string(file_path).unwrap();
+    // Access the parsed arguments, which clap requires when there is no subcommand
+    let file_path = args.file_path.expect("required by clap");
+    let image_path = args.image_path.expect("required by clap");
+    let height = args.height.expect("required by clap");
+    let width = args.width.expect("required by clap");
+    exit_code(render(&file_path, &image_path, width, height, &args.render))
+}
 
-    let mut image = Image::new(width, height);
-    let mut turtle = Turtle::new(&mut image);
+fn exit_code(result: Result<(), Failure>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => failure.into(),
+    }
+}
 
-    let instructions = parse_content(&content);
+/// Runs the program at `file_path` on a canvas of the given size and saves what it draws to `image_path`,
+/// printing any errors and warnings to standard error.
+fn render(file_path: &Path, image_path: &Path, width: u32, height: u32, args: &RenderArgs) -> Result<(), Failure> {
+    // Checked before running, so a long program is not run only to find that its image cannot be saved.
+    let Some(format) = ImageFormat::from_path(image_path) else {
+        eprintln!("Error: {} does not end in .svg or .png", image_path.display());
+        return Err(Failure::Usage);
+    };
+    let content = std::fs::read_to_string(file_path).map_err(|e| {
+        eprintln!("Error reading {}: {e}", file_path.display());
+        Failure::Io
+    })?;
+    let pragmas = match Pragmas::parse(&content) {
+        Ok(pragmas) => pragmas,
+        Err(e) => {
+            eprintln!("Error reading pragmas: {e}");
+            return Err(Failure::Program);
+        }
+    };
+    // The program knows best what canvas it was written for.
+    let (width, height) = pragmas.size.unwrap_or((width, height));
+
+    let limits = Limits {
+        max_call_depth: Some(args.max_call_depth),
+        max_loop_iterations: Some(args.max_loop_iterations).filter(|&max| max > 0),
+        ..Limits::default()
+    };
+    let mut turtle = Turtle::with_limits(width, height, limits);
+    pragmas.apply(&mut turtle);
+    turtle.set_grid(args.grid);
+    turtle.set_percentage_mode(args.percent);
+    turtle.set_heading_convention(HeadingConvention { clockwise: !args.counter_clockwise, zero: args.heading_zero });
+    turtle.set_continue_on_error(args.continue_on_error);
+    turtle.set_integer_mode(args.integer);
+    turtle.set_epsilon(args.epsilon);
+    if let Some(seed) = args.seed {
+        turtle.set_seed(seed);
+    }
+    if let Err(e) = turtle.set_arguments(&args.arguments) {
+        eprintln!("Error passing arguments: {e}");
+        return Err(Failure::Program);
+    }
+    if let Some(audit_path) = &args.audit {
+        match std::fs::File::create(audit_path) {
+            Ok(file) => turtle.set_audit_sink(Some(Box::new(WriterSink::new(file)))),
+            Err(e) => {
+                eprintln!("Error creating audit log: {e}");
+                return Err(Failure::Io);
+            }
+        }
+    }
+
+    let mut phases = PhaseTimes::default();
+    let start = Instant::now();
+    let tokens: Vec<_> = tokenize(&content).collect();
+    phases.tokenize = start.elapsed();
+    let symbols = SymbolTable::new(&tokens);
+
+    let start = Instant::now();
+    let instructions = parse_tokens(tokens, content.len(), &Primitives::new());
+    phases.parse = start.elapsed();
     let instructions = match instructions {
         Ok(instructions) => instructions,
-        Err(_e) => {
-            return Err(());
+        Err(errors) => {
+            for error in &errors {
+                report(&Diagnostic::from_parse_error(error), &content);
+            }
+            return Err(Failure::Program);
         }
     };
-    for instruction in instructions {
-        let _ = instruction.execute(&mut turtle);
+    for warning in lint(&instructions) {
+        eprintln!("Warning: {warning}");
+    }
+    for warning in symbols.warnings() {
+        report(&warning, &content);
+    }
+    let instructions = if args.optimize { eliminate_common_subexpressions(instructions) } else { instructions };
+    let start = Instant::now();
+    let result = run(&instructions, &mut turtle);
+    phases.execute = start.elapsed();
+    if let Err(e) = result {
+        report(&Diagnostic::from_runtime_error(&e, turtle.get_error_span()), &content);
+        return Err(Failure::Program);
+    }
+    for (error, span) in turtle.get_diagnostics() {
+        let mut diagnostic = Diagnostic::from_runtime_error(error, Some(span));
+        diagnostic.severity = Severity::Warning;
+        diagnostic.message = format!("skipped instruction: {}", diagnostic.message);
+        report(&diagnostic, &content);
     }
 
+    let mut display_list = turtle.get_display_list().clone();
+    if args.deduplicate {
+        display_list = display_list.deduplicate();
+    }
+    if let Some(tolerance) = args.simplify {
+        display_list = display_list.simplify(tolerance);
+    }
+    if let (true, Some(bounds)) = (args.fit, display_list.bounds()) {
+        display_list = display_list.transformed(Transform::fit(bounds, width, height, args.margin));
+    }
 
-    match image_path.extension().and_then(|s| s.to_str()) {
-        Some("svg") => {
-            let res = image.save_svg(&image_path);
+    if let Some(heatmap_path) = &args.heatmap {
+        let heatmap = Heatmap::new(&display_list, width, height, args.heatmap_cell_size);
+        if let Err(e) = std::fs::write(heatmap_path, heatmap.to_svg()) {
+            eprintln!("Error saving heatmap: {e}");
+            return Err(Failure::Io);
+        }
+    }
+
+    #[cfg(feature = "serial")]
+    if let Some(port) = &args.plotter {
+        plot(&display_list, height, port, args.plotter_baud, args.plotter_protocol.as_deref())
+            .map_err(|()| Failure::Io)?;
+    }
+
+    let mut image = Image::new(width, height);
+    if let Some(background) = pragmas.background {
+        if let Err(e) = DisplayList::fill_background(&mut image, unsvg::COLORS[background]) {
+            eprintln!("Error drawing image: {e}");
+            return Err(Failure::Program);
+        }
+    }
+    let start = Instant::now();
+    if matches!(format, ImageFormat::Png) {
+        if let Err(e) = display_list.render(&mut image) {
+            eprintln!("Error drawing image: {e}");
+            return Err(Failure::Program);
+        }
+    }
+    phases.render = start.elapsed();
+    if args.report {
+        eprintln!("{}", ResourceReport::new(&turtle, phases));
+    }
+
+    match format {
+        ImageFormat::Svg => {
+            // The lines are drawn as they are saved, so each keeps its own stroke width.
+            let res = display_list.save_svg(&mut image, image_path);
             if let Err(e) = res {
                 eprintln!("Error saving svg: {e}");
-                return Err(());
+                return Err(Failure::Io);
             }
         }
-        Some("png") => {
-            let res = image.save_png(&image_path);
+        ImageFormat::Png => {
+            let res = image.save_png(image_path);
             if let Err(e) = res {
                 eprintln!("Error saving png: {e}");
-                return Err(());
+                return Err(Failure::Io);
             }
         }
-        _ => {
-            eprintln!("File extension not supported");
-            return Err(());
+    }
+
+    Ok(())
+}
+
+/// How often `watch` checks whether the program has changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Renders the program at `file_path` as `render` does, then again each time the file changes, until the process
+/// is interrupted. A change is rendered once the file has gone `debounce` without changing again, so an editor
+/// that saves in several writes causes a single render. Errors in the program are printed and then the file is
+/// watched for a fix, so this only returns if the image path is invalid.
+fn watch(
+    file_path: &Path,
+    image_path: &Path,
+    width: u32,
+    height: u32,
+    debounce: Duration,
+    args: &RenderArgs,
+) -> Result<(), Failure> {
+    // The size is compared too, as some file systems only record modification times to the second.
+    let version = || std::fs::metadata(file_path).ok().map(|metadata| (metadata.modified().ok(), metadata.len()));
+
+    let mut rendered = version();
+    loop {
+        match render(file_path, image_path, width, height, args) {
+            Ok(()) => eprintln!("Saved {}", image_path.display()),
+            Err(Failure::Usage) => return Err(Failure::Usage),
+            Err(_) => {},
+        }
+        eprintln!("Watching {} for changes", file_path.display());
+
+        let mut current = rendered;
+        while current == rendered {
+            std::thread::sleep(WATCH_INTERVAL);
+            current = version();
+        }
+        loop {
+            std::thread::sleep(debounce);
+            let settled = version();
+            if settled == current {
+                break;
+            }
+            current = settled;
+        }
+        rendered = current;
+    }
+}
+
+/// Parses each program in `file_paths` and analyzes it without running it, printing every error and warning
+/// found, and a summary of each program. Fails if any program has an error.
+fn check(file_paths: &[std::path::PathBuf]) -> Result<(), Failure> {
+    let mut result = Ok(());
+    for file_path in file_paths {
+        let content = match std::fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading {}: {e}", file_path.display());
+                result = Err(Failure::Io);
+                continue;
+            }
+        };
+
+        let mut errors = 0;
+        let mut warnings = 0;
+        if let Err(e) = Pragmas::parse(&content) {
+            eprintln!("Error reading pragmas: {e}");
+            errors += 1;
+        }
+        let tokens: Vec<_> = tokenize(&content).collect();
+        let symbols = SymbolTable::new(&tokens);
+        match parse_tokens(tokens, content.len(), &Primitives::new()) {
+            Ok(instructions) => {
+                for warning in lint(&instructions) {
+                    eprintln!("Warning: {warning}");
+                    warnings += 1;
+                }
+                for warning in symbols.warnings() {
+                    report(&warning, &content);
+                    warnings += 1;
+                }
+            },
+            Err(parse_errors) => {
+                for error in &parse_errors {
+                    report(&Diagnostic::from_parse_error(error), &content);
+                }
+                errors += parse_errors.len();
+            }
+        }
+
+        eprintln!("{}: {errors} error(s), {warnings} warning(s)", file_path.display());
+        if errors > 0 && result.is_ok() {
+            result = Err(Failure::Program);
         }
     }
+    result
+}
 
+/// Formats each program in `file_paths` in place, or with `check`, lists those that are not formatted and
+/// fails if there are any. Programs that cannot be parsed are reported and left as they are.
+fn fmt(file_paths: &[std::path::PathBuf], check: bool) -> Result<(), Failure> {
+    let mut result = Ok(());
+    let mut fail = |failure: Failure| {
+        if !matches!(result, Err(Failure::Io)) {
+            result = Err(failure);
+        }
+    };
+    for file_path in file_paths {
+        let content = match std::fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading {}: {e}", file_path.display());
+                fail(Failure::Io);
+                continue;
+            }
+        };
+        let formatted = match format_source(&content) {
+            Ok(formatted) => formatted,
+            Err(Error::Parse(errors)) => {
+                for error in &errors {
+                    report(&Diagnostic::from_parse_error(error), &content);
+                }
+                fail(Failure::Program);
+                continue;
+            },
+            Err(e) => {
+                eprintln!("Error formatting {}: {e}", file_path.display());
+                fail(Failure::Program);
+                continue;
+            },
+        };
+        if formatted == content {
+            continue;
+        }
+        if check {
+            println!("{}", file_path.display());
+            fail(Failure::Program);
+        } else if let Err(e) = std::fs::write(file_path, formatted) {
+            eprintln!("Error writing {}: {e}", file_path.display());
+            fail(Failure::Io);
+        }
+    }
+    result
+}
+
+/// Reads Logo and meta-commands from standard input until it is closed, printing what each one returns.
+#[cfg(feature = "readline")]
+fn repl(width: u32, height: u32) -> std::io::Result<()> {
+    use rustyline::error::ReadlineError;
+    use rustyline::history::DefaultHistory;
+    use rustyline::Editor;
+
+    let mut repl = rslogo::repl::Repl::new(width, height);
+    let mut editor: Editor<rslogo::repl::Helper, DefaultHistory> = Editor::new().map_err(std::io::Error::other)?;
+    editor.set_helper(Some(rslogo::repl::Helper::new(repl.get_session().get_turtle().get_primitives())));
+    let history = std::env::var_os("HOME").map(|home| std::path::PathBuf::from(home).join(".rslogo_history"));
+    if let Some(history) = &history {
+        // There is no history the first time the REPL is run.
+        let _ = editor.load_history(history);
+    }
+    loop {
+        let line = match editor.readline(if repl.is_pending() { "... " } else { "> " }) {
+            Ok(line) => line,
+            // Ctrl-C abandons the line being typed, like in a shell.
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(std::io::Error::other(e)),
+        };
+        if !line.trim().is_empty() {
+            editor.add_history_entry(line.as_str()).map_err(std::io::Error::other)?;
+        }
+        match repl.eval(&line) {
+            Ok(output) => print!("{output}"),
+            Err(error) => eprintln!("{}", error.trim_end()),
+        }
+    }
+    if let Some(Err(e)) = history.map(|history| editor.save_history(&history)) {
+        eprintln!("Error saving history: {e}");
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "readline"))]
+fn repl(width: u32, height: u32) -> std::io::Result<()> {
+    use std::io::{BufRead, Write};
+
+    let mut repl = rslogo::repl::Repl::new(width, height);
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        print!("{}", if repl.is_pending() { "... " } else { "> " });
+        std::io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        match repl.eval(&line?) {
+            Ok(output) => print!("{output}"),
+            Err(error) => eprintln!("{}", error.trim_end()),
+        }
+    }
+}
+
+/// Prints `diagnostic` to standard error, quoting the part of `source` it was found in.
+fn report(diagnostic: &Diagnostic, source: &str) {
+    #[cfg(feature = "ariadne")]
+    if diagnostic.report().eprint(ariadne::Source::from(source)).is_ok() {
+        return;
+    }
+    eprint!("{}", diagnostic.render(source));
+}
+
+#[cfg(feature = "serial")]
+fn plot(
+    display_list: &DisplayList,
+    height: u32,
+    port: &str,
+    baud: u32,
+    protocol: Option<&std::path::Path>,
+) -> Result<(), ()> {
+    use rslogo::plotter::{Plotter, Protocol};
+
+    let protocol = match protocol.map(std::fs::read_to_string) {
+        None => Protocol::gcode(),
+        Some(Ok(config)) => Protocol::parse(&config).map_err(|e| eprintln!("Error reading plotter protocol: {e}"))?,
+        Some(Err(e)) => {
+            eprintln!("Error reading plotter protocol: {e}");
+            return Err(());
+        }
+    };
+    let port = serialport::new(port, baud)
+        .timeout(std::time::Duration::from_secs(10))
+        .open()
+        .map_err(|e| eprintln!("Error opening plotter: {e}"))?;
+    let replies = port.try_clone().map_err(|e| eprintln!("Error opening plotter: {e}"))?;
+    let mut plotter = Plotter::new(port, protocol, height).with_replies(replies);
+    if let Err(e) = plotter.plot(display_list).and_then(|_| plotter.finish()) {
+        eprintln!("Error plotting: {e}");
+        return Err(());
+    }
     Ok(())
 }
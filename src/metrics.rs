@@ -0,0 +1,215 @@
+//! # Metrics
+//!
+//! This module measures the structure of a parsed program without running it, for automated graders that
+//! check a submission against a rubric such as "uses `WHILE` and at least one variable".
+//!
+//! `Metrics::analyze` counts how many times each keyword, registered primitive, and procedure is used,
+//! including inside blocks and expressions, along with the variables named, how deeply blocks are nested,
+//! and how many loops, conditionals, and procedure definitions there are.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::metrics::Metrics;
+//! use rslogo::parser::parse_content;
+//!
+//! let asts = parse_content("MAKE \"i \"0 WHILE LT :i \"4 [ IF EQ :i \"2 [ PENDOWN ] FORWARD \"10 ADDASSIGN \"i \"1 ]").unwrap();
+//! let metrics = Metrics::analyze(&asts);
+//!
+//! assert!(metrics.uses("WHILE") && !metrics.variables.is_empty());
+//! assert_eq!(metrics.count("FORWARD"), 1);
+//! assert!(!metrics.uses("REPEAT"));
+//! assert_eq!((metrics.loops, metrics.conditionals, metrics.max_depth), (1, 1, 2));
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ast::{ASTNode, Condition, ControlFlow, Expression, Procedure, Query};
+
+/// The structure of a program, as measured by `Metrics::analyze`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// How many times each keyword, registered primitive, and procedure is used, by the name it is written
+    /// with, such as `FORWARD` or `+`.
+    pub keywords: BTreeMap<String, usize>,
+
+    /// The names of the variables that are assigned or read.
+    pub variables: BTreeSet<String>,
+
+    /// How many blocks deep the most deeply nested instruction is, where top-level instructions are at 0.
+    pub max_depth: usize,
+
+    /// The number of `WHILE`, `REPEAT`, `FOR`, `DOTIMES`, `DO.WHILE`, and `DO.UNTIL` loops.
+    pub loops: usize,
+
+    /// The number of `IF` statements.
+    pub conditionals: usize,
+
+    /// The number of procedures defined with `TO`.
+    pub definitions: usize,
+}
+
+impl Metrics {
+    /// Measures `instructions`, including those nested in blocks.
+    pub fn analyze(instructions: &[ASTNode]) -> Self {
+        let mut metrics = Metrics::default();
+        metrics.block(instructions, 0);
+        metrics
+    }
+
+    /// Gets the number of times `keyword` is used, which is 0 if it is not used at all.
+    pub fn count(&self, keyword: &str) -> usize {
+        self.keywords.get(keyword).copied().unwrap_or_default()
+    }
+
+    /// Whether `keyword` is used at least once.
+    pub fn uses(&self, keyword: &str) -> bool {
+        self.count(keyword) > 0
+    }
+
+    /// Records the keyword an instruction or expression starts with, which is the first word it unparses to.
+    fn keyword(&mut self, node: &impl std::fmt::Display) {
+        if let Some(keyword) = node.to_string().split_whitespace().next() {
+            *self.keywords.entry(keyword.to_string()).or_default() += 1;
+        }
+    }
+
+    fn variable(&mut self, name: &Expression) {
+        if let Expression::String(name) = name {
+            self.variables.insert(name.clone());
+        }
+    }
+
+    fn block(&mut self, instructions: &[ASTNode], depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+        for instruction in instructions {
+            match instruction {
+                ASTNode::Procedure(procedure, _) => self.procedure(procedure),
+                ASTNode::ControlFlow(flow, _) => self.control_flow(flow, depth),
+            }
+        }
+    }
+
+    fn procedure(&mut self, procedure: &Procedure) {
+        self.keyword(procedure);
+        match procedure {
+            Procedure::Make(name, _)
+            | Procedure::Local(name)
+            | Procedure::LocalMake(name, _)
+            | Procedure::AddAssign(name, _)
+            | Procedure::SubAssign(name, _)
+            | Procedure::MulAssign(name, _)
+            | Procedure::DivAssign(name, _) => self.variable(name),
+            _ => {},
+        }
+        procedure.expressions().into_iter().for_each(|expression| self.expression(expression));
+    }
+
+    fn control_flow(&mut self, flow: &ControlFlow, depth: usize) {
+        self.keyword(flow);
+        let block = match flow {
+            ControlFlow::If { condition, block } => {
+                self.conditionals += 1;
+                self.expression(condition);
+                block
+            },
+            ControlFlow::While { condition, block }
+            | ControlFlow::DoWhile { condition, block }
+            | ControlFlow::Until { condition, block } => {
+                self.loops += 1;
+                self.expression(condition);
+                block
+            },
+            ControlFlow::Repeat { count, block } => {
+                self.loops += 1;
+                self.expression(count);
+                block
+            },
+            ControlFlow::For { variable, start, end, step, block } => {
+                self.loops += 1;
+                self.variable(variable);
+                [start, end, step].into_iter().for_each(|expression| self.expression(expression));
+                block
+            },
+            ControlFlow::DoTimes { variable, count, block } => {
+                self.loops += 1;
+                self.variable(variable);
+                self.expression(count);
+                block
+            },
+            ControlFlow::To { parameters, block, .. } => {
+                self.definitions += 1;
+                self.variables.extend(parameters.iter().cloned());
+                block
+            },
+            ControlFlow::WhenKey { key: word, block } | ControlFlow::Catch { tag: word, block } => {
+                self.expression(word);
+                block
+            },
+            ControlFlow::WhenClick { block } | ControlFlow::EveryFrame { block } => block,
+        };
+        self.block(block, depth + 1);
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Float(_) | Expression::String(_) => {},
+            Expression::Variable(name) => {
+                self.variables.insert(name.clone());
+            },
+            Expression::Query(query) => {
+                self.keyword(query);
+                match query {
+                    Query::Random(max) => self.expression(max),
+                    Query::PixelColor(x, y) => {
+                        self.expression(x);
+                        self.expression(y);
+                    },
+                    _ => {},
+                }
+            },
+            Expression::Math(math) => {
+                self.keyword(math);
+                math.operands().into_iter().for_each(|operand| self.expression(operand));
+            },
+            Expression::Bool(condition) => self.condition(condition),
+            Expression::Call(_, args) => {
+                self.keyword(expression);
+                args.iter().for_each(|arg| self.expression(arg));
+            },
+            Expression::List(items) => items.iter().for_each(|item| self.expression(item)),
+            Expression::ListOp(op) => {
+                self.keyword(op);
+                op.operands().into_iter().for_each(|operand| self.expression(operand));
+            },
+            Expression::ReadWord => self.keyword(expression),
+            Expression::Shared(shared) => self.expression(&shared.expression),
+        }
+    }
+
+    fn condition(&mut self, condition: &Condition) {
+        self.keyword(condition);
+        match condition {
+            Condition::Equal(lhs, rhs)
+            | Condition::NotEqual(lhs, rhs)
+            | Condition::NearlyEqual(lhs, rhs)
+            | Condition::LessThan(lhs, rhs)
+            | Condition::GreaterThan(lhs, rhs)
+            | Condition::LessEqual(lhs, rhs)
+            | Condition::GreaterEqual(lhs, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+            },
+            Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+                self.condition(lhs);
+                self.condition(rhs);
+            },
+            Condition::Not(condition) => self.condition(condition),
+            Condition::Inside(value)
+            | Condition::IsNumber(value)
+            | Condition::IsWord(value)
+            | Condition::IsList(value)
+            | Condition::IsEmpty(value) => self.expression(value),
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! # Node
+//!
+//! This module exports the interpreter to Node.js through N-API, so Electron and Node based teaching tools
+//! can run Logo programs natively. It is only compiled with the `node` feature; build the addon with
+//! `cargo rustc --release --lib --features node --crate-type cdylib` and load the resulting dynamic library
+//! as a `.node` file. The crate type is chosen on the command line so that other builds do not pay for
+//! linking a dynamic library they never use.
+//!
+//! `keywords` describes every keyword, for completions and help in web playgrounds.
+//!
+//! Both `parse` and `run` report problems as structured `Diagnostic` objects instead of throwing, so a
+//! tool can underline the offending source range. `run` applies the same limits as `rslogo serve` unless
+//! they are raised, and `runAsync` runs a program on the libuv thread pool so it does not block the event loop:
+//!
+//! ```js
+//! const { parse, run, runAsync } = require('./rslogo.node');
+//!
+//! parse('FORWARD');   // [{ message: 'Unexpected end of input', start: 7, end: 7, recoverable: false }]
+//! run('PENDOWN FORWARD "10', 100, 100).svg;
+//! await runAsync('REPEAT "1000 [ FORWARD "1 ]', 100, 100, { maxSteps: 10000000 });
+//! ```
+
+use std::time::Duration;
+
+use chumsky::error::Simple;
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Task};
+use napi_derive::napi;
+
+use crate::ast::Span;
+use crate::diagnostics;
+use crate::error::RuntimeError;
+use crate::input::NoInput;
+use crate::keywords;
+use crate::limits::{Limits, DEFAULT_MAX_CALL_DEPTH};
+use crate::parser::parse_content;
+use crate::runner::run as run_instructions;
+use crate::tokenizer::Token;
+use crate::turtle::Turtle;
+
+/// A problem found while parsing or running a program.
+#[napi(object)]
+pub struct Diagnostic {
+    /// A description of the problem.
+    pub message: String,
+
+    /// The byte offset in the source the problem starts at, if it is known.
+    pub start: Option<u32>,
+
+    /// The byte offset in the source the problem ends at, if it is known.
+    pub end: Option<u32>,
+
+    /// Whether the program carried on past the problem.
+    pub recoverable: bool,
+}
+
+/// Options for `run`, matching the flags of the command line interface. Each limit defaults to the default of
+/// the matching `rslogo serve` flag.
+#[napi(object)]
+pub struct RunOptions {
+    /// Round all endpoints to a grid of this size before drawing.
+    pub grid: Option<f64>,
+
+    /// Interpret distances and coordinates as percentages of the canvas size.
+    pub percent: Option<bool>,
+
+    /// Skip instructions that fail instead of stopping, reporting each failure as a diagnostic.
+    pub continue_on_error: Option<bool>,
+
+    /// Maximum number of instructions the program may execute.
+    pub max_steps: Option<u32>,
+
+    /// Maximum number of seconds the program may run for.
+    pub max_time: Option<f64>,
+
+    /// Maximum number of bytes the program may store in variables.
+    pub max_memory: Option<u32>,
+
+    /// Maximum number of lines the program may draw.
+    pub max_image_operations: Option<u32>,
+
+    /// Maximum depth the program may nest blocks to.
+    pub max_recursion_depth: Option<u32>,
+
+    /// Maximum number of procedure calls the program may have in progress at once.
+    pub max_call_depth: Option<u32>,
+
+    /// Maximum number of times a single loop may repeat, or 0 for no limit.
+    pub max_loop_iterations: Option<u32>,
+}
+
+/// The outcome of `run`.
+#[napi(object)]
+pub struct RunResult {
+    /// The drawing as SVG source, or `None` if the program could not be parsed or stopped with an error.
+    pub svg: Option<String>,
+
+    /// Every problem found, in the order they were found.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A keyword of the language, as described by `keywords`.
+#[napi(object)]
+pub struct Keyword {
+    /// The keyword, as it is written in a program.
+    pub name: String,
+
+    /// How the keyword is used: `command`, `query`, `operator`, `control`, or `primitive`.
+    pub kind: String,
+
+    /// The keyword followed by the names of its arguments.
+    pub signature: String,
+
+    /// A one line description of what the keyword does.
+    pub doc: String,
+}
+
+/// Describes every keyword of the language.
+#[napi]
+pub fn keywords() -> Vec<Keyword> {
+    keywords::keywords(&Default::default())
+        .into_iter()
+        .map(|keyword| Keyword {
+            name: keyword.name,
+            kind: keyword.kind.to_string(),
+            signature: keyword.signature,
+            doc: keyword.doc,
+        })
+        .collect()
+}
+
+/// Parses `source`, returning the syntax errors in it.
+#[napi]
+pub fn parse(source: String) -> Vec<Diagnostic> {
+    match parse_content(&source) {
+        Ok(_) => vec![],
+        Err(errors) => errors.iter().map(parse_diagnostic).collect(),
+    }
+}
+
+/// Parses and runs `source` on a canvas of the given size, returning the drawing as SVG source.
+#[napi]
+pub fn run(source: String, width: u32, height: u32, options: Option<RunOptions>) -> RunResult {
+    render(&source, width, height, options.as_ref())
+}
+
+/// Parses and runs `source` like `run`, but on the libuv thread pool, so the returned promise resolves
+/// without blocking the JavaScript thread while the program runs.
+#[napi]
+pub fn run_async(source: String, width: u32, height: u32, options: Option<RunOptions>) -> AsyncTask<RunTask> {
+    AsyncTask::new(RunTask { source, width, height, options })
+}
+
+/// A call to `runAsync`, waiting to be run on the libuv thread pool.
+pub struct RunTask {
+    source: String,
+    width: u32,
+    height: u32,
+    options: Option<RunOptions>,
+}
+
+#[napi]
+impl Task for RunTask {
+    type Output = RunResult;
+    type JsValue = RunResult;
+
+    fn compute(&mut self) -> napi::Result<RunResult> {
+        Ok(render(&self.source, self.width, self.height, self.options.as_ref()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: RunResult) -> napi::Result<RunResult> {
+        Ok(output)
+    }
+}
+
+fn render(source: &str, width: u32, height: u32, options: Option<&RunOptions>) -> RunResult {
+    let limits = match limits(options) {
+        Ok(limits) => limits,
+        Err(message) => {
+            let diagnostic = Diagnostic { message, start: None, end: None, recoverable: false };
+            return RunResult { svg: None, diagnostics: vec![diagnostic] };
+        }
+    };
+    let instructions = match parse_content(source) {
+        Ok(instructions) => instructions,
+        Err(errors) => {
+            return RunResult { svg: None, diagnostics: errors.iter().map(parse_diagnostic).collect() };
+        }
+    };
+
+    let mut turtle = Turtle::with_limits(width, height, limits);
+    turtle.set_input_source(Box::new(NoInput));
+    if let Some(options) = options {
+        turtle.set_grid(options.grid.map(|grid| grid as f32));
+        turtle.set_percentage_mode(options.percent.unwrap_or(false));
+        turtle.set_continue_on_error(options.continue_on_error.unwrap_or(false));
+    }
+
+    let result = run_instructions(&instructions, &mut turtle);
+    let mut diagnostics: Vec<Diagnostic> = turtle.get_diagnostics()
+        .iter()
+        .map(|(error, span)| runtime_diagnostic(error, Some(span)))
+        .collect();
+    if let Err(e) = result {
+        diagnostics.push(runtime_diagnostic(&e, turtle.get_error_span()));
+        return RunResult { svg: None, diagnostics };
+    }
+
+    match turtle.get_display_list().to_svg(width, height) {
+        Ok(svg) => RunResult { svg: Some(svg), diagnostics },
+        Err(e) => {
+            diagnostics.push(Diagnostic { message: e, start: None, end: None, recoverable: false });
+            RunResult { svg: None, diagnostics }
+        }
+    }
+}
+
+/// Builds the limits for a run from `options`, falling back to the defaults of `rslogo serve`.
+fn limits(options: Option<&RunOptions>) -> Result<Limits, String> {
+    let limit = |get: fn(&RunOptions) -> Option<u32>, default: u32| options.and_then(get).unwrap_or(default);
+    let max_time = options.and_then(|options| options.max_time).unwrap_or(5.0);
+    let max_time = Duration::try_from_secs_f64(max_time)
+        .map_err(|_| format!("maxTime must be a non-negative number of seconds, not {max_time}"))?;
+    Ok(Limits {
+        max_steps: Some(limit(|options| options.max_steps, 1_000_000).into()),
+        max_time: Some(max_time),
+        max_memory: Some(limit(|options| options.max_memory, 1 << 20) as usize),
+        max_image_operations: Some(limit(|options| options.max_image_operations, 100_000).into()),
+        max_recursion_depth: Some(limit(|options| options.max_recursion_depth, 256) as usize),
+        max_call_depth: Some(limit(|options| options.max_call_depth, DEFAULT_MAX_CALL_DEPTH as u32) as usize),
+        max_loop_iterations: Some(limit(|options| options.max_loop_iterations, 1_000_000).into())
+            .filter(|&max| max > 0),
+    })
+}
+
+fn parse_diagnostic(error: &Simple<Token>) -> Diagnostic {
+    diagnostics::Diagnostic::from_parse_error(error).into()
+}
+
+fn runtime_diagnostic(error: &RuntimeError, span: Option<&Span>) -> Diagnostic {
+    diagnostics::Diagnostic::from_runtime_error(error, span).into()
+}
+
+impl From<diagnostics::Diagnostic> for Diagnostic {
+    fn from(diagnostic: diagnostics::Diagnostic) -> Self {
+        Diagnostic {
+            message: diagnostic.message,
+            start: diagnostic.span.as_ref().map(|span| span.start as u32),
+            end: diagnostic.span.as_ref().map(|span| span.end as u32),
+            recoverable: diagnostic.recoverable,
+        }
+    }
+}
@@ -0,0 +1,660 @@
+//! # Ops
+//!
+//! This module contains the `TurtleOps` trait, the operations that executing the AST performs on a turtle.
+//! `ASTNode::execute` and `Expression::evaluate` are generic over it, so an AST can be run against something
+//! other than a `Turtle`, such as a mock that records the commands it receives, with no image at all.
+//!
+//! Only moving, turning, the pen, and variables must be implemented. Every other operation has a default that
+//! either builds on those, as `BACK` moves forward by the negated distance, or does nothing, as a turtle that
+//! cannot draw has nothing to fill. `Turtle` implements every operation itself.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use rslogo::error::RuntimeError;
+//! use rslogo::ops::TurtleOps;
+//! use rslogo::parser::parse_content;
+//! use rslogo::value::Value;
+//!
+//! #[derive(Default)]
+//! struct Recorder {
+//!     commands: Vec<String>,
+//!     variables: HashMap<String, Value>,
+//! }
+//!
+//! impl TurtleOps for Recorder {
+//!     fn pen_up(&mut self) {
+//!         self.commands.push("PENUP".to_string());
+//!     }
+//!
+//!     fn pen_down(&mut self) {
+//!         self.commands.push("PENDOWN".to_string());
+//!     }
+//!
+//!     fn forward(&mut self, distance: f32) -> Result<(), RuntimeError> {
+//!         self.commands.push(format!("FORWARD {distance}"));
+//!         Ok(())
+//!     }
+//!
+//!     fn turn(&mut self, degrees: f32) {
+//!         self.commands.push(format!("TURN {degrees}"));
+//!     }
+//!
+//!     fn add_variable(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+//!         self.variables.insert(name.to_string(), value);
+//!         Ok(())
+//!     }
+//!
+//!     fn get_variable(&self, name: &str) -> Result<&Value, RuntimeError> {
+//!         self.variables.get(name)
+//!             .ok_or_else(|| RuntimeError::UndefinedVariable { name: name.to_string(), suggestion: None })
+//!     }
+//! }
+//!
+//! let program = parse_content("MAKE \"side \"10 PENDOWN REPEAT \"2 [ FORWARD :side TURN \"90 ] BACK \"5").unwrap();
+//! let mut recorder = Recorder::default();
+//! for instruction in &program {
+//!     instruction.execute(&mut recorder).unwrap();
+//! }
+//!
+//! assert_eq!(recorder.commands, ["PENDOWN", "FORWARD 10", "TURN 90", "FORWARD 10", "TURN 90", "FORWARD -5"]);
+//! ```
+
+use std::sync::Arc;
+
+use crate::ast::{word, ASTNode, Definition, Procedure, Span};
+use crate::error::RuntimeError;
+use crate::turtle::{NamePolicy, Turtle};
+use crate::value::Value;
+
+/// The operations that executing the AST performs on a turtle.
+///
+/// Distances and coordinates are in the turtle's units, headings and angles in degrees, and colors are
+/// indices into the `COLORS` array, as they are written in a program.
+pub trait TurtleOps {
+    /// Lifts the pen, so moving does not draw.
+    fn pen_up(&mut self);
+
+    /// Puts the pen down, so moving draws a line.
+    fn pen_down(&mut self);
+
+    /// Moves forward by `distance`.
+    fn forward(&mut self, distance: f32) -> Result<(), RuntimeError>;
+
+    /// Turns clockwise by `degrees`.
+    fn turn(&mut self, degrees: f32);
+
+    /// Sets the variable `name` to `value`.
+    fn add_variable(&mut self, name: &str, value: Value) -> Result<(), RuntimeError>;
+
+    /// Gets the value of the variable `name`.
+    fn get_variable(&self, name: &str) -> Result<&Value, RuntimeError>;
+
+    /// Moves backward by `distance`. Defaults to moving forward by `-distance`.
+    fn back(&mut self, distance: f32) -> Result<(), RuntimeError> {
+        self.forward(-distance)
+    }
+
+    /// Moves to the left by `distance`, without changing the heading. Defaults to turning left, moving forward,
+    /// and turning back.
+    fn left(&mut self, distance: f32) -> Result<(), RuntimeError> {
+        self.turn(-90.0);
+        let result = self.forward(distance);
+        self.turn(90.0);
+        result
+    }
+
+    /// Moves to the right by `distance`, without changing the heading. Defaults to moving left by `-distance`.
+    fn right(&mut self, distance: f32) -> Result<(), RuntimeError> {
+        self.left(-distance)
+    }
+
+    /// Moves by `distance` towards the absolute `angle`, without changing the heading. Defaults to turning to
+    /// the angle, moving forward, and turning back.
+    fn move_polar(&mut self, angle: f32, distance: f32) -> Result<(), RuntimeError> {
+        let heading = self.get_heading();
+        self.turn(angle - heading);
+        let result = self.forward(distance);
+        self.turn(heading - angle);
+        result
+    }
+
+    /// Sets the heading to `heading`. Defaults to turning by the difference from the current heading.
+    fn set_heading(&mut self, heading: f32) {
+        self.turn(heading - self.get_heading());
+    }
+
+    /// Sets the x-coordinate. Does nothing by default.
+    fn set_x(&mut self, _x: f32) {}
+
+    /// Sets the y-coordinate. Does nothing by default.
+    fn set_y(&mut self, _y: f32) {}
+
+    /// Gets the x-coordinate, for `XCOR`. Defaults to 0.
+    fn get_x(&self) -> f32 {
+        0.0
+    }
+
+    /// Gets the y-coordinate, for `YCOR`. Defaults to 0.
+    fn get_y(&self) -> f32 {
+        0.0
+    }
+
+    /// Gets the heading, for `HEADING`. Defaults to 0.
+    fn get_heading(&self) -> f32 {
+        0.0
+    }
+
+    /// Sets the pen color. Does nothing by default.
+    fn set_pen_color(&mut self, _index: f32) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Gets the pen color, for `COLOR`. Defaults to 7, the color turtles start with.
+    fn get_pen_color(&self) -> f32 {
+        7.0
+    }
+
+    /// Sets the fill color. Does nothing by default.
+    fn set_fill_color(&mut self, _index: f32) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Gets the fill color, for `FILLCOLOR`. Defaults to 7, the color turtles start with.
+    fn get_fill_color(&self) -> f32 {
+        7.0
+    }
+
+    /// Sets the width of the lines drawn. Does nothing by default.
+    fn set_pen_size(&mut self, _size: f32) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Sets the speed recorded on the lines drawn. Does nothing by default.
+    fn set_speed(&mut self, _speed: f32) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Fills the shape around `(x, y)`. Does nothing by default.
+    fn fill_at(&mut self, _x: f32, _y: f32) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Plays a tone. Does nothing by default.
+    fn toot(&mut self, _frequency: f32, _duration: f32) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Pauses for `ticks`, 60 to a second. Does nothing by default.
+    fn wait(&mut self, _ticks: f32) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Saves the position, heading, and pen. Does nothing by default.
+    fn push_state(&mut self) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Restores the last saved position, heading, and pen. Does nothing by default.
+    fn pop_state(&mut self) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Remembers the position and heading under `name`. Does nothing by default.
+    fn mark(&mut self, _name: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Moves to the position and heading remembered under `name`. Does nothing by default.
+    fn goto(&mut self, _name: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Saves the lines drawn so far under `name`. Does nothing by default.
+    fn snapshot(&mut self, _name: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Replaces the lines drawn so far with those saved under `name`. Does nothing by default.
+    fn restore_image(&mut self, _name: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Gets the width of the canvas, for `SCREENWIDTH`. Defaults to 0.
+    fn get_screen_width(&self) -> f32 {
+        0.0
+    }
+
+    /// Gets the height of the canvas, for `SCREENHEIGHT`. Defaults to 0.
+    fn get_screen_height(&self) -> f32 {
+        0.0
+    }
+
+    /// Gets the color index of the line drawn at `(x, y)`, for `PIXELCOLOR`. Defaults to -1, for no line.
+    fn get_pixel_color(&self, _x: f32, _y: f32) -> f32 {
+        -1.0
+    }
+
+    /// Seeds `RANDOM`. Does nothing by default.
+    fn set_seed(&mut self, _seed: u64) {}
+
+    /// Generates a random number from 0 up to, but not including, 1, for `RANDOM`. Defaults to 0.
+    fn next_random(&self) -> f32 {
+        0.0
+    }
+
+    /// Reads the next word of input, for `READWORD`. Defaults to the empty word, as if there were no input.
+    fn read_word(&self) -> Result<Value, RuntimeError> {
+        Ok(word(String::new()))
+    }
+
+    /// Declares the local variable `name` in the innermost scope. Does nothing by default, so every variable
+    /// is global.
+    fn add_local_variable(&mut self, _name: &str) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Gets the iteration of the innermost `REPEAT`, for `REPCOUNT`. Defaults to -1, for outside of any `REPEAT`.
+    fn get_repcount(&self) -> f32 {
+        -1.0
+    }
+
+    /// Sets the iteration of the innermost `REPEAT`, returning the previous one. Does nothing by default.
+    fn set_repcount(&mut self, _repcount: Option<usize>) -> Option<usize> {
+        None
+    }
+
+    /// Defines the procedure `name`, as `TO` does. Does nothing by default.
+    fn define(&mut self, _name: &str, _definition: Definition) {}
+
+    /// Gets the procedure defined as `name`. Defaults to `None`, so calling it is an error.
+    fn get_definition(&self, _name: &str) -> Option<Arc<Definition>> {
+        None
+    }
+
+    /// Runs the command primitive `name` with `args`. Defaults to an error, as no primitives are registered.
+    fn call_primitive(&mut self, name: &str, _args: &[f32]) -> Result<(), RuntimeError> {
+        Err(RuntimeError::UndefinedProcedure(name.to_string()))
+    }
+
+    /// Evaluates the query primitive `name` with `args`, or `None` if it gives no number. Defaults to an error,
+    /// as no primitives are registered.
+    fn evaluate_primitive(&self, name: &str, _args: &[f32]) -> Result<Option<f32>, RuntimeError> {
+        Err(RuntimeError::UndefinedProcedure(name.to_string()))
+    }
+
+    /// Runs `block` when `key` is pressed, as `WHENKEY` does. Does nothing by default.
+    fn on_key(&mut self, _key: &str, _block: Arc<Vec<ASTNode>>) {}
+
+    /// Runs `block` when the canvas is clicked, as `WHENCLICK` does. Does nothing by default.
+    fn on_click(&mut self, _block: Arc<Vec<ASTNode>>) {}
+
+    /// Runs `block` every frame, as `EVERYFRAME` does. Does nothing by default.
+    fn on_frame(&mut self, _block: Arc<Vec<ASTNode>>) {}
+
+    /// Whether numbers are whole numbers. Defaults to `false`.
+    fn is_integer_mode(&self) -> bool {
+        false
+    }
+
+    /// How far apart numbers may be while still being equal. Defaults to 0.
+    fn get_epsilon(&self) -> f32 {
+        0.0
+    }
+
+    /// Whether recoverable errors are recorded and skipped rather than stopping execution. Defaults to `false`.
+    fn is_continue_on_error(&self) -> bool {
+        false
+    }
+
+    /// The rules the names of variables must follow. Defaults to `NamePolicy::default()`.
+    fn get_name_policy(&self) -> NamePolicy {
+        NamePolicy::default()
+    }
+
+    /// Counts an executed instruction against the turtle's limits.
+    #[doc(hidden)]
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Records `procedure` to the audit log.
+    #[doc(hidden)]
+    fn audit(&mut self, _procedure: &Procedure) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Gets the value kept in `slot` by a shared expression of the running instruction, if there is one.
+    /// Defaults to `None`, so every occurrence of a shared expression evaluates it.
+    #[doc(hidden)]
+    fn shared_value(&self, _slot: usize) -> Option<Value> {
+        None
+    }
+
+    /// Keeps `value` in `slot` for the later occurrences of a shared expression in the running instruction.
+    #[doc(hidden)]
+    fn set_shared_value(&self, _slot: usize, _value: Value) {}
+
+    /// Forgets the values of shared expressions, as a new instruction starts.
+    #[doc(hidden)]
+    fn clear_shared_values(&mut self) {}
+
+    /// Records the span of the innermost instruction that raised the error being raised.
+    #[doc(hidden)]
+    fn locate_error(&mut self, _span: &Span) {}
+
+    /// Takes the span recorded by `locate_error`.
+    #[doc(hidden)]
+    fn take_error_span(&mut self) -> Option<Span> {
+        None
+    }
+
+    /// Records an error skipped in continue-on-error mode.
+    #[doc(hidden)]
+    fn add_diagnostic(&mut self, _error: RuntimeError, _span: Span) {}
+
+    /// Starts a scope for local variables.
+    #[doc(hidden)]
+    fn push_scope(&mut self, _procedure: bool) {}
+
+    /// Ends the innermost scope.
+    #[doc(hidden)]
+    fn pop_scope(&mut self) {}
+
+    /// Records entering a nested block.
+    #[doc(hidden)]
+    fn enter_block(&mut self) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Records leaving a nested block.
+    #[doc(hidden)]
+    fn exit_block(&mut self) {}
+
+    /// Records starting a procedure call.
+    #[doc(hidden)]
+    fn enter_call(&mut self) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Records finishing a procedure call.
+    #[doc(hidden)]
+    fn exit_call(&mut self) {}
+
+    /// Checks that a loop may run its block again.
+    #[doc(hidden)]
+    fn loop_iteration(&self, _keyword: &'static str, _iteration: u64) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+impl TurtleOps for Turtle {
+    fn pen_up(&mut self) {
+        Turtle::pen_up(self)
+    }
+
+    fn pen_down(&mut self) {
+        Turtle::pen_down(self)
+    }
+
+    fn forward(&mut self, distance: f32) -> Result<(), RuntimeError> {
+        Turtle::forward(self, distance)
+    }
+
+    fn turn(&mut self, degrees: f32) {
+        Turtle::turn(self, degrees)
+    }
+
+    fn add_variable(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        Turtle::add_variable(self, name, value)
+    }
+
+    fn get_variable(&self, name: &str) -> Result<&Value, RuntimeError> {
+        Turtle::get_variable(self, name)
+    }
+
+    fn back(&mut self, distance: f32) -> Result<(), RuntimeError> {
+        Turtle::back(self, distance)
+    }
+
+    fn left(&mut self, distance: f32) -> Result<(), RuntimeError> {
+        Turtle::left(self, distance)
+    }
+
+    fn right(&mut self, distance: f32) -> Result<(), RuntimeError> {
+        Turtle::right(self, distance)
+    }
+
+    fn move_polar(&mut self, angle: f32, distance: f32) -> Result<(), RuntimeError> {
+        Turtle::move_polar(self, angle, distance)
+    }
+
+    fn set_heading(&mut self, heading: f32) {
+        Turtle::set_heading(self, heading)
+    }
+
+    fn set_x(&mut self, x: f32) {
+        Turtle::set_x(self, x)
+    }
+
+    fn set_y(&mut self, y: f32) {
+        Turtle::set_y(self, y)
+    }
+
+    fn get_x(&self) -> f32 {
+        Turtle::get_x(self)
+    }
+
+    fn get_y(&self) -> f32 {
+        Turtle::get_y(self)
+    }
+
+    fn get_heading(&self) -> f32 {
+        Turtle::get_heading(self)
+    }
+
+    fn set_pen_color(&mut self, index: f32) -> Result<(), RuntimeError> {
+        Turtle::set_pen_color(self, index)
+    }
+
+    fn get_pen_color(&self) -> f32 {
+        Turtle::get_pen_color(self)
+    }
+
+    fn set_fill_color(&mut self, index: f32) -> Result<(), RuntimeError> {
+        Turtle::set_fill_color(self, index)
+    }
+
+    fn get_fill_color(&self) -> f32 {
+        Turtle::get_fill_color(self)
+    }
+
+    fn set_pen_size(&mut self, size: f32) -> Result<(), RuntimeError> {
+        Turtle::set_pen_size(self, size)
+    }
+
+    fn set_speed(&mut self, speed: f32) -> Result<(), RuntimeError> {
+        Turtle::set_speed(self, speed)
+    }
+
+    fn fill_at(&mut self, x: f32, y: f32) -> Result<(), RuntimeError> {
+        Turtle::fill_at(self, x, y)
+    }
+
+    fn toot(&mut self, frequency: f32, duration: f32) -> Result<(), RuntimeError> {
+        Turtle::toot(self, frequency, duration)
+    }
+
+    fn wait(&mut self, ticks: f32) -> Result<(), RuntimeError> {
+        Turtle::wait(self, ticks)
+    }
+
+    fn push_state(&mut self) -> Result<(), RuntimeError> {
+        Turtle::push_state(self)
+    }
+
+    fn pop_state(&mut self) -> Result<(), RuntimeError> {
+        Turtle::pop_state(self)
+    }
+
+    fn mark(&mut self, name: &str) -> Result<(), RuntimeError> {
+        Turtle::mark(self, name)
+    }
+
+    fn goto(&mut self, name: &str) -> Result<(), RuntimeError> {
+        Turtle::goto(self, name)
+    }
+
+    fn snapshot(&mut self, name: &str) -> Result<(), RuntimeError> {
+        Turtle::snapshot(self, name)
+    }
+
+    fn restore_image(&mut self, name: &str) -> Result<(), RuntimeError> {
+        Turtle::restore_image(self, name)
+    }
+
+    fn get_screen_width(&self) -> f32 {
+        Turtle::get_screen_width(self)
+    }
+
+    fn get_screen_height(&self) -> f32 {
+        Turtle::get_screen_height(self)
+    }
+
+    fn get_pixel_color(&self, x: f32, y: f32) -> f32 {
+        Turtle::get_pixel_color(self, x, y)
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        Turtle::set_seed(self, seed)
+    }
+
+    fn next_random(&self) -> f32 {
+        Turtle::next_random(self)
+    }
+
+    fn read_word(&self) -> Result<Value, RuntimeError> {
+        Turtle::read_word(self)
+    }
+
+    fn add_local_variable(&mut self, name: &str) -> Result<(), RuntimeError> {
+        Turtle::add_local_variable(self, name)
+    }
+
+    fn get_repcount(&self) -> f32 {
+        Turtle::get_repcount(self)
+    }
+
+    fn set_repcount(&mut self, repcount: Option<usize>) -> Option<usize> {
+        Turtle::set_repcount(self, repcount)
+    }
+
+    fn define(&mut self, name: &str, definition: Definition) {
+        Turtle::define(self, name, definition)
+    }
+
+    fn get_definition(&self, name: &str) -> Option<Arc<Definition>> {
+        Turtle::get_definition(self, name)
+    }
+
+    fn call_primitive(&mut self, name: &str, args: &[f32]) -> Result<(), RuntimeError> {
+        let primitive = self.get_primitives().get(name)
+            .ok_or_else(|| RuntimeError::UndefinedProcedure(name.to_string()))?;
+        primitive.execute(self, args)
+    }
+
+    fn evaluate_primitive(&self, name: &str, args: &[f32]) -> Result<Option<f32>, RuntimeError> {
+        let primitive = self.get_primitives().get(name)
+            .ok_or_else(|| RuntimeError::UndefinedProcedure(name.to_string()))?;
+        Ok(primitive.evaluate(self, args))
+    }
+
+    fn on_key(&mut self, key: &str, block: Arc<Vec<ASTNode>>) {
+        Turtle::on_key(self, key, block)
+    }
+
+    fn on_click(&mut self, block: Arc<Vec<ASTNode>>) {
+        Turtle::on_click(self, block)
+    }
+
+    fn on_frame(&mut self, block: Arc<Vec<ASTNode>>) {
+        Turtle::on_frame(self, block)
+    }
+
+    fn is_integer_mode(&self) -> bool {
+        Turtle::is_integer_mode(self)
+    }
+
+    fn get_epsilon(&self) -> f32 {
+        Turtle::get_epsilon(self)
+    }
+
+    fn is_continue_on_error(&self) -> bool {
+        Turtle::is_continue_on_error(self)
+    }
+
+    fn get_name_policy(&self) -> NamePolicy {
+        Turtle::get_name_policy(self)
+    }
+
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        Turtle::step(self)
+    }
+
+    fn audit(&mut self, procedure: &Procedure) -> Result<(), RuntimeError> {
+        Turtle::audit(self, procedure)
+    }
+
+    fn shared_value(&self, slot: usize) -> Option<Value> {
+        Turtle::shared_value(self, slot)
+    }
+
+    fn set_shared_value(&self, slot: usize, value: Value) {
+        Turtle::set_shared_value(self, slot, value)
+    }
+
+    fn clear_shared_values(&mut self) {
+        Turtle::clear_shared_values(self)
+    }
+
+    fn locate_error(&mut self, span: &Span) {
+        Turtle::locate_error(self, span)
+    }
+
+    fn take_error_span(&mut self) -> Option<Span> {
+        Turtle::take_error_span(self)
+    }
+
+    fn add_diagnostic(&mut self, error: RuntimeError, span: Span) {
+        Turtle::add_diagnostic(self, error, span)
+    }
+
+    fn push_scope(&mut self, procedure: bool) {
+        Turtle::push_scope(self, procedure)
+    }
+
+    fn pop_scope(&mut self) {
+        Turtle::pop_scope(self)
+    }
+
+    fn enter_block(&mut self) -> Result<(), RuntimeError> {
+        Turtle::enter_block(self)
+    }
+
+    fn exit_block(&mut self) {
+        Turtle::exit_block(self)
+    }
+
+    fn enter_call(&mut self) -> Result<(), RuntimeError> {
+        Turtle::enter_call(self)
+    }
+
+    fn exit_call(&mut self) {
+        Turtle::exit_call(self)
+    }
+
+    fn loop_iteration(&self, keyword: &'static str, iteration: u64) -> Result<(), RuntimeError> {
+        Turtle::loop_iteration(self, keyword, iteration)
+    }
+}
@@ -0,0 +1,392 @@
+//! # Optimizer
+//!
+//! This module contains passes that rewrite a parsed program into an equivalent one that is cheaper to execute.
+//!
+//! `eliminate_common_subexpressions` finds math expressions that appear more than once within a single
+//! instruction, such as `+ XCOR "10` in `SETX * + XCOR "10 + XCOR "10`, and rewrites them so the instruction
+//! evaluates them only once each time it runs. The arguments of an instruction are all evaluated, in order,
+//! before it changes the turtle, so queries and variables are safe to share between them. Expressions that
+//! give a different value each time, such as `RANDOM`, `READWORD`, and queries registered by the host, are
+//! never shared, and no instructions or variables are added, so the rewritten program does exactly what the
+//! original did, errors included.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::optimizer::eliminate_common_subexpressions;
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let asts = parse_content("SETX * + XCOR \"10 + XCOR \"10").unwrap();
+//! let optimized = eliminate_common_subexpressions(asts.clone());
+//! assert_eq!(optimized.len(), 1);
+//! assert_eq!(optimized[0].to_string(), asts[0].to_string());
+//!
+//! let mut turtle = Turtle::new(100, 100);
+//! run(&optimized, &mut turtle).unwrap();
+//! assert_eq!(turtle.get_x(), 3600.0);
+//!
+//! // A skipped instruction does not leave a value behind for the next time it runs.
+//! let source = "MAKE \"d \"1 REPEAT \"2 [ SETX + + XCOR / \"10 :d / \"10 :d MAKE \"d \"0 ]";
+//! let asts = parse_content(source).unwrap();
+//! let mut turtle = Turtle::new(100, 100);
+//! turtle.set_continue_on_error(true);
+//! run(&eliminate_common_subexpressions(asts), &mut turtle).unwrap();
+//! assert_eq!(turtle.get_x(), 70.0);
+//! assert_eq!(turtle.get_diagnostics().len(), 1);
+//!
+//! // Shared values are kept on the turtle running the program, so one optimized program can run on many at once.
+//! let optimized = eliminate_common_subexpressions(parse_content("SETX * + XCOR \"10 + XCOR \"10").unwrap());
+//! let xs: Vec<f32> = std::thread::scope(|scope| {
+//!     let runs: Vec<_> = [0, 10].into_iter().map(|start| {
+//!         let optimized = &optimized;
+//!         scope.spawn(move || {
+//!             let mut turtle = Turtle::new(100, 100);
+//!             run(&parse_content(&format!("SETX \"{start}")).unwrap(), &mut turtle).unwrap();
+//!             run(optimized, &mut turtle).unwrap();
+//!             turtle.get_x()
+//!         })
+//!     }).collect();
+//!     runs.into_iter().map(|run| run.join().unwrap()).collect()
+//! });
+//! assert_eq!(xs, [100.0, 400.0]);
+//! ```
+
+use crate::ast::{ASTNode, Condition, ControlFlow, Expression, ListOp, Math, Procedure, Query, Shared};
+
+/// Rewrites math expressions repeated within an instruction to be evaluated once, recursing into blocks.
+pub fn eliminate_common_subexpressions(instructions: impl Into<Vec<ASTNode>>) -> Vec<ASTNode> {
+    let _span = tracing::info_span!("optimize").entered();
+    eliminate_in_block(instructions.into())
+}
+
+fn eliminate_in_block(instructions: Vec<ASTNode>) -> Vec<ASTNode> {
+    instructions.into_iter().map(|instruction| match instruction {
+        ASTNode::Procedure(mut procedure, span) => {
+            let mut slot = 0;
+            while let Some(common) = find_common_subexpression(&procedure) {
+                let mut first = None;
+                for expression in procedure.expressions_mut() {
+                    replace(expression, &common, slot, &mut first);
+                }
+                slot += 1;
+            }
+            ASTNode::Procedure(procedure, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::If { condition, block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::If { condition, block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::While { condition, block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::While { condition, block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::DoWhile { block, condition }, span) => {
+            ASTNode::ControlFlow(ControlFlow::DoWhile { block: eliminate_in_block(block), condition }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::Until { block, condition }, span) => {
+            ASTNode::ControlFlow(ControlFlow::Until { block: eliminate_in_block(block), condition }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::DoTimes { variable, count, block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::DoTimes { variable, count, block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::Repeat { count, block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::Repeat { count, block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::To { name, parameters, block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::To { name, parameters, block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::For { variable, start, end, step, block }, span) => {
+            let block = eliminate_in_block(block);
+            ASTNode::ControlFlow(ControlFlow::For { variable, start, end, step, block }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::WhenKey { key, block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::WhenKey { key, block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::Catch { tag, block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::Catch { tag, block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::WhenClick { block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::WhenClick { block: eliminate_in_block(block) }, span)
+        },
+        ASTNode::ControlFlow(ControlFlow::EveryFrame { block }, span) => {
+            ASTNode::ControlFlow(ControlFlow::EveryFrame { block: eliminate_in_block(block) }, span)
+        },
+    }).collect()
+}
+
+/// Finds the outermost math expression that appears at least twice in the arguments of `procedure`.
+fn find_common_subexpression(procedure: &Procedure) -> Option<Expression> {
+    let mut candidates = Vec::new();
+    for expression in procedure.expressions() {
+        collect_math(expression, &mut candidates);
+    }
+    candidates.iter()
+        .find(|candidate| candidates.iter().filter(|other| other == candidate).count() > 1)
+        .map(|candidate| (*candidate).clone())
+}
+
+/// Collects every math sub-expression of `expression`, outermost first.
+fn collect_math<'a>(expression: &'a Expression, candidates: &mut Vec<&'a Expression>) {
+    match expression {
+        Expression::Math(math) => {
+            // Each `RANDOM` returns a different number, so repeats of it are not common subexpressions.
+            if is_pure(expression) {
+                candidates.push(expression);
+            }
+            math.operands().into_iter().for_each(|operand| collect_math(operand, candidates));
+        },
+        Expression::Bool(condition) => collect_condition(condition, candidates),
+        Expression::Call(_, args) => args.iter().for_each(|arg| collect_math(arg, candidates)),
+        _ => {},
+    }
+}
+
+/// Whether `expression` gives the same value each time it is evaluated in the same state. `RANDOM`,
+/// `READWORD`, and queries registered by the host may not, and neither may anything containing them.
+fn is_pure(expression: &Expression) -> bool {
+    match expression {
+        Expression::Query(Query::Random(_)) | Expression::ReadWord | Expression::Call(..) => false,
+        Expression::Query(Query::PixelColor(x, y)) => is_pure(x) && is_pure(y),
+        Expression::Math(math) => math.operands().into_iter().all(is_pure),
+        Expression::List(items) => items.iter().all(is_pure),
+        Expression::ListOp(op) => op.operands().into_iter().all(is_pure),
+        Expression::Bool(condition) => is_pure_condition(condition),
+        Expression::Shared(shared) => is_pure(&shared.expression),
+        Expression::Float(_) | Expression::Query(_) | Expression::Variable(_) | Expression::String(_) => true,
+    }
+}
+
+fn is_pure_condition(condition: &Condition) -> bool {
+    match condition {
+        Condition::Equal(lhs, rhs)
+        | Condition::NotEqual(lhs, rhs)
+        | Condition::NearlyEqual(lhs, rhs)
+        | Condition::LessThan(lhs, rhs)
+        | Condition::GreaterThan(lhs, rhs)
+        | Condition::LessEqual(lhs, rhs)
+        | Condition::GreaterEqual(lhs, rhs) => is_pure(lhs) && is_pure(rhs),
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => is_pure_condition(lhs) && is_pure_condition(rhs),
+        Condition::Not(condition) => is_pure_condition(condition),
+        Condition::Inside(value)
+        | Condition::IsNumber(value)
+        | Condition::IsWord(value)
+        | Condition::IsList(value)
+        | Condition::IsEmpty(value) => is_pure(value),
+    }
+}
+
+fn collect_condition<'a>(condition: &'a Condition, candidates: &mut Vec<&'a Expression>) {
+    match condition {
+        Condition::Equal(lhs, rhs)
+        | Condition::NotEqual(lhs, rhs)
+        | Condition::NearlyEqual(lhs, rhs)
+        | Condition::LessThan(lhs, rhs)
+        | Condition::GreaterThan(lhs, rhs)
+        | Condition::LessEqual(lhs, rhs)
+        | Condition::GreaterEqual(lhs, rhs) => {
+            collect_math(lhs, candidates);
+            collect_math(rhs, candidates);
+        },
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            collect_condition(lhs, candidates);
+            collect_condition(rhs, candidates);
+        },
+        Condition::Not(condition) => collect_condition(condition, candidates),
+        Condition::Inside(value)
+        | Condition::IsNumber(value)
+        | Condition::IsWord(value)
+        | Condition::IsList(value)
+        | Condition::IsEmpty(value) => collect_math(value, candidates),
+    }
+}
+
+/// Replaces every occurrence of `common` within `expression` with an occurrence sharing the value of `first`,
+/// creating `first`, with its value kept in `slot`, at the first occurrence found.
+fn replace(expression: &mut Expression, common: &Expression, slot: usize, first: &mut Option<Shared>) {
+    if expression == common {
+        let shared = match first {
+            Some(first) => first.repeat(),
+            None => first.insert(Shared::first(common.clone(), slot)).clone(),
+        };
+        *expression = Expression::Shared(Box::new(shared));
+        return;
+    }
+    match expression {
+        Expression::Math(math) => {
+            math.operands_mut().into_iter().for_each(|operand| replace(operand, common, slot, first));
+        },
+        Expression::Bool(condition) => replace_in_condition(condition, common, slot, first),
+        Expression::Call(_, args) => args.iter_mut().for_each(|arg| replace(arg, common, slot, first)),
+        _ => {},
+    }
+}
+
+fn replace_in_condition(condition: &mut Condition, common: &Expression, slot: usize, first: &mut Option<Shared>) {
+    match condition {
+        Condition::Equal(lhs, rhs)
+        | Condition::NotEqual(lhs, rhs)
+        | Condition::NearlyEqual(lhs, rhs)
+        | Condition::LessThan(lhs, rhs)
+        | Condition::GreaterThan(lhs, rhs)
+        | Condition::LessEqual(lhs, rhs)
+        | Condition::GreaterEqual(lhs, rhs) => {
+            replace(lhs, common, slot, first);
+            replace(rhs, common, slot, first);
+        },
+        Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+            replace_in_condition(lhs, common, slot, first);
+            replace_in_condition(rhs, common, slot, first);
+        },
+        Condition::Not(condition) => replace_in_condition(condition, common, slot, first),
+        Condition::Inside(value)
+        | Condition::IsNumber(value)
+        | Condition::IsWord(value)
+        | Condition::IsList(value)
+        | Condition::IsEmpty(value) => replace(value, common, slot, first),
+    }
+}
+
+impl ListOp {
+    pub(crate) fn operands(&self) -> Vec<&Expression> {
+        match self {
+            ListOp::First(list) | ListOp::ButFirst(list) | ListOp::Last(list) | ListOp::Count(list) | ListOp::Char(list) => {
+                vec![list]
+            },
+            ListOp::Item(first, second) | ListOp::Word(first, second) | ListOp::Sentence(first, second) => vec![first, second],
+        }
+    }
+
+    pub(crate) fn operands_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            ListOp::First(list) | ListOp::ButFirst(list) | ListOp::Last(list) | ListOp::Count(list) | ListOp::Char(list) => {
+                vec![list]
+            },
+            ListOp::Item(first, second) | ListOp::Word(first, second) | ListOp::Sentence(first, second) => vec![first, second],
+        }
+    }
+}
+
+impl Math {
+    pub(crate) fn operands(&self) -> Vec<&Expression> {
+        match self {
+            Math::Add(lhs, rhs)
+            | Math::Sub(lhs, rhs)
+            | Math::Mul(lhs, rhs)
+            | Math::Div(lhs, rhs)
+            | Math::Mod(lhs, rhs)
+            | Math::Remainder(lhs, rhs)
+            | Math::Power(lhs, rhs) => vec![lhs, rhs],
+            Math::Sin(expr)
+            | Math::Cos(expr)
+            | Math::Tan(expr)
+            | Math::Sqrt(expr)
+            | Math::Int(expr)
+            | Math::Round(expr)
+            | Math::Abs(expr) => vec![expr],
+            Math::Clamp(a, b, c) | Math::Lerp(a, b, c) => vec![a, b, c],
+            Math::MapRange(a, b, c, d, e) => vec![a, b, c, d, e],
+        }
+    }
+
+    pub(crate) fn operands_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Math::Add(lhs, rhs)
+            | Math::Sub(lhs, rhs)
+            | Math::Mul(lhs, rhs)
+            | Math::Div(lhs, rhs)
+            | Math::Mod(lhs, rhs)
+            | Math::Remainder(lhs, rhs)
+            | Math::Power(lhs, rhs) => vec![lhs, rhs],
+            Math::Sin(expr)
+            | Math::Cos(expr)
+            | Math::Tan(expr)
+            | Math::Sqrt(expr)
+            | Math::Int(expr)
+            | Math::Round(expr)
+            | Math::Abs(expr) => vec![expr],
+            Math::Clamp(a, b, c) | Math::Lerp(a, b, c) => vec![a, b, c],
+            Math::MapRange(a, b, c, d, e) => vec![a, b, c, d, e],
+        }
+    }
+}
+
+impl Procedure {
+    /// The expressions evaluated as arguments when the procedure runs. The name of a variable being assigned is not included.
+    pub(crate) fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Procedure::PenUp
+            | Procedure::PenDown
+            | Procedure::PushState
+            | Procedure::PopState
+            | Procedure::Mark(_)
+            | Procedure::Goto(_)
+            | Procedure::Snapshot(_)
+            | Procedure::RestoreImage(_)
+            | Procedure::Local(_) => vec![],
+            Procedure::Forward(expr)
+            | Procedure::Back(expr)
+            | Procedure::Left(expr)
+            | Procedure::Right(expr)
+            | Procedure::SetPenColor(expr)
+            | Procedure::SetFillColor(expr)
+            | Procedure::Turn(expr)
+            | Procedure::SetHeading(expr)
+            | Procedure::SetX(expr)
+            | Procedure::SetY(expr)
+            | Procedure::SetSeed(expr)
+            | Procedure::SetSpeed(expr)
+            | Procedure::Wait(expr)
+            | Procedure::SetPenSize(expr)
+            | Procedure::Throw(expr) => vec![expr],
+            Procedure::Make(_, value)
+            | Procedure::LocalMake(_, value)
+            | Procedure::AddAssign(_, value)
+            | Procedure::SubAssign(_, value)
+            | Procedure::MulAssign(_, value)
+            | Procedure::DivAssign(_, value) => vec![value],
+            Procedure::MovePolar(first, second) | Procedure::Toot(first, second) | Procedure::FillAt(first, second) => {
+                vec![first, second]
+            },
+            Procedure::Call(_, args) => args.iter().collect(),
+        }
+    }
+
+    pub(crate) fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Procedure::PenUp
+            | Procedure::PenDown
+            | Procedure::PushState
+            | Procedure::PopState
+            | Procedure::Mark(_)
+            | Procedure::Goto(_)
+            | Procedure::Snapshot(_)
+            | Procedure::RestoreImage(_)
+            | Procedure::Local(_) => vec![],
+            Procedure::Forward(expr)
+            | Procedure::Back(expr)
+            | Procedure::Left(expr)
+            | Procedure::Right(expr)
+            | Procedure::SetPenColor(expr)
+            | Procedure::SetFillColor(expr)
+            | Procedure::Turn(expr)
+            | Procedure::SetHeading(expr)
+            | Procedure::SetX(expr)
+            | Procedure::SetY(expr)
+            | Procedure::SetSeed(expr)
+            | Procedure::SetSpeed(expr)
+            | Procedure::Wait(expr)
+            | Procedure::SetPenSize(expr)
+            | Procedure::Throw(expr) => vec![expr],
+            Procedure::Make(_, value)
+            | Procedure::LocalMake(_, value)
+            | Procedure::AddAssign(_, value)
+            | Procedure::SubAssign(_, value)
+            | Procedure::MulAssign(_, value)
+            | Procedure::DivAssign(_, value) => vec![value],
+            Procedure::MovePolar(first, second) | Procedure::Toot(first, second) | Procedure::FillAt(first, second) => {
+                vec![first, second]
+            },
+            Procedure::Call(_, args) => args.iter_mut().collect(),
+        }
+    }
+}
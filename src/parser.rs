@@ -5,12 +5,45 @@
 //! The `ASTNode` enum is defined in the `ast` module.
 //! 
 //! The parser is implemented using the `chumsky` crate, which is a parser combinator library.
+//! 
+//! Math is written in prefix notation, such as `+ :x "10`, but the last argument of a command may also be
+//! written in infix notation, such as `FORWARD :x * 2 + 10`, where `*`, `/`, and `%` bind tighter than `+`
+//! and `-`. Infix is limited to the last argument because any other argument is followed by another one, so
+//! `MAKE "x - "5 "1` could otherwise mean either `MAKE (- "x "5) "1` or `MAKE "x (- "5 "1)`. The operands
+//! of infix operators may be prefix expressions, so `+ 1 2 * 3` is 9.
+//! 
+//! Any argument may be wrapped in parentheses to group it, such as `MAKE "x (+ 1 (* 2 3))`. Infix notation
+//! can be used anywhere inside parentheses, since the closing parenthesis shows where the argument ends.
+//! 
+//! ```
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//! 
+//! let asts = parse_content("MAKE \"x \"10 SETX :x * 2 + 10 SETY + 1 2 * 3").unwrap();
+//! let mut turtle = Turtle::new(100, 100);
+//! run(&asts, &mut turtle).unwrap();
+//! 
+//! assert_eq!(turtle.get_x(), 30.0);
+//! assert_eq!(turtle.get_y(), 9.0);
+//! 
+//! let asts = parse_content("MAKE \"x (+ 1 (* 2 3)) SETX (:x - 1) * 2 SETHEADING (90 / 2)").unwrap();
+//! run(&asts, &mut turtle).unwrap();
+//! 
+//! assert_eq!(turtle.get_x(), 12.0);
+//! assert_eq!(turtle.get_heading(), 45.0);
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use chumsky::{prelude::*, Stream};
 use regex::Regex;
 
+use crate::primitive::Primitives;
+use crate::program::Program;
 use crate::tokenizer::{tokenize, Token};
-use crate::ast::{ASTNode, ControlFlow, Condition, Expression, Procedure, Query, Math};
+use crate::ast::{ASTNode, ControlFlow, Condition, Expression, ListOp, Procedure, Query, Math};
 
 /// Parses the content of a Logo file and returns a vector of `ASTNode`s.
 /// If the content is invalid, returns a vector of `Simple<Token>` containing the errors.
@@ -33,14 +66,171 @@ use crate::ast::{ASTNode, ControlFlow, Condition, Expression, Procedure, Query,
 /// ```
 /// 
 /// 
-pub fn parse_content(content:&str) -> Result<Vec<ASTNode>, Vec<Simple<Token>>>{
-	let token_iter = tokenize(content);
-	let token_stream = Stream::from_iter(content.len()..content.len(), token_iter);
-	let asts = parser().parse(token_stream)?;
+pub fn parse_content(content:&str) -> Result<Program, Vec<Simple<Token>>>{
+	parse_content_with(content, &Primitives::new())
+}
+
+/// Parses the content of a Logo file, recognising the commands and queries in `primitives`
+/// in addition to the built-in ones.
+/// 
+/// Registered commands are parsed into `Procedure::Call` nodes and registered queries into
+/// `Expression::Call` nodes, each taking the number of arguments given by the primitive's arity.
+/// Calls to procedures defined with `TO` anywhere in `content` are also parsed into `Procedure::Call`
+/// nodes, taking one argument per parameter, and take precedence over primitives with the same name.
+pub fn parse_content_with(content: &str, primitives: &Primitives) -> Result<Program, Vec<Simple<Token>>>{
+	let _span = tracing::info_span!("parse", bytes = content.len()).entered();
+	parse_tokens(tokenize(content).collect(), content.len(), primitives).map(Program::new)
+}
+
+/// Parses tokens already produced by `tokenize`, recognising the commands and queries in `primitives`, as
+/// `parse_content_with` does. `end` is the length of the source, where errors at the end of input are reported.
+/// 
+/// Tokenizing separately lets callers time the two phases, or reuse the tokens.
+pub fn parse_tokens(
+	tokens: Vec<(Token, std::ops::Range<usize>)>,
+	end: usize,
+	primitives: &Primitives,
+) -> Result<Vec<ASTNode>, Vec<Simple<Token>>> {
+	parse_tokens_with_definitions(tokens, end, primitives, &HashMap::new())
+}
+
+/// Parses tokens as `parse_tokens` does, also recognising calls to the procedures in `definitions`, which
+/// maps the name of each procedure defined before these tokens, such as in an earlier input to a REPL, to
+/// its number of parameters. A procedure defined again in the tokens takes its new number of parameters.
+pub fn parse_tokens_with_definitions(
+	tokens: Vec<(Token, std::ops::Range<usize>)>,
+	end: usize,
+	primitives: &Primitives,
+	definitions: &HashMap<String, usize>,
+) -> Result<Vec<ASTNode>, Vec<Simple<Token>>> {
+	let mut arities = definitions.clone();
+	arities.extend(definition_arities(&tokens));
+	let definitions = Arc::new(arities);
+	let token_stream = Stream::from_iter(end..end, tokens.into_iter());
+	let asts = parser(primitives, definitions).parse(token_stream).inspect_err(|errors| {
+		tracing::debug!(errors = errors.len(), "failed to parse");
+	})?;
+	tracing::debug!(instructions = asts.len(), "parsed");
 	Ok(asts)
 }
 
-fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
+/// Finds the number of parameters of each procedure defined with `TO`, so calls can be parsed before
+/// the parser reaches the definition. If a name is defined more than once, the first definition wins.
+fn definition_arities(tokens: &[(Token, std::ops::Range<usize>)]) -> HashMap<String, usize> {
+	let mut arities = HashMap::new();
+	for (i, window) in tokens.windows(2).enumerate() {
+		if let [(Token::To, _), (Token::Word(name), _)] = window {
+			let arity = tokens[i + 2..].iter()
+				.take_while(|(token, _)| matches!(token, Token::Variable(_) | Token::Value(_)))
+				.count();
+			arities.entry(name.clone()).or_insert(arity);
+		}
+	}
+	arities
+}
+
+/// Finds an upper bound on how deeply `tokens` nest, so a service can reject a program that would make the
+/// parser or the interpreter recurse deeply enough to overflow the stack, before parsing it.
+/// 
+/// Each bracket and parenthesis opens a level, and so does each operator, query, and call of a procedure with
+/// parameters, as the next expression in the instruction may be its argument. A new instruction starts again
+/// from the level of the block it is in.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::parser::nesting_depth;
+/// use rslogo::tokenizer::tokenize;
+/// 
+/// let depth = |source: &str| nesting_depth(&tokenize(source).collect::<Vec<_>>());
+/// assert_eq!(depth("FORWARD \"1 FORWARD \"2 FORWARD \"3"), 0);
+/// assert_eq!(depth("REPEAT \"4 [ FORWARD + \"1 :x ]"), 2);
+/// assert_eq!(depth(&format!("FORWARD {}\"1", "+ \"1 ".repeat(10_000))), 10_000);
+/// ```
+pub fn nesting_depth(tokens: &[(Token, std::ops::Range<usize>)]) -> usize {
+	let arities = definition_arities(tokens);
+	// The levels opened in the current instruction, and those opened by the brackets it is inside of.
+	let (mut levels, mut outer, mut enclosing) = (0, 0, Vec::new());
+	let mut deepest = 0;
+	for (token, _) in tokens {
+		match token {
+			Token::LParen | Token::OpenGroup => {
+				enclosing.push(levels);
+				outer += levels + 1;
+				levels = 0;
+			},
+			Token::RParen | Token::CloseGroup => {
+				if let Some(enclosing) = enclosing.pop() {
+					outer -= enclosing + 1;
+					levels = enclosing;
+				}
+			},
+			Token::Value(_) | Token::Variable(_) => {},
+			Token::Word(name) if arities.get(name).is_none_or(|&arity| arity == 0) => {},
+			token if starts_instruction(token) => levels = 0,
+			_ => levels += 1,
+		}
+		deepest = deepest.max(outer + levels);
+	}
+	deepest
+}
+
+/// Whether `token` is a command or control keyword, which can only begin an instruction.
+fn starts_instruction(token: &Token) -> bool {
+	matches!(
+		token,
+		Token::PenUp | Token::PenDown | Token::PushState | Token::PopState | Token::Forward | Token::Back
+			| Token::Left | Token::Right | Token::MovePolar | Token::SetPenColor | Token::SetFillColor | Token::Turn
+			| Token::SetHeading | Token::SetX | Token::SetY | Token::SetSeed | Token::SetSpeed | Token::SetPenSize
+			| Token::FillAt | Token::Toot | Token::Wait | Token::Mark | Token::Goto | Token::Snapshot
+			| Token::RestoreImage | Token::Make | Token::Local | Token::LocalMake | Token::AddAssign
+			| Token::SubAssign | Token::MulAssign | Token::DivAssign | Token::If | Token::While | Token::DoWhile
+			| Token::Until | Token::DoTimes | Token::For | Token::WhenKey | Token::WhenClick | Token::EveryFrame
+			| Token::Repeat | Token::To | Token::End | Token::Catch | Token::Throw
+	)
+}
+
+/// Whether `expression` may evaluate to a word, so it can name the variable set by `MAKE` or `ADDASSIGN`.
+/// Names may be built while the program runs, such as `MAKE WORD "var :i "10`.
+fn is_word(expression: &Expression) -> bool {
+	matches!(expression, Expression::String(_) | Expression::Variable(_) | Expression::ListOp(_))
+}
+
+/// How tightly an infix operator binds, or `None` if `token` cannot be used as one.
+fn precedence(token: &Token) -> Option<u8> {
+	match token {
+		Token::Add | Token::Sub => Some(1),
+		Token::Mul | Token::Div | Token::Mod => Some(2),
+		_ => None,
+	}
+}
+
+/// Combines `lhs` with the infix operators and operands in `rest` that bind at least as tightly as
+/// `min_precedence`, applying operators of equal precedence from left to right.
+fn climb<I: Iterator<Item = (Token, Expression)>>(
+	mut lhs: Expression,
+	rest: &mut std::iter::Peekable<I>,
+	min_precedence: u8,
+) -> Expression {
+	while let Some(current) = rest.peek().and_then(|(token, _)| precedence(token)).filter(|&p| p >= min_precedence) {
+		let (token, mut rhs) = rest.next().unwrap();
+		while let Some(next) = rest.peek().and_then(|(token, _)| precedence(token)).filter(|&p| p > current) {
+			rhs = climb(rhs, rest, next);
+		}
+		let math = match token {
+			Token::Add => Math::Add(lhs, rhs),
+			Token::Sub => Math::Sub(lhs, rhs),
+			Token::Mul => Math::Mul(lhs, rhs),
+			Token::Div => Math::Div(lhs, rhs),
+			Token::Mod => Math::Mod(lhs, rhs),
+			_ => unreachable!(),
+		};
+		lhs = Expression::Math(Box::new(math));
+	}
+	lhs
+}
+
+fn parser(primitives: &Primitives, definitions: Arc<HashMap<String, usize>>) -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 	// Helper parsers
 	let value = select! {
 		Token::Value(s) if Regex::new(r"^-?[0-9]*\.?[0-9]+$").unwrap().is_match(&s) => Expression::Float(s.parse().unwrap()),
@@ -52,19 +242,110 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 		Token::YCOR => Expression::Query(Query::YCOR),
 		Token::HEADING => Expression::Query(Query::HEADING),
 		Token::COLOR => Expression::Query(Query::COLOR),
+		Token::FILLCOLOR => Expression::Query(Query::FILLCOLOR),
+		Token::SCREENWIDTH => Expression::Query(Query::SCREENWIDTH),
+		Token::SCREENHEIGHT => Expression::Query(Query::SCREENHEIGHT),
+		Token::REPCOUNT => Expression::Query(Query::REPCOUNT),
+		Token::ReadWord => Expression::ReadWord,
 	};
 
+	let infix_op = just(Token::Add)
+		.or(just(Token::Sub))
+		.or(just(Token::Mul))
+		.or(just(Token::Div))
+		.or(just(Token::Mod));
+
 	// Recursive parsers
+	let query_names = primitives.clone();
+	let query_primitives = primitives.clone();
 	let arg = recursive(|math| {
 		let add = just(Token::Add);
 		let sub = just(Token::Sub);
 		let mul = just(Token::Mul);
 		let div = just(Token::Div);
+		let modulo = just(Token::Mod);
+		let remainder = just(Token::Remainder);
+		let power = just(Token::Power);
 
-		let op = add.or(sub).or(mul).or(div);
+		let op = add.or(sub).or(mul).or(div).or(modulo).or(remainder).or(power);
+		let unary_op = just(Token::Sin)
+			.or(just(Token::Cos))
+			.or(just(Token::Tan))
+			.or(just(Token::Sqrt))
+			.or(just(Token::Int))
+			.or(just(Token::Round))
+			.or(just(Token::Abs));
 		let body = math.clone()
 			.then(math.clone());
 
+		let call_args = math.clone();
+		let call = select! { Token::Word(name) if query_names.query_arity(&name).is_some() => name }
+			.then_with(move |name| {
+				let arity = query_primitives.query_arity(&name).unwrap_or_default();
+				call_args.clone()
+					.repeated()
+					.exactly(arity)
+					.map(move |args| Expression::Call(name.clone(), args))
+			});
+
+		let ternary_op = just(Token::Clamp).or(just(Token::Lerp));
+		let map_range = just(Token::MapRange)
+			.ignore_then(math.clone().repeated().exactly(5))
+			.map(|args| {
+				let [value, in_low, in_high, out_low, out_high]: [Expression; 5] = args.try_into().unwrap();
+				Expression::Math(Box::new(Math::MapRange(value, in_low, in_high, out_low, out_high)))
+			});
+
+		let group = math.clone()
+			.then(infix_op.clone().then(math.clone()).repeated())
+			.map(|(first, rest)| climb(first, &mut rest.into_iter().peekable(), 0))
+			.delimited_by(just(Token::OpenGroup), just(Token::CloseGroup));
+
+		let list = math.clone()
+			.or(select! { Token::Word(word) => Expression::String(word) })
+			.repeated()
+			.delimited_by(just(Token::LParen), just(Token::RParen))
+			.map(Expression::List);
+		let list_op = just(Token::First)
+			.or(just(Token::ButFirst))
+			.or(just(Token::Last))
+			.or(just(Token::Count))
+			.then(math.clone())
+			.map(|(token, list)| {
+				let op = match token {
+					Token::First => ListOp::First(list),
+					Token::ButFirst => ListOp::ButFirst(list),
+					Token::Last => ListOp::Last(list),
+					Token::Count => ListOp::Count(list),
+					_ => unreachable!(),
+				};
+				Expression::ListOp(Box::new(op))
+			})
+			.or(just(Token::Item)
+				.or(just(Token::Concat))
+				.or(just(Token::Sentence))
+				.then(math.clone().then(math.clone()))
+				.map(|(token, (first, second))| {
+					let op = match token {
+						Token::Item => ListOp::Item(first, second),
+						Token::Concat => ListOp::Word(first, second),
+						Token::Sentence => ListOp::Sentence(first, second),
+						_ => unreachable!(),
+					};
+					Expression::ListOp(Box::new(op))
+				}))
+			.or(just(Token::Char)
+				.ignore_then(math.clone())
+				.map(|code| Expression::ListOp(Box::new(ListOp::Char(code)))));
+
+		let random = just(Token::Random)
+			.ignore_then(math.clone())
+			.map(|max| Expression::Query(Query::Random(Box::new(max))))
+			.or(just(Token::PixelColor)
+				.ignore_then(math.clone())
+				.then(math.clone())
+				.map(|(x, y)| Expression::Query(Query::PixelColor(Box::new(x), Box::new(y)))));
+
 		op.then(body)
 			.try_map(|(token, (lhs, rhs)), _span| {
 				match token {
@@ -72,31 +353,75 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 					Token::Sub => Ok(Expression::Math(Box::new(Math::Sub(lhs, rhs)))),
 					Token::Mul => Ok(Expression::Math(Box::new(Math::Mul(lhs, rhs)))),
 					Token::Div => Ok(Expression::Math(Box::new(Math::Div(lhs, rhs)))),
+					Token::Mod => Ok(Expression::Math(Box::new(Math::Mod(lhs, rhs)))),
+					Token::Remainder => Ok(Expression::Math(Box::new(Math::Remainder(lhs, rhs)))),
+					Token::Power => Ok(Expression::Math(Box::new(Math::Power(lhs, rhs)))),
 					_ => unreachable!(),
 				}
-			}).or(value)
+			}).or(unary_op.then(math.clone())
+				.try_map(|(token, arg), _span| {
+					match token {
+						Token::Sin => Ok(Expression::Math(Box::new(Math::Sin(arg)))),
+						Token::Cos => Ok(Expression::Math(Box::new(Math::Cos(arg)))),
+						Token::Tan => Ok(Expression::Math(Box::new(Math::Tan(arg)))),
+						Token::Sqrt => Ok(Expression::Math(Box::new(Math::Sqrt(arg)))),
+						Token::Int => Ok(Expression::Math(Box::new(Math::Int(arg)))),
+						Token::Round => Ok(Expression::Math(Box::new(Math::Round(arg)))),
+						Token::Abs => Ok(Expression::Math(Box::new(Math::Abs(arg)))),
+						_ => unreachable!(),
+					}
+				}))
+			.or(ternary_op.then(math.clone()).then(math.clone().then(math.clone()))
+				.try_map(|((token, a), (b, c)), _span| {
+					match token {
+						Token::Clamp => Ok(Expression::Math(Box::new(Math::Clamp(a, b, c)))),
+						Token::Lerp => Ok(Expression::Math(Box::new(Math::Lerp(a, b, c)))),
+						_ => unreachable!(),
+					}
+				}))
+			.or(map_range)
+			.or(value)
 			.or(variable)
 			.or(query)
+			.or(random)
+			.or(call)
+			.or(group)
+			.or(list)
+			.or(list_op)
 	});
 
+	// The last argument of a command, which may be written in infix notation
+	let last_arg = arg.clone()
+		.then(infix_op.then(arg.clone()).repeated())
+		.map(|(first, rest)| climb(first, &mut rest.into_iter().peekable(), 0));
+
 	let condition = recursive(|cond| {
 		let equal = just(Token::Equal);
 		let not_eequal = just(Token::NotEqual);
+		let nearly_equal = just(Token::NearlyEqual);
 		let less_than = just(Token::LessThan);
 		let greater_than = just(Token::GreaterThan);
+		let less_equal = just(Token::LessEqual);
+		let greater_equal = just(Token::GreaterEqual);
 
 		let math_cond =  equal
 			.or(not_eequal)
+			.or(nearly_equal)
 			.or(less_than)
 			.or(greater_than)
+			.or(less_equal)
+			.or(greater_equal)
 			.then(arg.clone()
 				.then(arg.clone()))
 			.try_map(|(token, (lhs, rhs)), _span| {
 				match token {
 					Token::Equal => Ok(Condition::Equal(lhs, rhs)),
 					Token::NotEqual => Ok(Condition::NotEqual(lhs, rhs)),
+					Token::NearlyEqual => Ok(Condition::NearlyEqual(lhs, rhs)),
 					Token::LessThan => Ok(Condition::LessThan(lhs, rhs)),
 					Token::GreaterThan => Ok(Condition::GreaterThan(lhs, rhs)),
+					Token::LessEqual => Ok(Condition::LessEqual(lhs, rhs)),
+					Token::GreaterEqual => Ok(Condition::GreaterEqual(lhs, rhs)),
 					_ => unreachable!(),
 				}
 			});
@@ -116,7 +441,30 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 				}
 			});
 
-		math_cond.or(bool_cond)
+		let not_cond = just(Token::Not)
+			.ignore_then(cond.clone())
+			.map(|cond| Condition::Not(Box::new(cond)));
+
+		let inside = just(Token::Inside)
+			.ignore_then(arg.clone())
+			.map(Condition::Inside);
+
+		let type_cond = just(Token::NumberP)
+			.or(just(Token::WordP))
+			.or(just(Token::ListP))
+			.or(just(Token::EmptyP))
+			.then(arg.clone())
+			.try_map(|(token, value), _span| {
+				match token {
+					Token::NumberP => Ok(Condition::IsNumber(value)),
+					Token::WordP => Ok(Condition::IsWord(value)),
+					Token::ListP => Ok(Condition::IsList(value)),
+					Token::EmptyP => Ok(Condition::IsEmpty(value)),
+					_ => unreachable!(),
+				}
+			});
+
+		math_cond.or(bool_cond).or(not_cond).or(inside).or(type_cond)
 	});
 
 	// Procedure parsers
@@ -125,10 +473,14 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 		.or(end());
 	let procedure_no_args = just(Token::PenUp)
 		.or(just(Token::PenDown))
+		.or(just(Token::PushState))
+		.or(just(Token::PopState))
 		.try_map(|token, _span| {
 			match token {
-				Token::PenUp => Ok(ASTNode::Procedure(Procedure::PenUp)),
-				Token::PenDown => Ok(ASTNode::Procedure(Procedure::PenDown)),
+				Token::PenUp => Ok(Procedure::PenUp),
+				Token::PenDown => Ok(Procedure::PenDown),
+				Token::PushState => Ok(Procedure::PushState),
+				Token::PopState => Ok(Procedure::PopState),
 				_ => unreachable!(),
 			}
 		}).then_ignore(no_arg.clone());
@@ -141,20 +493,51 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 		.or(just(Token::SetHeading))
 		.or(just(Token::SetX))
 		.or(just(Token::SetY))
+		.or(just(Token::SetSeed))
+		.or(just(Token::SetSpeed))
+		.or(just(Token::Wait))
+		.or(just(Token::SetPenSize))
+		.or(just(Token::Throw))
 		.or(just(Token::SetPenColor))
-		.then(arg.clone())
+		.or(just(Token::SetFillColor))
+		.then(last_arg.clone())
 		.try_map(|(token, value), _span| {
 			match token {
-				Token::Forward => Ok(ASTNode::Procedure(Procedure::Forward(value))),
-				Token::Back => Ok(ASTNode::Procedure(Procedure::Back(value))),
-				Token::Left => Ok(ASTNode::Procedure(Procedure::Left(value))),
-				Token::Right => Ok(ASTNode::Procedure(Procedure::Right(value))),
-				Token::Turn => Ok(ASTNode::Procedure(Procedure::Turn(value))),
-				Token::SetHeading => Ok(ASTNode::Procedure(Procedure::SetHeading(value))),
-				Token::SetX => Ok(ASTNode::Procedure(Procedure::SetX(value))),
-				Token::SetY => Ok(ASTNode::Procedure(Procedure::SetY(value))),
-				Token::SetPenColor => Ok(ASTNode::Procedure(Procedure::SetPenColor(value))),
+				Token::Forward => Ok(Procedure::Forward(value)),
+				Token::Back => Ok(Procedure::Back(value)),
+				Token::Left => Ok(Procedure::Left(value)),
+				Token::Right => Ok(Procedure::Right(value)),
+				Token::Turn => Ok(Procedure::Turn(value)),
+				Token::SetHeading => Ok(Procedure::SetHeading(value)),
+				Token::SetX => Ok(Procedure::SetX(value)),
+				Token::SetY => Ok(Procedure::SetY(value)),
+				Token::SetSeed => Ok(Procedure::SetSeed(value)),
+				Token::SetSpeed => Ok(Procedure::SetSpeed(value)),
+				Token::Wait => Ok(Procedure::Wait(value)),
+				Token::SetPenSize => Ok(Procedure::SetPenSize(value)),
+				Token::Throw => Ok(Procedure::Throw(value)),
+				Token::SetPenColor => Ok(Procedure::SetPenColor(value)),
+				Token::SetFillColor => Ok(Procedure::SetFillColor(value)),
+				_ => unreachable!(),
+			}
+		}).then_ignore(no_arg.clone());
+
+	let bookmark = just(Token::Mark)
+		.or(just(Token::Goto))
+		.or(just(Token::Snapshot))
+		.or(just(Token::RestoreImage))
+		.then(arg.clone())
+		.try_map(|(token, name), span| {
+			let (keyword, procedure): (_, fn(String) -> Procedure) = match token {
+				Token::Mark => ("MARK", Procedure::Mark),
+				Token::Goto => ("GOTO", Procedure::Goto),
+				Token::Snapshot => ("SNAPSHOT", Procedure::Snapshot),
+				Token::RestoreImage => ("RESTOREIMAGE", Procedure::RestoreImage),
 				_ => unreachable!(),
+			};
+			match name {
+				Expression::String(name) => Ok(procedure(name)),
+				_ => Err(Simple::custom(span, format!("Argument of {keyword} should be a name"))),
 			}
 		}).then_ignore(no_arg.clone());
 
@@ -162,29 +545,96 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 		.map(|c| Expression::Bool(Box::new(c)));
 
 	let make = just(Token::Make)
-		.ignore_then(arg.clone()
-			.then(arg.clone().or(bool.clone())))
-		.try_map(|(name, value), span| {
-			let name = match name {
-				Expression::String(s) => Expression::Variable(s),
-				_ => return Err(Simple::custom(span, "First argument of MAKE should be a variable")),
+		.or(just(Token::LocalMake))
+		.then(arg.clone()
+			.then(last_arg.clone().or(bool.clone())))
+		.try_map(|(token, (name, value)), span| {
+			let (keyword, procedure): (_, fn(Expression, Expression) -> Procedure) = match token {
+				Token::Make => ("MAKE", Procedure::Make),
+				Token::LocalMake => ("LOCALMAKE", Procedure::LocalMake),
+				_ => unreachable!(),
 			};
-			Ok(ASTNode::Procedure(Procedure::Make(name, value)))	
+			if !is_word(&name) {
+				return Err(Simple::custom(span, format!("First argument of {keyword} should be a word")));
+			}
+			Ok(procedure(name, value))
 		}).then_ignore(no_arg.clone());
 
-	let add_assign = just(Token::AddAssign)
-		.ignore_then(arg.clone()
-			.then(arg.clone()))
-		.try_map(| (name, value), span| {
-			let name = match name {
-				Expression::String(s) => Expression::Variable(s),
-				_ => return Err(Simple::custom(span, "First argument of ADDASSIGN should be a variable")),
+	let local = just(Token::Local)
+		.ignore_then(last_arg.clone())
+		.try_map(|name, span| {
+			if !is_word(&name) {
+				return Err(Simple::custom(span, "Argument of LOCAL should be a word"));
+			}
+			Ok(Procedure::Local(name))
+		}).then_ignore(no_arg.clone());
+
+	let compound_assign = just(Token::AddAssign)
+		.or(just(Token::SubAssign))
+		.or(just(Token::MulAssign))
+		.or(just(Token::DivAssign))
+		.then(arg.clone()
+			.then(last_arg.clone()))
+		.try_map(| (token, (name, value)), span| {
+			let (keyword, procedure): (_, fn(Expression, Expression) -> Procedure) = match token {
+				Token::AddAssign => ("ADDASSIGN", Procedure::AddAssign),
+				Token::SubAssign => ("SUBASSIGN", Procedure::SubAssign),
+				Token::MulAssign => ("MULASSIGN", Procedure::MulAssign),
+				Token::DivAssign => ("DIVASSIGN", Procedure::DivAssign),
+				_ => unreachable!(),
 			};
-			Ok(ASTNode::Procedure(Procedure::AddAssign(name, value)))
+			if !is_word(&name) {
+				return Err(Simple::custom(span, format!("First argument of {keyword} should be a word")));
+			}
+			Ok(procedure(name, value))
 		}).then_ignore(no_arg.clone());
 	
-	let procedure_two_args = make.or(add_assign);
-	let procedure = procedure_no_args.or(procedure_one_arg).or(procedure_two_args);
+	let move_polar = just(Token::MovePolar)
+		.ignore_then(arg.clone()
+			.then(last_arg.clone()))
+		.map(|(angle, distance)| Procedure::MovePolar(angle, distance))
+		.then_ignore(no_arg.clone());
+
+	let toot = just(Token::Toot)
+		.ignore_then(arg.clone()
+			.then(last_arg.clone()))
+		.map(|(frequency, duration)| Procedure::Toot(frequency, duration))
+		.then_ignore(no_arg.clone());
+
+	let fill_at = just(Token::FillAt)
+		.ignore_then(arg.clone()
+			.then(last_arg.clone()))
+		.map(|(x, y)| Procedure::FillAt(x, y))
+		.then_ignore(no_arg.clone());
+
+	let procedure_two_args = make.or(compound_assign).or(move_polar).or(toot).or(fill_at);
+
+	let command_names = primitives.clone();
+	let command_primitives = primitives.clone();
+	let command_definitions = definitions.clone();
+	let call_args = arg.clone();
+	let call_last_arg = last_arg.clone();
+	let procedure_call = select! {
+		Token::Word(name) if definitions.contains_key(&name) || command_names.command_arity(&name).is_some() => name
+	}
+		.then_with(move |name| {
+			let arity = command_definitions.get(&name).copied()
+				.or_else(|| command_primitives.command_arity(&name))
+				.unwrap_or_default();
+			call_args.clone()
+				.repeated()
+				.exactly(arity.saturating_sub(1))
+				.chain(call_last_arg.clone().repeated().exactly(arity.min(1)))
+				.map(move |args| Procedure::Call(name.clone(), args))
+		}).then_ignore(no_arg.clone());
+
+	let procedure = procedure_no_args
+		.or(procedure_one_arg)
+		.or(bookmark)
+		.or(local)
+		.or(procedure_two_args)
+		.or(procedure_call)
+		.map_with_span(ASTNode::Procedure);
 	
 	// Control flow parsers
 	let control_flow = recursive(|control_flow| {
@@ -194,6 +644,7 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 
 		let if_condition = just(Token::If).then(cond.clone());
 		let while_condition = just(Token::While).then(cond.clone());
+		let repeat_count = just(Token::Repeat).then(last_arg.clone());
 
 		let body = procedure.clone()
 			.or(control_flow)
@@ -202,19 +653,97 @@ fn parser() -> impl Parser<Token, Vec<ASTNode>, Error = Simple<Token>> {
 			.delimited_by(just(Token::LParen), just(Token::RParen));
 
 		if_condition.or(while_condition)
-			.then(body)
-			.try_map(|((token, condition), body), _span| {
+			.or(repeat_count)
+			.then(body.clone())
+			.try_map(|((token, condition), body), span| {
 				let control_flow = match token {
 					Token::If => ControlFlow::If { condition, block: body },
 					Token::While => ControlFlow::While { condition, block: body },
+					Token::Repeat => ControlFlow::Repeat { count: condition, block: body },
 					_ => unreachable!(),
 				};
-				Ok(ASTNode::ControlFlow(control_flow))
+				Ok(ASTNode::ControlFlow(control_flow, span))
 			})
+			.or(just(Token::DoWhile)
+				.or(just(Token::Until))
+				.then(body.clone())
+				.then(cond.clone())
+				.map_with_span(|((token, block), condition), span| {
+					let control_flow = match token {
+						Token::DoWhile => ControlFlow::DoWhile { block, condition },
+						Token::Until => ControlFlow::Until { block, condition },
+						_ => unreachable!(),
+					};
+					ASTNode::ControlFlow(control_flow, span)
+				}))
+			.or(just(Token::DoTimes)
+				.ignore_then(arg.clone())
+				.then(last_arg.clone())
+				.then(body.clone())
+				.try_map(|((variable, count), block), span| {
+					if !is_word(&variable) {
+						return Err(Simple::custom(span, "First argument of DOTIMES should be a word"));
+					}
+					Ok(ASTNode::ControlFlow(ControlFlow::DoTimes { variable, count, block }, span))
+				}))
+			.or(just(Token::For)
+				.ignore_then(arg.clone())
+				.then(arg.clone())
+				.then(arg.clone())
+				.then(last_arg.clone())
+				.then(body.clone())
+				.try_map(|((((variable, start), end), step), block), span| {
+					if !is_word(&variable) {
+						return Err(Simple::custom(span, "First argument of FOR should be a word"));
+					}
+					Ok(ASTNode::ControlFlow(ControlFlow::For { variable, start, end, step, block }, span))
+				}))
+			.or(just(Token::WhenKey)
+				.or(just(Token::Catch))
+				.then(last_arg.clone())
+				.then(body.clone())
+				.map_with_span(|((token, word), block), span| {
+					let control_flow = match token {
+						Token::WhenKey => ControlFlow::WhenKey { key: word, block },
+						Token::Catch => ControlFlow::Catch { tag: word, block },
+						_ => unreachable!(),
+					};
+					ASTNode::ControlFlow(control_flow, span)
+				}))
+			.or(just(Token::WhenClick)
+				.or(just(Token::EveryFrame))
+				.then(body)
+				.map_with_span(|(token, block), span| {
+					let control_flow = match token {
+						Token::WhenClick => ControlFlow::WhenClick { block },
+						Token::EveryFrame => ControlFlow::EveryFrame { block },
+						_ => unreachable!(),
+					};
+					ASTNode::ControlFlow(control_flow, span)
+				}))
 	});
 
+	let parameter = select! {
+		Token::Variable(name) => name,
+		Token::Value(name) => name,
+	};
+	let definition = just(Token::To)
+		.ignore_then(select! { Token::Word(name) => name })
+		.then(parameter.repeated())
+		.then(procedure.clone()
+			.or(control_flow.clone())
+			.repeated())
+		.then_ignore(just(Token::End))
+		.try_map(|((name, parameters), block), span| {
+			if parameters.iter().any(|parameter| parameter.is_empty()) {
+				return Err(Simple::custom(span, format!("Parameters of {name} should be named")));
+			}
+			Ok(ASTNode::ControlFlow(ControlFlow::To { name, parameters, block }, span))
+		});
+
 	procedure
 		.or(control_flow)
+		.or(definition)
 		.repeated()
 		.at_least(1)
 }
\ No newline at end of file
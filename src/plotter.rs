@@ -0,0 +1,203 @@
+//! # Plotter
+//!
+//! This module contains the `Plotter`, which drives a physical turtle robot or pen plotter from a
+//! `DisplayList` instead of rendering it onto an image.
+//!
+//! The plotter writes one command per line to any `Write`, usually a serial port. What those commands look
+//! like is set by a `Protocol`, which defaults to G-code as understood by AxiDraw-style plotters running GRBL.
+//! The pen is only lifted when the next line does not start where the previous one ended. G-code's y axis
+//! points up, so by default y coordinates are measured from the bottom of the canvas rather than the top.
+//!
+//! A plotter only buffers a few commands, so given the plotter's replies with `with_replies`, the `Plotter`
+//! waits for each command to be acknowledged before sending the next, as GRBL's protocol requires. Without
+//! replies, commands are written as fast as the writer accepts them, and the writer must pace them itself.
+//!
+//! # Example
+//!
+//! ```
+//! use std::io::Cursor;
+//! use rslogo::parser::parse_content;
+//! use rslogo::plotter::{Plotter, Protocol};
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//!
+//! let mut turtle = Turtle::new(100, 100);
+//! run(&parse_content("PENDOWN FORWARD \"10 RIGHT \"10").unwrap(), &mut turtle).unwrap();
+//!
+//! let replies = Cursor::new("ok\nok\n[MSG:Pen down]\nok\nok\n");
+//! let mut plotter = Plotter::new(Vec::new(), Protocol::gcode(), 100).with_replies(replies);
+//! plotter.plot(turtle.get_display_list()).unwrap();
+//! let commands = String::from_utf8(plotter.into_inner()).unwrap();
+//!
+//! assert_eq!(commands.lines().collect::<Vec<_>>(), ["G0 X50 Y50", "M3", "G1 X50 Y60", "G1 X60 Y60"]);
+//!
+//! let mut plotter = Plotter::new(Vec::new(), Protocol::gcode(), 100).with_replies(Cursor::new("ok\nerror:2\n"));
+//! assert!(plotter.plot(turtle.get_display_list()).is_err());
+//! ```
+
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::display::DisplayList;
+
+/// The commands a `Plotter` sends. Movement commands are templates where `{x}` and `{y}` are replaced
+/// with the coordinates to move to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Protocol {
+    /// Lifts the pen.
+    pub pen_up: String,
+
+    /// Lowers the pen.
+    pub pen_down: String,
+
+    /// Moves to a point with the pen up.
+    pub travel: String,
+
+    /// Moves to a point with the pen down.
+    pub draw: String,
+
+    /// The number of plotter units per pixel of the image.
+    pub scale: f32,
+
+    /// Whether the plotter's y axis points up, unlike the image's, so y coordinates are measured from the
+    /// bottom of the canvas.
+    pub y_up: bool,
+
+    /// The reply the plotter sends once it has accepted a command. Other replies are skipped, except those
+    /// starting with `error` or `ALARM`, which GRBL sends when it rejects a command.
+    pub acknowledgement: String,
+}
+
+impl Protocol {
+    /// G-code, with `M3`/`M5` to lower and lift the pen, one unit per pixel, the y axis pointing up, and each
+    /// command acknowledged with `ok`.
+    pub fn gcode() -> Self {
+        Self {
+            pen_up: "M5".to_string(),
+            pen_down: "M3".to_string(),
+            travel: "G0 X{x} Y{y}".to_string(),
+            draw: "G1 X{x} Y{y}".to_string(),
+            scale: 1.0,
+            y_up: true,
+            acknowledgement: "ok".to_string(),
+        }
+    }
+
+    /// Parses a protocol from `key = value` lines, starting from `Protocol::gcode`. The keys are the names of
+    /// the fields of `Protocol`, and blank lines and lines starting with `#` are ignored.
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut protocol = Self::gcode();
+        for line in config.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("Expected `key = value`, found `{line}`"));
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "pen_up" => protocol.pen_up = value,
+                "pen_down" => protocol.pen_down = value,
+                "travel" => protocol.travel = value,
+                "draw" => protocol.draw = value,
+                "scale" => protocol.scale = value.parse().map_err(|_| format!("Invalid scale `{value}`"))?,
+                "y_up" => protocol.y_up = value.parse().map_err(|_| format!("Invalid y_up `{value}`"))?,
+                "acknowledgement" => protocol.acknowledgement = value,
+                key => return Err(format!("Unknown protocol key `{key}`")),
+            }
+        }
+        Ok(protocol)
+    }
+
+    fn movement(&self, template: &str, (x, y): (f32, f32), height: f32) -> String {
+        let y = if self.y_up { height - y } else { y };
+        template.replace("{x}", &(x * self.scale).to_string()).replace("{y}", &(y * self.scale).to_string())
+    }
+}
+
+/// Sends the lines of a `DisplayList` to a plotter as pen and movement commands.
+pub struct Plotter<W> {
+    writer: W,
+    replies: Option<Box<dyn BufRead>>,
+    protocol: Protocol,
+    height: f32,
+    position: Option<(f32, f32)>,
+    pen_down: bool,
+}
+
+impl<W: Write> Plotter<W> {
+    /// Creates a plotter that sends commands in `protocol` to `writer`, for drawings on a canvas `height`
+    /// pixels tall. The pen is assumed to start up.
+    pub fn new(writer: W, protocol: Protocol, height: u32) -> Self {
+        Self { writer, replies: None, protocol, height: height as f32, position: None, pen_down: false }
+    }
+
+    /// Waits for the protocol's acknowledgement on `replies` after sending each command, failing if the
+    /// plotter rejects a command or stops replying.
+    pub fn with_replies(mut self, replies: impl Read + 'static) -> Self {
+        self.replies = Some(Box::new(BufReader::new(replies)));
+        self
+    }
+
+    /// Plots every line in `display_list`.
+    pub fn plot(&mut self, display_list: &DisplayList) -> std::io::Result<()> {
+        self.plot_from(0, display_list)
+    }
+
+    /// Plots the lines from index `start` onwards, skipping those that were already plotted.
+    pub fn plot_from(&mut self, start: usize, display_list: &DisplayList) -> std::io::Result<()> {
+        let lines = display_list.lines().get(start..).unwrap_or_default();
+        let _span = tracing::info_span!("plot", lines = lines.len()).entered();
+        for line in lines {
+            let from = (line.x, line.y);
+            if self.position != Some(from) {
+                if self.pen_down {
+                    self.send(&self.protocol.pen_up.clone())?;
+                    self.pen_down = false;
+                }
+                self.send(&self.protocol.movement(&self.protocol.travel, from, self.height))?;
+            }
+            if !self.pen_down {
+                self.send(&self.protocol.pen_down.clone())?;
+                self.pen_down = true;
+            }
+            let to = line.end();
+            self.send(&self.protocol.movement(&self.protocol.draw, to, self.height))?;
+            self.position = Some(to);
+        }
+        self.writer.flush()
+    }
+
+    /// Lifts the pen if it is down, for example once the program has finished.
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if self.pen_down {
+            self.send(&self.protocol.pen_up.clone())?;
+            self.pen_down = false;
+        }
+        self.writer.flush()
+    }
+
+    /// Consumes the plotter, returning the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes `command`, then waits for it to be acknowledged if the plotter's replies are being read.
+    fn send(&mut self, command: &str) -> std::io::Result<()> {
+        writeln!(self.writer, "{command}")?;
+        let Some(replies) = &mut self.replies else {
+            return Ok(());
+        };
+        self.writer.flush()?;
+        let mut reply = String::new();
+        loop {
+            reply.clear();
+            if replies.read_line(&mut reply)? == 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "the plotter stopped replying"));
+            }
+            let reply = reply.trim();
+            if reply == self.protocol.acknowledgement {
+                return Ok(());
+            }
+            if reply.starts_with("error") || reply.starts_with("ALARM") {
+                return Err(std::io::Error::other(format!("the plotter rejected `{command}`: {reply}")));
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! # Pragma
+//!
+//! This module reads the pragmas at the top of a Logo program, which set up the canvas the program expects
+//! to be drawn on, so it does not depend on being run with the right command line arguments.
+//!
+//! Each pragma is a line starting with `;#`, and they must come before the first instruction, although
+//! blank lines and comments may be mixed in. The tokenizer skips pragma lines, so the same source can be
+//! parsed as usual. The supported pragmas are:
+//!
+//! - `;#size <width> <height>` sets the size of the canvas in pixels.
+//! - `;#background <color>` fills the canvas with a color, given as an index or a name, before drawing.
+//! - `;#origin <origin>` starts the turtle at `center`, `top-left`, `top-right`, `bottom-left`, or `bottom-right`.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::pragma::{Origin, Pragmas};
+//! use rslogo::turtle::Turtle;
+//!
+//! let source = ";#size 800 600\n;#background red\n;#origin top-left\nPENDOWN FORWARD \"10\n";
+//! let pragmas = Pragmas::parse(source).unwrap();
+//! assert_eq!(pragmas.size, Some((800, 600)));
+//! assert_eq!(pragmas.background, Some(4));
+//! assert_eq!(pragmas.origin, Some(Origin::TopLeft));
+//!
+//! let mut turtle = Turtle::new(800, 600);
+//! pragmas.apply(&mut turtle);
+//! assert_eq!((turtle.get_x(), turtle.get_y()), (0.0, 0.0));
+//!
+//! assert!(Pragmas::parse(";#size 800\n").is_err());
+//! ```
+
+use crate::turtle::{color_index, Turtle, COLOR_NAMES};
+
+/// Where the turtle starts on the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// The center of the canvas, where the turtle starts without a pragma.
+    Center,
+
+    /// The top left corner of the canvas.
+    TopLeft,
+
+    /// The top right corner of the canvas.
+    TopRight,
+
+    /// The bottom left corner of the canvas.
+    BottomLeft,
+
+    /// The bottom right corner of the canvas.
+    BottomRight,
+}
+
+impl Origin {
+    /// Gets the coordinates of the origin on a canvas of the given size.
+    pub fn position(self, width: u32, height: u32) -> (f32, f32) {
+        let (width, height) = (width as f32, height as f32);
+        match self {
+            Origin::Center => (width / 2.0, height / 2.0),
+            Origin::TopLeft => (0.0, 0.0),
+            Origin::TopRight => (width, 0.0),
+            Origin::BottomLeft => (0.0, height),
+            Origin::BottomRight => (width, height),
+        }
+    }
+}
+
+/// The start state requested by the pragmas at the top of a program. Each is `None` if it was not given.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pragmas {
+    /// The width and height of the canvas in pixels.
+    pub size: Option<(u32, u32)>,
+
+    /// The index in the `COLORS` array of the color to fill the canvas with.
+    pub background: Option<usize>,
+
+    /// Where the turtle starts.
+    pub origin: Option<Origin>,
+}
+
+impl Pragmas {
+    /// Parses the pragmas at the top of `source`, stopping at the first line that is not a pragma, a comment,
+    /// or blank. Returns an error naming the line of a pragma that is unknown or has invalid arguments.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut pragmas = Self::default();
+        for (number, line) in source.lines().enumerate().map(|(index, line)| (index + 1, line.trim())) {
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            let Some(pragma) = line.strip_prefix(";#") else {
+                break;
+            };
+            let mut words = pragma.split_whitespace();
+            let name = words.next().unwrap_or_default();
+            let args: Vec<&str> = words.collect();
+            match (name, args.as_slice()) {
+                ("size", [width, height]) => match (width.parse(), height.parse()) {
+                    (Ok(width), Ok(height)) if width > 0 && height > 0 => pragmas.size = Some((width, height)),
+                    _ => return Err(format!("Line {number}: invalid canvas size `{width} {height}`")),
+                },
+                ("background", [color]) => {
+                    let index = color.parse().ok().filter(|&index| index < COLOR_NAMES.len()).or_else(|| color_index(color));
+                    match index {
+                        Some(index) => pragmas.background = Some(index),
+                        None => return Err(format!("Line {number}: unknown color `{color}`")),
+                    }
+                },
+                ("origin", [origin]) => {
+                    pragmas.origin = Some(match *origin {
+                        "center" => Origin::Center,
+                        "top-left" => Origin::TopLeft,
+                        "top-right" => Origin::TopRight,
+                        "bottom-left" => Origin::BottomLeft,
+                        "bottom-right" => Origin::BottomRight,
+                        _ => return Err(format!("Line {number}: unknown origin `{origin}`")),
+                    });
+                },
+                ("size", _) => return Err(format!("Line {number}: expected `;#size <width> <height>`")),
+                ("background", _) => return Err(format!("Line {number}: expected `;#background <color>`")),
+                ("origin", _) => return Err(format!("Line {number}: expected `;#origin <origin>`")),
+                (name, _) => return Err(format!("Line {number}: unknown pragma `{name}`")),
+            }
+        }
+        Ok(pragmas)
+    }
+
+    /// Moves `turtle` to the requested origin. The size and background have to be applied by the caller
+    /// when it creates the turtle and the image, since a turtle's canvas cannot be resized.
+    pub fn apply(&self, turtle: &mut Turtle) {
+        if let Some(origin) = self.origin {
+            let (width, height) = turtle.get_dimensions();
+            let (x, y) = origin.position(width, height);
+            turtle.set_position(x, y);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! # Primitive
+//!
+//! This module contains the `Primitive` trait and the `Primitives` registry, which let extension crates
+//! add new commands and queries to the Logo language without modifying the parser or the AST.
+//!
+//! A registered command is parsed into a `Procedure::Call` node, and a registered query into an
+//! `Expression::Call` node. The registry is passed to `parse_content_with` so the parser knows how many
+//! arguments each primitive takes, and to the `Turtle` so the primitives can be run.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::error::RuntimeError;
+//! use rslogo::parser::parse_content_with;
+//! use rslogo::primitive::{Primitive, Primitives};
+//! use rslogo::turtle::Turtle;
+//!
+//! struct Square;
+//!
+//! impl Primitive for Square {
+//!     fn arity(&self) -> usize {
+//!         1
+//!     }
+//!
+//!     fn execute(&self, turtle: &mut Turtle, args: &[f32]) -> Result<(), RuntimeError> {
+//!         for _ in 0..4 {
+//!             turtle.forward(args[0])?;
+//!             turtle.turn(90.0);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut primitives = Primitives::new();
+//! primitives.register("SQUARE", Square);
+//!
+//! let asts = parse_content_with("SQUARE \"10", &primitives).unwrap();
+//!
+//! let mut turtle = Turtle::new(100, 100);
+//! turtle.set_primitives(primitives);
+//! for ast in asts {
+//!     ast.execute(&mut turtle).unwrap();
+//! }
+//!
+//! assert_eq!(turtle.get_heading(), 360.0);
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::RuntimeError;
+use crate::turtle::Turtle;
+
+/// A command or query provided by code outside of this crate.
+///
+/// Commands implement `execute`, queries return `true` from `is_query` and implement `evaluate`.
+pub trait Primitive: Send + Sync {
+    /// The number of arguments the primitive takes.
+    fn arity(&self) -> usize;
+
+    /// Whether the primitive is a query, which is used as an expression, rather than a command.
+    fn is_query(&self) -> bool {
+        false
+    }
+
+    /// Runs the primitive as a command with the evaluated arguments.
+    fn execute(&self, _turtle: &mut Turtle, _args: &[f32]) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Evaluates the primitive as a query with the evaluated arguments.
+    fn evaluate(&self, _turtle: &Turtle, _args: &[f32]) -> Option<f32> {
+        None
+    }
+}
+
+/// A registry of primitives, keyed by the keyword used to invoke them.
+///
+/// Cloning a `Primitives` is cheap, as the primitives themselves are shared.
+#[derive(Clone, Default)]
+pub struct Primitives {
+    primitives: HashMap<String, Arc<dyn Primitive>>,
+}
+
+impl Primitives {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `primitive` under the keyword `name`, replacing any primitive already registered with that name.
+    pub fn register(&mut self, name: &str, primitive: impl Primitive + 'static) {
+        self.primitives.insert(name.to_string(), Arc::new(primitive));
+    }
+
+    /// Gets the primitive registered under `name`.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Primitive>> {
+        self.primitives.get(name).cloned()
+    }
+
+    /// Gets the names of the registered primitives, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.primitives.keys().map(String::as_str)
+    }
+
+    /// Gets the arity of the command registered under `name`, if there is one.
+    pub fn command_arity(&self, name: &str) -> Option<usize> {
+        self.primitives.get(name)
+            .filter(|primitive| !primitive.is_query())
+            .map(|primitive| primitive.arity())
+    }
+
+    /// Gets the arity of the query registered under `name`, if there is one.
+    pub fn query_arity(&self, name: &str) -> Option<usize> {
+        self.primitives.get(name)
+            .filter(|primitive| primitive.is_query())
+            .map(|primitive| primitive.arity())
+    }
+}
@@ -0,0 +1,108 @@
+//! # Program
+//!
+//! This module contains the `Program` type, which `parse_content` returns, so the common case of running a
+//! Logo program does not need the tokenizer, parser, and turtle to be wired together by hand.
+//!
+//! A program is parsed once and can then be run any number of times, on turtles the caller sets up with
+//! `run`, or on a fresh turtle rendered straight to an image with `run_to_image`. It dereferences to its
+//! instructions, so it can be passed anywhere a slice of `ASTNode`s is expected.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::parser::parse_content;
+//! use rslogo::turtle::Turtle;
+//!
+//! let program = parse_content("PENDOWN REPEAT \"4 [ FORWARD \"10 TURN \"90 ]").unwrap();
+//! assert_eq!(program.instructions().len(), 2);
+//!
+//! for width in [100, 200] {
+//!     let mut turtle = Turtle::new(width, 100);
+//!     program.run(&mut turtle).unwrap();
+//!     assert_eq!(turtle.get_display_list().lines().len(), 4);
+//! }
+//!
+//! let image = program.run_to_image(100, 100).unwrap();
+//! assert_eq!(image.get_dimensions(), (100, 100));
+//! ```
+
+use std::ops::Deref;
+
+use unsvg::Image;
+
+use crate::ast::ASTNode;
+use crate::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::runner;
+use crate::turtle::Turtle;
+
+/// A parsed Logo program, ready to be run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Program {
+    instructions: Vec<ASTNode>,
+}
+
+impl Program {
+    /// Creates a program that runs `instructions` in order.
+    pub fn new(instructions: Vec<ASTNode>) -> Self {
+        Self { instructions }
+    }
+
+    /// Gets the top-level instructions of the program.
+    pub fn instructions(&self) -> &[ASTNode] {
+        &self.instructions
+    }
+
+    /// Takes the top-level instructions of the program, such as to pass them to an optimizer.
+    pub fn into_instructions(self) -> Vec<ASTNode> {
+        self.instructions
+    }
+
+    /// Runs the program on `turtle`, carrying on from whatever state it is in.
+    pub fn run(&self, turtle: &mut Turtle) -> Result<(), RuntimeError> {
+        runner::run(&self.instructions, turtle)
+    }
+
+    /// Runs the program on a new turtle with a canvas of the given size, returning an image of what it drew.
+    pub fn run_to_image(&self, width: u32, height: u32) -> Result<Image, RuntimeError> {
+        Interpreter::new(width, height).execute(&self.instructions)
+    }
+}
+
+impl Deref for Program {
+    type Target = [ASTNode];
+
+    fn deref(&self) -> &[ASTNode] {
+        &self.instructions
+    }
+}
+
+impl From<Vec<ASTNode>> for Program {
+    fn from(instructions: Vec<ASTNode>) -> Self {
+        Self::new(instructions)
+    }
+}
+
+impl From<Program> for Vec<ASTNode> {
+    fn from(program: Program) -> Self {
+        program.instructions
+    }
+}
+
+impl IntoIterator for Program {
+    type Item = ASTNode;
+    type IntoIter = std::vec::IntoIter<ASTNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instructions.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Program {
+    type Item = &'a ASTNode;
+    type IntoIter = std::slice::Iter<'a, ASTNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instructions.iter()
+    }
+}
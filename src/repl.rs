@@ -0,0 +1,258 @@
+//! # REPL
+//!
+//! This module contains the `Repl` struct, which runs Logo typed in one line at a time on a `Session`, and
+//! understands meta-commands starting with `:` that manage the session itself:
+//!
+//! - `:load <file>` runs a Logo file as if it had been typed in.
+//! - `:save <file>` saves everything run so far as a Logo file, which `:load` can run again.
+//! - `:reset` starts again with a fresh turtle.
+//! - `:vars` lists the variables and their values.
+//! - `:undo` takes back the last input that ran, by running everything before it again on a fresh turtle.
+//! - `:export <file>` saves the drawing as an `svg` or `png` image.
+//!
+//! Input that ends part way through an instruction, such as the first line of a `TO` definition, is kept
+//! until the rest of it is typed in. Procedures defined with `TO`, whether typed in or loaded, can be called
+//! by any later input.
+//!
+//! With the `readline` feature, `Helper` adds keyword completion to a `rustyline` editor, and keeps editing
+//! a block such as `IF` or `WHILE` over several lines until its brackets are closed.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::repl::Repl;
+//!
+//! let mut repl = Repl::new(100, 100);
+//! repl.eval("MAKE \"size \"20").unwrap();
+//! repl.eval("PENDOWN FORWARD :size").unwrap();
+//! assert_eq!(repl.eval(":vars").unwrap(), "size = \"20\n");
+//!
+//! repl.eval("REPEAT \"4 [").unwrap();
+//! assert!(repl.is_pending());
+//! repl.eval("FORWARD \"1 ]").unwrap();
+//! assert_eq!(repl.get_session().get_turtle().get_display_list().lines().len(), 5);
+//!
+//! repl.eval(":undo").unwrap();
+//! assert_eq!(repl.get_session().get_turtle().get_display_list().lines().len(), 1);
+//! assert!(repl.eval(":frobnicate").is_err());
+//!
+//! repl.eval("TO side :n FORWARD :n END").unwrap();
+//! repl.eval("PENDOWN side \"10").unwrap();
+//! repl.eval("TURN \"90").unwrap();
+//! repl.eval(":undo").unwrap();
+//! assert_eq!(repl.get_session().get_turtle().get_display_list().lines().len(), 2);
+//! ```
+
+use std::fmt::Write;
+
+use chumsky::error::Simple;
+use unsvg::Image;
+
+use crate::ast::ASTNode;
+use crate::diagnostics::Diagnostic;
+use crate::parser::parse_tokens_with_definitions;
+use crate::primitive::Primitives;
+use crate::session::Session;
+use crate::tokenizer::{tokenize, Token};
+#[cfg(feature = "readline")]
+use crate::keywords::keywords;
+
+/// An interactive session that runs Logo and meta-commands one line at a time.
+pub struct Repl {
+    session: Session,
+    width: u32,
+    height: u32,
+    /// The source of each input that ran successfully, in order.
+    history: Vec<String>,
+    /// The lines of an instruction that has not been finished yet.
+    pending: String,
+}
+
+impl Repl {
+    /// Creates a new REPL with a fresh turtle for a canvas of the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { session: Session::new(width, height), width, height, history: Vec::new(), pending: String::new() }
+    }
+
+    /// Runs a line of input, returning what to show the user, or a description of what went wrong.
+    pub fn eval(&mut self, line: &str) -> Result<String, String> {
+        let trimmed = line.trim();
+        if self.pending.is_empty() {
+            if let Some(command) = trimmed.strip_prefix(':') {
+                let (name, argument) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+                return self.meta_command(name, argument.trim());
+            }
+            if trimmed.is_empty() {
+                return Ok(String::new());
+            }
+        }
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        let source = std::mem::take(&mut self.pending);
+        match self.run(&source) {
+            Err(Incomplete) => {
+                self.pending = source;
+                Ok(String::new())
+            },
+            Ok(result) => result,
+        }
+    }
+
+    /// Whether the last line of input ended part way through an instruction, which the next line continues.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Gets the session the input is run on.
+    pub fn get_session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Gets the session the input is run on mutably, for example to change the turtle's settings.
+    pub fn get_session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Parses and runs `source`, remembering it if it runs successfully. Returns `Err(Incomplete)` if the
+    /// source ends part way through an instruction.
+    fn run(&mut self, source: &str) -> Result<Result<String, String>, Incomplete> {
+        let instructions = match self.parse(source) {
+            Ok(instructions) => instructions,
+            Err(errors) if errors.iter().any(|error| error.found().is_none()) => return Err(Incomplete),
+            Err(errors) => {
+                let report = errors.iter().map(|error| Diagnostic::from_parse_error(error).render(source)).collect();
+                return Ok(Err(report));
+            },
+        };
+        if let Err(e) = self.session.run(&instructions) {
+            let span = self.session.get_turtle().get_error_span();
+            return Ok(Err(Diagnostic::from_runtime_error(&e, span).render(source)));
+        }
+        self.history.push(source.trim_end().to_string());
+        Ok(Ok(String::new()))
+    }
+
+    /// Parses `source`, recognising calls to the procedures defined by the inputs that ran before it.
+    fn parse(&self, source: &str) -> Result<Vec<ASTNode>, Vec<Simple<Token>>> {
+        let definitions = self.session.get_turtle().get_definition_arities();
+        parse_tokens_with_definitions(tokenize(source).collect(), source.len(), &Primitives::new(), &definitions)
+    }
+
+    fn meta_command(&mut self, name: &str, argument: &str) -> Result<String, String> {
+        match (name, argument) {
+            ("load", "") | ("save", "") | ("export", "") => Err(format!(":{name} needs a file name")),
+            ("load", path) => {
+                let source = std::fs::read_to_string(path).map_err(|e| format!("Error reading {path}: {e}"))?;
+                match self.run(&source) {
+                    Ok(result) => result,
+                    Err(Incomplete) => Err(format!("{path} ends part way through an instruction")),
+                }
+            },
+            ("save", path) => {
+                let mut source = self.history.join("\n");
+                source.push('\n');
+                std::fs::write(path, source).map_err(|e| format!("Error saving {path}: {e}"))?;
+                Ok(format!("Saved {} inputs to {path}\n", self.history.len()))
+            },
+            ("reset", "") => {
+                self.session = Session::new(self.width, self.height);
+                self.history.clear();
+                Ok(String::new())
+            },
+            ("vars", "") => {
+                let mut output = String::new();
+                for (name, value) in self.session.get_turtle().get_variables() {
+                    let _ = writeln!(output, "{name} = {value}");
+                }
+                Ok(output)
+            },
+            ("undo", "") => {
+                if self.history.pop().is_none() {
+                    return Err("Nothing to undo".to_string());
+                }
+                self.session = Session::new(self.width, self.height);
+                for source in &self.history {
+                    // Each input ran before, so it parses again, but it may not run the same if it used RANDOM.
+                    let instructions = self.parse(source).map_err(|_| format!("Could not parse {source} again"))?;
+                    self.session.run(&instructions).map_err(|e| format!("Could not run {source} again: {e}"))?;
+                }
+                Ok(String::new())
+            },
+            ("export", path) => {
+                let mut image = Image::new(self.width, self.height);
+                let display_list = self.session.get_turtle().get_display_list();
+                let saved = match std::path::Path::new(path).extension().and_then(|extension| extension.to_str()) {
+                    Some("svg") => display_list.save_svg(&mut image, std::path::Path::new(path)),
+                    Some("png") => display_list.render(&mut image)
+                        .and_then(|()| image.save_png(path))
+                        .map_err(|e| e.to_string()),
+                    _ => return Err("Only svg and png images can be exported".to_string()),
+                };
+                saved.map_err(|e| format!("Error saving {path}: {e}"))?;
+                Ok(format!("Exported the drawing to {path}\n"))
+            },
+            ("reset" | "vars" | "undo", _) => Err(format!(":{name} does not take an argument")),
+            _ => Err(format!("Unknown command :{name}, expected one of :load, :save, :reset, :vars, :undo, :export")),
+        }
+    }
+}
+
+/// The input ended part way through an instruction.
+struct Incomplete;
+
+/// Completes keywords and waits for open brackets to be closed in a `rustyline` editor.
+#[cfg(feature = "readline")]
+pub struct Helper {
+    names: Vec<String>,
+}
+
+#[cfg(feature = "readline")]
+impl Helper {
+    /// Creates a helper that completes the built in keywords and the primitives registered in `primitives`.
+    pub fn new(primitives: &Primitives) -> Self {
+        Self { names: keywords(primitives).into_iter().map(|keyword| keyword.name).collect() }
+    }
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::completion::Completer for Helper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(|c: char| c.is_whitespace() || "[]()".contains(c)).map_or(0, |i| i + 1);
+        let prefix = line[start..pos].to_uppercase();
+        // Variables and values are names the user chose, not keywords.
+        if prefix.is_empty() || prefix.starts_with([':', '"']) {
+            return Ok((start, Vec::new()));
+        }
+        let candidates = self.names.iter().filter(|name| name.to_uppercase().starts_with(&prefix)).cloned().collect();
+        Ok((start, candidates))
+    }
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::validate::Validator for Helper {
+    fn validate(&self, ctx: &mut rustyline::validate::ValidationContext) -> rustyline::Result<rustyline::validate::ValidationResult> {
+        let depth = tokenize(ctx.input()).fold(0i32, |depth, (token, _)| match token {
+            Token::LParen => depth + 1,
+            Token::RParen => depth - 1,
+            _ => depth,
+        });
+        Ok(if depth > 0 {
+            rustyline::validate::ValidationResult::Incomplete
+        } else {
+            rustyline::validate::ValidationResult::Valid(None)
+        })
+    }
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::hint::Hinter for Helper {
+    type Hint = String;
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::highlight::Highlighter for Helper {}
+
+#[cfg(feature = "readline")]
+impl rustyline::Helper for Helper {}
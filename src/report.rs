@@ -0,0 +1,101 @@
+//! # Report
+//!
+//! This module contains the `ResourceReport` struct, which summarizes the resources a run of a program used,
+//! so a slow or heavy program can be traced to the stage responsible for it.
+//!
+//! The counts come from the `Turtle` the program ran on. The time spent in each phase is measured by the
+//! caller, since only it knows where each phase starts and ends.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Instant;
+//! use rslogo::parser::parse_tokens;
+//! use rslogo::primitive::Primitives;
+//! use rslogo::report::{PhaseTimes, ResourceReport};
+//! use rslogo::runner::run;
+//! use rslogo::tokenizer::tokenize;
+//! use rslogo::turtle::Turtle;
+//!
+//! let source = "MAKE \"i \"0 PENDOWN WHILE LT :i \"4 [ FORWARD \"10 TURN \"90 ADDASSIGN \"i \"1 ]";
+//! let mut phases = PhaseTimes::default();
+//!
+//! let start = Instant::now();
+//! let tokens = tokenize(source).collect();
+//! phases.tokenize = start.elapsed();
+//!
+//! let start = Instant::now();
+//! let asts = parse_tokens(tokens, source.len(), &Primitives::new()).unwrap();
+//! phases.parse = start.elapsed();
+//!
+//! let mut turtle = Turtle::new(100, 100);
+//! let start = Instant::now();
+//! run(&asts, &mut turtle).unwrap();
+//! phases.execute = start.elapsed();
+//!
+//! let report = ResourceReport::new(&turtle, phases);
+//! assert_eq!(report.instructions, 15);
+//! assert_eq!(report.peak_variables, 1);
+//! assert_eq!(report.segments, 4);
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::turtle::Turtle;
+
+/// The time spent in each phase of running a program.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhaseTimes {
+    /// The time spent splitting the source into tokens.
+    pub tokenize: Duration,
+
+    /// The time spent parsing the tokens into instructions.
+    pub parse: Duration,
+
+    /// The time spent executing the instructions.
+    pub execute: Duration,
+
+    /// The time spent drawing the lines onto the image.
+    pub render: Duration,
+}
+
+/// The resources used by one run of a program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceReport {
+    /// The number of instructions executed, including those inside blocks.
+    pub instructions: u64,
+
+    /// The largest number of variables that were defined at once.
+    pub peak_variables: usize,
+
+    /// The number of lines drawn.
+    pub segments: usize,
+
+    /// The time spent in each phase.
+    pub phases: PhaseTimes,
+}
+
+impl ResourceReport {
+    /// Summarizes the resources used by the program that ran on `turtle`, which took `phases`.
+    pub fn new(turtle: &Turtle, phases: PhaseTimes) -> Self {
+        Self {
+            instructions: turtle.get_steps(),
+            peak_variables: turtle.get_peak_variables(),
+            segments: turtle.get_display_list().lines().len(),
+            phases,
+        }
+    }
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions executed: {}", self.instructions)?;
+        writeln!(f, "Peak variables: {}", self.peak_variables)?;
+        writeln!(f, "Segments drawn: {}", self.segments)?;
+        writeln!(f, "Tokenize: {:?}", self.phases.tokenize)?;
+        writeln!(f, "Parse: {:?}", self.phases.parse)?;
+        writeln!(f, "Execute: {:?}", self.phases.execute)?;
+        write!(f, "Render: {:?}", self.phases.render)
+    }
+}
@@ -0,0 +1,296 @@
+//! # Runner
+//!
+//! This module contains functions for running a whole Logo program on a `Turtle`.
+//!
+//! `run` executes the program to completion on the current thread. `run_async` does the same, but
+//! yields to the async executor every `yield_every` instructions, so web servers and GUI event loops
+//! can run long programs without blocking a thread or spawning one per program.
+//!
+//! `interpret` parses, executes, and draws a program in one call, for quick embedding and tests.
+//!
+//! `run_onto` executes a program and draws it onto an existing image, moved and scaled by a `Transform`,
+//! so several programs can be composited into one poster or contact sheet.
+//!
+//! # Example
+//!
+//! ```
+//! use std::future::Future;
+//! use std::pin::pin;
+//! use std::task::{Context, Poll, Waker};
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run_async;
+//! use rslogo::turtle::Turtle;
+//!
+//! let asts = parse_content("MAKE \"i \"0 WHILE LT :i \"10 [ ADDASSIGN \"i \"1 ]").unwrap();
+//! let mut turtle = Turtle::new(100, 100);
+//!
+//! let mut future = pin!(run_async(&asts, &mut turtle, 5));
+//! let mut context = Context::from_waker(Waker::noop());
+//! let mut polls = 1;
+//! while future.as_mut().poll(&mut context).is_pending() {
+//!     polls += 1;
+//! }
+//!
+//! assert!(polls > 1);
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tracing::Instrument;
+use unsvg::Image;
+
+use crate::ast::{catch, counter, eval_condition, ASTNode, ControlFlow};
+use crate::display::Transform;
+use crate::error::{Error, RuntimeError};
+use crate::interpreter::Interpreter;
+use crate::turtle::Turtle;
+
+/// Executes each instruction in `instructions` in order on `turtle`.
+pub fn run(instructions: &[ASTNode], turtle: &mut Turtle) -> Result<(), RuntimeError> {
+    let _span = tracing::info_span!("execute", instructions = instructions.len()).entered();
+    turtle.take_error_span();
+    instructions.iter()
+        .try_for_each(|instruction| instruction.execute(turtle))
+        .inspect_err(|e| tracing::debug!(error = %e, "execution failed"))
+}
+
+/// Parses `source` and runs it on a new turtle with a canvas of the given size, returning an image of what it
+/// drew. This is the quickest way to draw a program when nothing about the turtle needs to be set up.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::{interpret, Error};
+/// 
+/// let image = interpret("PENDOWN REPEAT \"4 [ FORWARD \"10 TURN \"90 ]", 100, 100).unwrap();
+/// assert_eq!(image.get_dimensions(), (100, 100));
+/// 
+/// assert!(matches!(interpret("FORWARD", 100, 100), Err(Error::Parse(_))));
+/// assert!(matches!(interpret("FORWARD \"10 !", 100, 100), Err(Error::Tokenize(_))));
+/// assert!(matches!(interpret("FORWARD :x", 100, 100), Err(Error::Runtime(_))));
+/// ```
+pub fn interpret(source: &str, width: u32, height: u32) -> Result<Image, Error> {
+    Interpreter::new(width, height).interpret(source)
+}
+
+/// Executes `instructions` on a new turtle with a canvas the size of `image`, then draws the lines it drew
+/// onto `image`, moved and scaled by `transform`. Nothing is drawn if the program fails.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::display::Transform;
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::run_onto;
+/// use unsvg::Image;
+/// 
+/// let square = parse_content("PENDOWN REPEAT \"4 [ FORWARD \"40 RIGHT \"90 ]").unwrap();
+/// let mut sheet = Image::new(200, 200);
+/// for (x_offset, y_offset) in [(0.0, 0.0), (100.0, 0.0), (0.0, 100.0), (100.0, 100.0)] {
+///     run_onto(&mut sheet, &square, Transform { x_offset, y_offset, scale: 0.5 }).unwrap();
+/// }
+/// ```
+pub fn run_onto(image: &mut Image, instructions: &[ASTNode], transform: Transform) -> Result<(), RuntimeError> {
+    let (width, height) = image.get_dimensions();
+    let mut turtle = Turtle::new(width, height);
+    run(instructions, &mut turtle)?;
+    turtle.get_display_list().render_with(image, transform).map_err(RuntimeError::Render)
+}
+
+/// Executes each instruction in `instructions` in order on `turtle`, yielding to the async executor
+/// after every `yield_every` instructions, including those inside `IF`, `WHILE`, `REPEAT`, and `FOR` blocks.
+/// 
+/// Errors are handled as `run` handles them, so a turtle that continues on errors skips an `IF` or loop whose
+/// header fails, rather than stopping.
+/// 
+/// # Example
+/// 
+/// ```
+/// use std::future::Future;
+/// use std::pin::pin;
+/// use std::task::{Context, Poll, Waker};
+/// use rslogo::parser::parse_content;
+/// use rslogo::runner::{run, run_async};
+/// use rslogo::turtle::Turtle;
+/// 
+/// let asts = parse_content("PENDOWN IF EQ :nope \"1 [ FORWARD \"5 ] REPEAT :nope [ FORWARD \"5 ] FORWARD \"10").unwrap();
+/// 
+/// let mut turtle = Turtle::new(100, 100);
+/// turtle.set_continue_on_error(true);
+/// run(&asts, &mut turtle).unwrap();
+/// assert_eq!(turtle.get_display_list().lines().len(), 1);
+/// assert_eq!(turtle.get_diagnostics().len(), 2);
+/// 
+/// let mut turtle = Turtle::new(100, 100);
+/// turtle.set_continue_on_error(true);
+/// let result = {
+///     let mut future = pin!(run_async(&asts, &mut turtle, 1));
+///     loop {
+///         if let Poll::Ready(result) = future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+///             break result;
+///         }
+///     }
+/// };
+/// result.unwrap();
+/// assert_eq!(turtle.get_display_list().lines().len(), 1);
+/// assert_eq!(turtle.get_diagnostics().len(), 2);
+/// ```
+pub async fn run_async(instructions: &[ASTNode], turtle: &mut Turtle, yield_every: u64) -> Result<(), RuntimeError> {
+    let mut yielder = Yielder { every: yield_every.max(1), since_yield: 0 };
+    turtle.take_error_span();
+    run_block_async(instructions, turtle, &mut yielder)
+        .instrument(tracing::info_span!("execute", instructions = instructions.len()))
+        .await
+        .inspect_err(|e| tracing::debug!(error = %e, "execution failed"))
+}
+
+/// Counts the instructions executed since the runner last yielded.
+struct Yielder {
+    every: u64,
+    since_yield: u64,
+}
+
+impl Yielder {
+    async fn tick(&mut self) {
+        self.since_yield += 1;
+        if self.since_yield >= self.every {
+            self.since_yield = 0;
+            YieldNow(false).await;
+        }
+    }
+}
+
+/// A future that returns `Pending` once, letting the executor run other tasks before resuming.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+async fn run_block_async(block: &[ASTNode], turtle: &mut Turtle, yielder: &mut Yielder) -> Result<(), RuntimeError> {
+    for instruction in block {
+        execute_async(instruction, turtle, yielder).await?;
+    }
+    Ok(())
+}
+
+/// Executes a single instruction, recursing into the blocks of `IF` and of loops so they can yield too.
+fn execute_async<'a>(
+    instruction: &'a ASTNode,
+    turtle: &'a mut Turtle,
+    yielder: &'a mut Yielder,
+) -> Pin<Box<dyn Future<Output = Result<(), RuntimeError>> + 'a>> {
+    Box::pin(async move {
+        let result: Result<(), RuntimeError> = async {
+            match instruction {
+                ASTNode::ControlFlow(ControlFlow::If { condition, block }, _) => {
+                    turtle.step()?;
+                    if eval_condition(condition, turtle)? {
+                        nested_async(block, turtle, yielder).await?;
+                    }
+                },
+                ASTNode::ControlFlow(ControlFlow::While { condition, block }, _) => {
+                    turtle.step()?;
+                    let mut iterations = 0;
+                    while eval_condition(condition, turtle)? {
+                        iterations += 1;
+                        turtle.loop_iteration("WHILE", iterations)?;
+                        nested_async(block, turtle, yielder).await?;
+                        yielder.tick().await;
+                    }
+                },
+                ASTNode::ControlFlow(
+                    flow @ (ControlFlow::DoWhile { block, condition } | ControlFlow::Until { block, condition }),
+                    _,
+                ) => {
+                    turtle.step()?;
+                    let until = matches!(flow, ControlFlow::Until { .. });
+                    let keyword = if until { "DO.UNTIL" } else { "DO.WHILE" };
+                    let mut iterations = 0;
+                    loop {
+                        iterations += 1;
+                        turtle.loop_iteration(keyword, iterations)?;
+                        nested_async(block, turtle, yielder).await?;
+                        yielder.tick().await;
+                        if eval_condition(condition, turtle)? == until {
+                            break;
+                        }
+                    }
+                },
+                ASTNode::ControlFlow(ControlFlow::Repeat { count, block }, _) => {
+                    turtle.step()?;
+                    let count = count.require_float(turtle)?;
+                    let outer = turtle.set_repcount(None);
+                    let mut result = Ok(());
+                    for repcount in 1..=count as usize {
+                        turtle.set_repcount(Some(repcount));
+                        result = nested_async(block, turtle, yielder).await;
+                        if result.is_err() {
+                            break;
+                        }
+                        yielder.tick().await;
+                    }
+                    turtle.set_repcount(outer);
+                    result?;
+                },
+                ASTNode::ControlFlow(
+                    flow @ (ControlFlow::For { block, .. } | ControlFlow::DoTimes { block, .. }),
+                    _,
+                ) => {
+                    turtle.step()?;
+                    let (name, values) = counter(flow, turtle)?;
+                    turtle.push_scope(false);
+                    let mut result = turtle.add_local_variable(&name);
+                    for value in values {
+                        if result.is_err() {
+                            break;
+                        }
+                        result = turtle.add_variable(&name, value);
+                        if result.is_ok() {
+                            result = nested_async(block, turtle, yielder).await;
+                        }
+                        yielder.tick().await;
+                    }
+                    turtle.pop_scope();
+                    result?;
+                },
+                ASTNode::ControlFlow(ControlFlow::Catch { tag, block }, _) => {
+                    turtle.step()?;
+                    let tag = tag.require_word(turtle)?;
+                    let result = nested_async(block, turtle, yielder).await;
+                    catch(&tag, result, turtle)?;
+                },
+                _ => instruction.execute(turtle)?,
+            }
+            Ok(())
+        }.await;
+        if result.is_err() {
+            turtle.locate_error(instruction.span());
+        }
+        instruction.skip_recoverable(result, turtle)?;
+        yielder.tick().await;
+        Ok(())
+    })
+}
+
+/// Executes `block` one level of nesting deeper than the caller, in a new scope for the local variables it declares.
+async fn nested_async(block: &[ASTNode], turtle: &mut Turtle, yielder: &mut Yielder) -> Result<(), RuntimeError> {
+    turtle.enter_block()?;
+    turtle.push_scope(false);
+    let result = run_block_async(block, turtle, yielder).await;
+    turtle.pop_scope();
+    turtle.exit_block();
+    result
+}
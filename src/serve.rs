@@ -0,0 +1,185 @@
+//! # Serve
+//!
+//! This module contains a small HTTP rendering service, so a school web portal can render Logo programs on
+//! the server. It is only compiled with the `serve` feature, and is started with `rslogo serve`.
+//!
+//! The service accepts `POST /render` with the Logo source as the request body. The query string may set
+//! `width` and `height` (500 by default) and `format`, which is `svg` (the default) or `png`. Every request
+//! runs on a fresh turtle with the service's `Limits` applied, so one program cannot starve the others.
+//! Programs that nest more than `MAX_NESTING_DEPTH` levels deep are rejected before they are parsed, as
+//! parsing and running them could overflow the stack and take the whole service down.
+//!
+//! The response is always JSON. `image` holds the SVG source, or the PNG base64 encoded, and is `null` if the
+//! program could not be parsed or stopped with an error; `diagnostics` lists every problem found:
+//!
+//! ```text
+//! $ curl -d 'FORWARD' 'localhost:8080/render?format=png'
+//! {"diagnostics":[{"end":7,"message":"Unexpected end of input","recoverable":false,"start":7}],"image":null}
+//! ```
+
+use std::io::Read;
+use std::num::NonZeroUsize;
+
+use base64::Engine;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::input::NoInput;
+use crate::json::{parse_diagnostic, request_diagnostic, runtime_diagnostic};
+use crate::limits::Limits;
+use crate::parser::{nesting_depth, parse_tokens};
+use crate::primitive::Primitives;
+use crate::runner::run;
+use crate::tokenizer::tokenize;
+use crate::turtle::Turtle;
+
+/// The largest request body, in bytes, that will be accepted.
+const MAX_SOURCE_LENGTH: usize = 1 << 20;
+
+/// The largest width or height, in pixels, that may be requested.
+const MAX_CANVAS_SIZE: u32 = 4096;
+
+/// The deepest that the expressions and blocks of a program, as measured by `nesting_depth`, may nest.
+const MAX_NESTING_DEPTH: usize = 256;
+
+/// The stack size of each worker, enough to parse and run a program nested `MAX_NESTING_DEPTH` levels deep
+/// inside each of its procedure calls, even in a debug build.
+const WORKER_STACK_SIZE: usize = 64 << 20;
+
+/// Listens on `address` and renders the programs posted to it, each with `limits` applied.
+///
+/// Requests are handled by a fixed pool of `workers` threads, so at most that many programs run at once and
+/// the rest wait in the server's queue. This only returns if the address cannot be bound or no worker could
+/// be started.
+pub fn serve(address: &str, limits: Limits, workers: NonZeroUsize) -> std::io::Result<()> {
+    let server = Server::http(address).map_err(std::io::Error::other)?;
+    tracing::info!(address, workers, "serving");
+    std::thread::scope(|scope| {
+        let mut result = Ok(());
+        for _ in 0..workers.get() {
+            let worker = std::thread::Builder::new().stack_size(WORKER_STACK_SIZE).spawn_scoped(scope, || {
+                for request in server.incoming_requests() {
+                    if let Err(e) = handle(request, limits) {
+                        tracing::warn!(error = %e, "failed to respond");
+                    }
+                }
+            });
+            if let Err(e) = worker {
+                tracing::warn!(error = %e, "failed to start a worker");
+                result = Err(e);
+            }
+        }
+        result
+    })
+}
+
+fn handle(mut request: Request, limits: Limits) -> std::io::Result<()> {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    if path != "/render" {
+        return respond(request, 404, error_body("Not found"));
+    }
+    if *request.method() != Method::Post {
+        return respond(request, 405, error_body("Only POST is supported"));
+    }
+
+    let options = match RenderOptions::from_query(query) {
+        Ok(options) => options,
+        Err(message) => return respond(request, 400, error_body(&message)),
+    };
+
+    let mut source = String::new();
+    let read = request.as_reader().take(MAX_SOURCE_LENGTH as u64 + 1).read_to_string(&mut source);
+    if read.is_err() {
+        return respond(request, 400, error_body("The program must be UTF-8 text"));
+    }
+    if source.len() > MAX_SOURCE_LENGTH {
+        return respond(request, 413, error_body("The program is too long"));
+    }
+
+    let (status, body) = render(&source, &options, limits);
+    respond(request, status, body)
+}
+
+/// The image requested in the query string of a request.
+struct RenderOptions {
+    width: u32,
+    height: u32,
+    png: bool,
+}
+
+impl RenderOptions {
+    fn from_query(query: &str) -> Result<Self, String> {
+        let mut options = RenderOptions { width: 500, height: 500, png: false };
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "width" => options.width = parse_size(key, value)?,
+                "height" => options.height = parse_size(key, value)?,
+                "format" => match value {
+                    "svg" => options.png = false,
+                    "png" => options.png = true,
+                    _ => return Err(format!("Unsupported format {value}")),
+                },
+                _ => return Err(format!("Unknown parameter {key}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_size(key: &str, value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(size) if (1..=MAX_CANVAS_SIZE).contains(&size) => Ok(size),
+        _ => Err(format!("{key} must be between 1 and {MAX_CANVAS_SIZE}")),
+    }
+}
+
+/// Runs `source`, returning the status code and JSON body to respond with.
+fn render(source: &str, options: &RenderOptions, limits: Limits) -> (u16, Value) {
+    let tokens: Vec<_> = tokenize(source).collect();
+    if nesting_depth(&tokens) > MAX_NESTING_DEPTH {
+        return (422, error_body(&format!("The program nests more than {MAX_NESTING_DEPTH} levels deep")));
+    }
+    let instructions = match parse_tokens(tokens, source.len(), &Primitives::new()) {
+        Ok(instructions) => instructions,
+        Err(errors) => {
+            let diagnostics: Vec<Value> = errors.iter().map(parse_diagnostic).collect();
+            return (422, json!({ "image": null, "diagnostics": diagnostics }));
+        }
+    };
+
+    let mut turtle = Turtle::with_limits(options.width, options.height, limits);
+    turtle.set_input_source(Box::new(NoInput));
+    let result = run(&instructions, &mut turtle);
+    let mut diagnostics: Vec<Value> = turtle.get_diagnostics()
+        .iter()
+        .map(|(error, span)| runtime_diagnostic(error, Some(span)))
+        .collect();
+    if let Err(e) = result {
+        diagnostics.push(runtime_diagnostic(&e, turtle.get_error_span()));
+        return (422, json!({ "image": null, "diagnostics": diagnostics }));
+    }
+
+    let display_list = turtle.get_display_list();
+    let image = if options.png {
+        display_list.to_png(options.width, options.height)
+            .map(|png| base64::engine::general_purpose::STANDARD.encode(png))
+    } else {
+        display_list.to_svg(options.width, options.height)
+    };
+    match image {
+        Ok(image) => (200, json!({ "image": image, "diagnostics": diagnostics })),
+        Err(e) => (500, error_body(&e)),
+    }
+}
+
+fn error_body(message: &str) -> Value {
+    json!({ "image": null, "diagnostics": [request_diagnostic(message)] })
+}
+
+fn respond(request: Request, status: u16, body: Value) -> std::io::Result<()> {
+    let content_type = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    let response = Response::from_string(body.to_string()).with_status_code(status).with_header(content_type);
+    request.respond(response)
+}
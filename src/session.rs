@@ -0,0 +1,98 @@
+//! # Session
+//!
+//! This module contains the `Session` struct, which keeps a `Turtle` alive across several pieces of a
+//! program, as in a REPL.
+//!
+//! As statements are appended to the session, the turtle keeps adding to the same display list. Rendering
+//! the session onto the image it was last rendered onto only draws the lines added since then, so previews
+//! stay instant no matter how long the session has been running. If lines were taken away instead, as by
+//! `RESTOREIMAGE`, the image is cleared and every line drawn again.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::parser::parse_content;
+//! use rslogo::session::Session;
+//! use unsvg::Image;
+//!
+//! let mut session = Session::new(100, 100);
+//! let mut image = Image::new(100, 100);
+//!
+//! session.run(&parse_content("PENDOWN FORWARD \"10").unwrap()).unwrap();
+//! assert_eq!(session.render(&mut image).unwrap(), 1);
+//!
+//! session.run(&parse_content("TURN \"90 FORWARD \"10").unwrap()).unwrap();
+//! assert_eq!(session.render(&mut image).unwrap(), 1);
+//!
+//! session.run(&parse_content("SNAPSHOT \"two FORWARD \"10 FORWARD \"10").unwrap()).unwrap();
+//! assert_eq!(session.render(&mut image).unwrap(), 2);
+//! session.run(&parse_content("RESTOREIMAGE \"two").unwrap()).unwrap();
+//! assert_eq!(session.render(&mut image).unwrap(), 2);
+//! ```
+
+use unsvg::Image;
+
+use crate::ast::ASTNode;
+use crate::error::RuntimeError;
+use crate::runner::run;
+use crate::turtle::Turtle;
+
+/// A turtle that statements are appended to over time, rendered incrementally.
+pub struct Session {
+    turtle: Turtle,
+    rendered: usize,
+    rewrites: u64,
+}
+
+impl Session {
+    /// Creates a new session with a fresh turtle for a canvas of the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_turtle(Turtle::new(width, height))
+    }
+
+    /// Creates a new session that continues from `turtle`. None of its lines have been rendered yet.
+    pub fn with_turtle(turtle: Turtle) -> Self {
+        let rewrites = turtle.get_rewrites();
+        Self { turtle, rendered: 0, rewrites }
+    }
+
+    /// Executes `instructions` after everything previously run in the session.
+    pub fn run(&mut self, instructions: &[ASTNode]) -> Result<(), RuntimeError> {
+        run(instructions, &mut self.turtle)
+    }
+
+    /// Draws the lines added since the last call to `render` onto `image`, returning how many were drawn.
+    ///
+    /// `image` should be the image the session was last rendered onto. To render onto a new image,
+    /// call `invalidate` first so every line is drawn. If lines were replaced since the last call, `image` is
+    /// replaced with a blank image of the same size and every line is drawn onto it.
+    pub fn render(&mut self, image: &mut Image) -> Result<usize, unsvg::Error> {
+        let display_list = self.turtle.get_display_list();
+        let lines = display_list.lines().len();
+        if self.turtle.get_rewrites() != self.rewrites || lines < self.rendered {
+            let (width, height) = image.get_dimensions();
+            *image = Image::new(width, height);
+            self.rendered = 0;
+            self.rewrites = self.turtle.get_rewrites();
+        }
+        display_list.render_from(self.rendered, image)?;
+        let drawn = lines.saturating_sub(self.rendered);
+        self.rendered = lines;
+        Ok(drawn)
+    }
+
+    /// Forgets which lines have been rendered, so the next call to `render` draws all of them.
+    pub fn invalidate(&mut self) {
+        self.rendered = 0;
+    }
+
+    /// Gets the turtle the session runs on.
+    pub fn get_turtle(&self) -> &Turtle {
+        &self.turtle
+    }
+
+    /// Gets the turtle the session runs on mutably, for example to change its settings.
+    pub fn get_turtle_mut(&mut self) -> &mut Turtle {
+        &mut self.turtle
+    }
+}
@@ -0,0 +1,268 @@
+//! # Similarity
+//!
+//! This module scores how alike two programs are, to help educators find submissions that were copied and
+//! then disguised.
+//!
+//! Both programs are normalized first. Variables, and procedures defined with `TO`, are renamed in the order
+//! they first appear, so renaming them changes nothing, and math on constants such as `+ "15 "5` is folded
+//! into its result. The score is the fraction of the words of the normalized programs that they have in
+//! common in the same order, from 0 for nothing in common to 1 for the same program.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::parser::parse_content;
+//! use rslogo::similarity::similarity;
+//!
+//! let original = parse_content("MAKE \"size \"20 REPEAT \"4 [ FORWARD :size TURN \"90 ]").unwrap();
+//! let disguised = parse_content("MAKE \"length + \"15 \"5 REPEAT \"4 [ FORWARD :length TURN \"90 ]").unwrap();
+//! let different = parse_content("PENDOWN SETPENCOLOR \"3 FORWARD \"50 BACK \"50").unwrap();
+//!
+//! assert_eq!(similarity(&original, &disguised), 1.0);
+//! assert!(similarity(&original, &different) < 0.5);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::ast::{ASTNode, Condition, ControlFlow, Expression, Procedure, Query};
+use crate::turtle::Turtle;
+use crate::value::Value;
+
+/// Scores how alike `a` and `b` are once both are normalized, from 0 for nothing in common to 1 for the same
+/// program.
+pub fn similarity(a: &[ASTNode], b: &[ASTNode]) -> f32 {
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    2.0 * longest_common_subsequence(&a, &b) as f32 / (a.len() + b.len()) as f32
+}
+
+/// Renames the variables and procedures of `instructions` to `v1`, `v2`, ... and `p1`, `p2`, ... in the order
+/// they first appear, and folds math on constants into its result.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::parser::parse_content;
+/// use rslogo::similarity::normalize;
+/// 
+/// let asts = parse_content("TO square :side REPEAT \"4 [ FORWARD * :side \"2 TURN + \"45 \"45 ] END square \"10").unwrap();
+/// let normalized: Vec<_> = normalize(&asts).iter().map(ToString::to_string).collect();
+/// assert_eq!(normalized, ["TO p1 :v1 REPEAT \"4 [ FORWARD * :v1 \"2 TURN \"90 ] END", "p1 \"10"]);
+/// ```
+pub fn normalize(instructions: &[ASTNode]) -> Vec<ASTNode> {
+    let mut procedures = Vec::new();
+    definitions(instructions, &mut procedures);
+    let mut normalizer = Normalizer {
+        variables: HashMap::new(),
+        procedures: procedures.into_iter()
+            .enumerate()
+            .map(|(i, name)| (name, format!("p{}", i + 1)))
+            .collect(),
+        turtle: Turtle::new(0, 0),
+    };
+    let mut instructions = instructions.to_vec();
+    normalizer.block(&mut instructions);
+    instructions
+}
+
+/// The words of the normalized source code of `instructions`.
+fn words(instructions: &[ASTNode]) -> Vec<String> {
+    normalize(instructions).iter()
+        .flat_map(|instruction| instruction.to_string().split_whitespace().map(String::from).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Finds the length of the longest sequence of words that appears in both `a` and `b` in the same order.
+fn longest_common_subsequence(a: &[String], b: &[String]) -> usize {
+    let mut previous = vec![0; b.len() + 1];
+    for word in a {
+        let mut current = vec![0; b.len() + 1];
+        for (j, other) in b.iter().enumerate() {
+            current[j + 1] = if word == other { previous[j] + 1 } else { previous[j + 1].max(current[j]) };
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Collects the name of each procedure defined with `TO` in `instructions`, including in blocks, in order.
+fn definitions(instructions: &[ASTNode], names: &mut Vec<String>) {
+    for instruction in instructions {
+        let ASTNode::ControlFlow(flow, _) = instruction else {
+            continue;
+        };
+        let block = match flow {
+            ControlFlow::To { name, block, .. } => {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+                block
+            },
+            ControlFlow::If { block, .. }
+            | ControlFlow::While { block, .. }
+            | ControlFlow::DoWhile { block, .. }
+            | ControlFlow::Until { block, .. }
+            | ControlFlow::Repeat { block, .. }
+            | ControlFlow::For { block, .. }
+            | ControlFlow::DoTimes { block, .. }
+            | ControlFlow::WhenKey { block, .. }
+            | ControlFlow::Catch { block, .. }
+            | ControlFlow::WhenClick { block }
+            | ControlFlow::EveryFrame { block } => block,
+        };
+        definitions(block, names);
+    }
+}
+
+/// Rewrites a program in place into its normalized form.
+struct Normalizer {
+    /// The new name of each variable seen so far.
+    variables: HashMap<String, String>,
+    /// The new name of each procedure defined in the program.
+    procedures: HashMap<String, String>,
+    /// The turtle constant math is evaluated on, which it never reads.
+    turtle: Turtle,
+}
+
+impl Normalizer {
+    fn variable(&mut self, name: &str) -> String {
+        let next = format!("v{}", self.variables.len() + 1);
+        self.variables.entry(name.to_string()).or_insert(next).clone()
+    }
+
+    /// Renames the variable named by `expression`, if it is written as a word.
+    fn name(&mut self, expression: &mut Expression) {
+        if let Expression::String(name) = expression {
+            *name = self.variable(name);
+        }
+    }
+
+    /// Renames a call to a procedure defined in the program, leaving primitives as they are.
+    fn procedure_name(&self, name: &mut String) {
+        if let Some(new_name) = self.procedures.get(name) {
+            *name = new_name.clone();
+        }
+    }
+
+    fn block(&mut self, instructions: &mut [ASTNode]) {
+        for instruction in instructions {
+            match instruction {
+                ASTNode::Procedure(procedure, _) => self.procedure(procedure),
+                ASTNode::ControlFlow(flow, _) => self.control_flow(flow),
+            }
+        }
+    }
+
+    fn procedure(&mut self, procedure: &mut Procedure) {
+        match procedure {
+            Procedure::Make(name, _)
+            | Procedure::Local(name)
+            | Procedure::LocalMake(name, _)
+            | Procedure::AddAssign(name, _)
+            | Procedure::SubAssign(name, _)
+            | Procedure::MulAssign(name, _)
+            | Procedure::DivAssign(name, _) => self.name(name),
+            Procedure::Call(name, _) => self.procedure_name(name),
+            _ => {},
+        }
+        procedure.expressions_mut().into_iter().for_each(|expression| self.expression(expression));
+    }
+
+    fn control_flow(&mut self, flow: &mut ControlFlow) {
+        let block = match flow {
+            ControlFlow::If { condition, block }
+            | ControlFlow::While { condition, block }
+            | ControlFlow::DoWhile { condition, block }
+            | ControlFlow::Until { condition, block } => {
+                self.expression(condition);
+                block
+            },
+            ControlFlow::Repeat { count, block } => {
+                self.expression(count);
+                block
+            },
+            ControlFlow::For { variable, start, end, step, block } => {
+                self.name(variable);
+                [start, end, step].into_iter().for_each(|expression| self.expression(expression));
+                block
+            },
+            ControlFlow::DoTimes { variable, count, block } => {
+                self.name(variable);
+                self.expression(count);
+                block
+            },
+            ControlFlow::To { name, parameters, block } => {
+                self.procedure_name(name);
+                for parameter in parameters.iter_mut() {
+                    *parameter = self.variable(parameter);
+                }
+                block
+            },
+            ControlFlow::WhenKey { key: word, block } | ControlFlow::Catch { tag: word, block } => {
+                self.expression(word);
+                block
+            },
+            ControlFlow::WhenClick { block } | ControlFlow::EveryFrame { block } => block,
+        };
+        self.block(block);
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        match expression {
+            Expression::Float(_) | Expression::String(_) | Expression::ReadWord => {},
+            Expression::Variable(name) => *name = self.variable(name),
+            Expression::Query(query) => match query {
+                Query::Random(max) => self.expression(max),
+                Query::PixelColor(x, y) => {
+                    self.expression(x);
+                    self.expression(y);
+                },
+                _ => {},
+            },
+            Expression::Math(math) => {
+                math.operands_mut().into_iter().for_each(|operand| self.expression(operand));
+                if math.operands().iter().all(|operand| matches!(operand, Expression::Float(_))) {
+                    // Math that fails, such as a division by zero, is left for the program to fail on.
+                    if let Ok(Value::Float(value)) = expression.evaluate(&self.turtle) {
+                        *expression = Expression::Float(value);
+                    }
+                }
+            },
+            Expression::Bool(condition) => self.condition(condition),
+            Expression::Call(name, args) => {
+                self.procedure_name(name);
+                args.iter_mut().for_each(|arg| self.expression(arg));
+            },
+            Expression::List(items) => items.iter_mut().for_each(|item| self.expression(item)),
+            Expression::ListOp(op) => op.operands_mut().into_iter().for_each(|operand| self.expression(operand)),
+            Expression::Shared(shared) => self.expression(&mut shared.expression),
+        }
+    }
+
+    fn condition(&mut self, condition: &mut Condition) {
+        match condition {
+            Condition::Equal(lhs, rhs)
+            | Condition::NotEqual(lhs, rhs)
+            | Condition::NearlyEqual(lhs, rhs)
+            | Condition::LessThan(lhs, rhs)
+            | Condition::GreaterThan(lhs, rhs)
+            | Condition::LessEqual(lhs, rhs)
+            | Condition::GreaterEqual(lhs, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+            },
+            Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+                self.condition(lhs);
+                self.condition(rhs);
+            },
+            Condition::Not(condition) => self.condition(condition),
+            Condition::Inside(value)
+            | Condition::IsNumber(value)
+            | Condition::IsWord(value)
+            | Condition::IsList(value)
+            | Condition::IsEmpty(value) => self.expression(value),
+        }
+    }
+}
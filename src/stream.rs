@@ -0,0 +1,148 @@
+//! # Stream
+//!
+//! This module contains a WebSocket service that runs Logo programs on the server and pushes each line to the
+//! browser the moment it is drawn, so the client can animate the drawing in real time. It is only compiled
+//! with the `websocket` feature, and is started with `rslogo stream`.
+//!
+//! A client opens a connection and sends one text message with the program to run, either as plain Logo
+//! source or as a JSON object with a `source` and optionally a `width` and `height` (500 by default).
+//! The server answers with one JSON message per line drawn, then a final message listing every problem found
+//! in the same shape as the HTTP service, and closes the connection:
+//!
+//! ```text
+//! > PENDOWN FORWARD "10
+//! < {"color":[255,255,255],"speed":null,"type":"line","width":1.0,"x1":250.0,"x2":250.0,"y1":250.0,"y2":240.0}
+//! < {"diagnostics":[],"type":"done"}
+//! ```
+//!
+//! Each line carries the `speed` set with `SETSPEED` when it was drawn, or `null`, so the client can pace the
+//! animation the way the program asks, and the `width` set with `SETPENSIZE`. `WAIT` pauses the program on the
+//! server, so the lines after it arrive that much later, but never for longer than the program's time limit.
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::display::{Line, LineSink};
+use crate::input::NoInput;
+use crate::json::{parse_diagnostic, request_diagnostic, runtime_diagnostic};
+use crate::limits::Limits;
+use crate::parser::parse_content;
+use crate::runner::run;
+use crate::turtle::Turtle;
+
+/// The largest width or height, in pixels, that may be requested.
+const MAX_CANVAS_SIZE: u64 = 4096;
+
+type Socket = Arc<Mutex<WebSocket<TcpStream>>>;
+
+/// Listens on `address` and runs the programs sent to it, each with `limits` applied.
+///
+/// Each connection is handled on its own thread. This only returns if the address cannot be bound.
+pub fn stream(address: &str, limits: Limits) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    tracing::info!(address, "streaming");
+    for connection in listener.incoming() {
+        let Ok(connection) = connection else { continue };
+        std::thread::spawn(move || {
+            if let Err(e) = handle(connection, limits) {
+                tracing::warn!(error = %e, "connection failed");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(connection: TcpStream, limits: Limits) -> tungstenite::Result<()> {
+    let mut socket = tungstenite::accept(connection).map_err(|e| match e {
+        tungstenite::HandshakeError::Failure(e) => e,
+        tungstenite::HandshakeError::Interrupted(_) => tungstenite::Error::ConnectionClosed,
+    })?;
+    let request = loop {
+        match socket.read()? {
+            Message::Text(text) => break text,
+            Message::Close(_) => return Ok(()),
+            _ => continue,
+        }
+    };
+
+    let socket = Arc::new(Mutex::new(socket));
+    let diagnostics = execute(&request, &socket, limits);
+    let mut socket = socket.lock().expect("socket lock poisoned");
+    socket.send(Message::text(json!({ "type": "done", "diagnostics": diagnostics }).to_string()))?;
+    socket.close(None)?;
+    // Wait for the client to acknowledge the close.
+    while socket.read().is_ok() {}
+    Ok(())
+}
+
+/// Runs the program in `request`, sending each line over `socket`, and returns the problems found.
+fn execute(request: &str, socket: &Socket, limits: Limits) -> Vec<Value> {
+    let (source, width, height) = match serde_json::from_str::<Value>(request) {
+        Ok(Value::Object(object)) => {
+            let Some(source) = object.get("source").and_then(Value::as_str) else {
+                return vec![request_diagnostic("The request must have a source")];
+            };
+            let size = |key| match object.get(key).map(Value::as_u64) {
+                None => Some(500),
+                Some(Some(size)) if (1..=MAX_CANVAS_SIZE).contains(&size) => Some(size as u32),
+                Some(_) => None,
+            };
+            let (Some(width), Some(height)) = (size("width"), size("height")) else {
+                return vec![request_diagnostic(&format!("The size must be between 1 and {MAX_CANVAS_SIZE}"))];
+            };
+            (source.to_string(), width, height)
+        }
+        _ => (request.to_string(), 500, 500),
+    };
+
+    let instructions = match parse_content(&source) {
+        Ok(instructions) => instructions,
+        Err(errors) => return errors.iter().map(parse_diagnostic).collect(),
+    };
+
+    let mut turtle = Turtle::with_limits(width, height, limits);
+    turtle.set_line_sink(Some(Box::new(SocketSink(socket.clone()))));
+    turtle.set_input_source(Box::new(NoInput));
+    let deadline = limits.max_time.map(|max_time| Instant::now() + max_time);
+    turtle.set_clock(Some(Box::new(move |duration: Duration| {
+        // Sleeping past the deadline would hold the connection open, as the time limit is only checked between
+        // instructions.
+        let remaining = deadline.map_or(duration, |deadline| deadline.saturating_duration_since(Instant::now()));
+        std::thread::sleep(duration.min(remaining));
+        Ok(())
+    })));
+    let result = run(&instructions, &mut turtle);
+    let mut diagnostics: Vec<Value> = turtle.get_diagnostics()
+        .iter()
+        .map(|(error, span)| runtime_diagnostic(error, Some(span)))
+        .collect();
+    if let Err(e) = result {
+        diagnostics.push(runtime_diagnostic(&e, turtle.get_error_span()));
+    }
+    diagnostics
+}
+
+/// A `LineSink` that sends each line over a WebSocket as JSON.
+struct SocketSink(Socket);
+
+impl LineSink for SocketSink {
+    fn draw(&mut self, line: &Line) -> std::io::Result<()> {
+        let (x2, y2) = line.end();
+        let message = json!({
+            "type": "line",
+            "x1": line.x,
+            "y1": line.y,
+            "x2": x2,
+            "y2": y2,
+            "color": [line.color.red, line.color.green, line.color.blue],
+            "speed": line.speed,
+            "width": line.width,
+        });
+        let mut socket = self.0.lock().expect("socket lock poisoned");
+        socket.send(Message::text(message.to_string())).map_err(std::io::Error::other)
+    }
+}
@@ -0,0 +1,225 @@
+//! # Symbols
+//!
+//! This module contains the `SymbolTable`, which resolves every variable and procedure name in a program to
+//! where it is defined and where it is used, without running the program.
+//!
+//! Names are resolved over the tokens rather than the abstract syntax tree, since only the tokens know where
+//! each name was written. Variables are defined by `MAKE`, and procedures and their parameters by `TO`. Inside
+//! a procedure, a variable with the name of one of its parameters refers to the parameter.
+//!
+//! `SymbolTable::warnings` reports the names used at the top level of the program before any definition,
+//! which will fail when that line runs. Uses inside procedures and `WHENKEY` or `WHENCLICK` handlers are not
+//! reported, nor are those in `EVERYFRAME` handlers, since a procedure may be called, or a handler run, after
+//! the names it uses are defined. Nor are uses of the variables that hold the program's arguments, such as
+//! `arg1` and `runargs`, which the host defines before the program runs.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::symbols::{SymbolKind, SymbolTable};
+//!
+//! let source = "FORWARD :size\nMAKE \"size \"10\nTO square :side\n  FORWARD :side\nEND\nsquare :size";
+//! let symbols = SymbolTable::from_source(source);
+//!
+//! let size = symbols.get(&SymbolKind::Variable, "size").unwrap();
+//! assert_eq!(size.definitions, [19..24]);
+//! assert_eq!(size.uses, [8..13, 72..77]);
+//!
+//! // Clicking on the use of `:side` finds the parameter it refers to.
+//! let side = symbols.at(57).unwrap();
+//! assert_eq!(side.kind, SymbolKind::Parameter("square".to_string()));
+//! assert_eq!(side.definitions, [39..44]);
+//!
+//! let warnings = symbols.warnings();
+//! assert_eq!(warnings.len(), 1);
+//! assert_eq!(warnings[0].span, Some(8..13));
+//! ```
+
+use crate::ast::Span;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::tokenizer::{tokenize, Token};
+use crate::turtle::is_argument_variable;
+
+/// What a name refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A variable set with `MAKE`.
+    Variable,
+
+    /// A parameter of the procedure with this name.
+    Parameter(String),
+
+    /// A procedure defined with `TO`, or a registered primitive if it has no definitions.
+    Procedure,
+}
+
+/// A name in a program, with every place it is defined and used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    /// The name, without the `"` or `:` it was written with.
+    pub name: String,
+
+    /// What the name refers to.
+    pub kind: SymbolKind,
+
+    /// The spans of the tokens that define the name, in source order.
+    pub definitions: Vec<Span>,
+
+    /// The spans of the tokens that use the name, in source order.
+    pub uses: Vec<Span>,
+}
+
+/// Every name in a program, in the order each was first written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    /// The spans from each `TO` to its `END`, which only run when the procedure is called, and of each event
+    /// handler, which only runs when the host reports the event.
+    bodies: Vec<Span>,
+}
+
+impl SymbolTable {
+    /// Tokenizes `source` and resolves the names in it.
+    pub fn from_source(source: &str) -> Self {
+        Self::new(&tokenize(source).collect::<Vec<_>>())
+    }
+
+    /// Resolves the names in `tokens`, as produced by `tokenize`.
+    pub fn new(tokens: &[(Token, Span)]) -> Self {
+        let mut table = Self::default();
+        // The name and parameters of the procedure being defined, if any, and the offset of its `TO`.
+        let mut procedure: Option<(String, Vec<String>)> = None;
+        let mut body_start = 0;
+        let mut i = 0;
+        while i < tokens.len() {
+            let (token, span) = &tokens[i];
+            match token {
+                Token::To => {
+                    body_start = span.start;
+                    if let Some((Token::Word(name), span)) = tokens.get(i + 1) {
+                        table.symbol(SymbolKind::Procedure, name).definitions.push(span.clone());
+                        let mut parameters = Vec::new();
+                        i += 2;
+                        while let Some((Token::Variable(parameter) | Token::Value(parameter), span)) = tokens.get(i) {
+                            table.symbol(SymbolKind::Parameter(name.clone()), parameter).definitions.push(span.clone());
+                            parameters.push(parameter.clone());
+                            i += 1;
+                        }
+                        procedure = Some((name.clone(), parameters));
+                        continue;
+                    }
+                },
+                Token::End if procedure.is_some() => {
+                    procedure = None;
+                    table.bodies.push(body_start..span.end);
+                },
+                Token::WhenKey | Token::WhenClick | Token::EveryFrame => {
+                    let mut depth = 0;
+                    let end = tokens[i..].iter().find_map(|(token, span)| {
+                        match token {
+                            Token::LParen => depth += 1,
+                            Token::RParen if depth == 1 => return Some(span.end),
+                            Token::RParen => depth -= 1,
+                            _ => {},
+                        }
+                        None
+                    });
+                    table.bodies.push(span.start..end.unwrap_or(usize::MAX));
+                },
+                Token::Make | Token::Local | Token::LocalMake | Token::For | Token::DoTimes => {
+                    if let Some((Token::Value(name), span)) = tokens.get(i + 1) {
+                        let kind = variable_kind(&procedure, name);
+                        table.symbol(kind, name).definitions.push(span.clone());
+                        i += 2;
+                        continue;
+                    }
+                },
+                Token::AddAssign | Token::SubAssign | Token::MulAssign | Token::DivAssign => {
+                    if let Some((Token::Value(name), span)) = tokens.get(i + 1) {
+                        let kind = variable_kind(&procedure, name);
+                        table.symbol(kind, name).uses.push(span.clone());
+                        i += 2;
+                        continue;
+                    }
+                },
+                Token::Variable(name) => {
+                    let kind = variable_kind(&procedure, name);
+                    table.symbol(kind, name).uses.push(span.clone());
+                },
+                Token::Word(name) => table.symbol(SymbolKind::Procedure, name).uses.push(span.clone()),
+                _ => {},
+            }
+            i += 1;
+        }
+        if procedure.is_some() {
+            table.bodies.push(body_start..usize::MAX);
+        }
+        table
+    }
+
+    /// Finds the symbol of the given kind and name, adding it if this is the first time it was written.
+    fn symbol(&mut self, kind: SymbolKind, name: &str) -> &mut Symbol {
+        let index = match self.symbols.iter().position(|symbol| symbol.kind == kind && symbol.name == name) {
+            Some(index) => index,
+            None => {
+                self.symbols.push(Symbol { name: name.to_string(), kind, definitions: Vec::new(), uses: Vec::new() });
+                self.symbols.len() - 1
+            },
+        };
+        &mut self.symbols[index]
+    }
+
+    /// Gets every symbol, in the order each was first written.
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Gets the symbol of the given kind and name, if it was written anywhere.
+    pub fn get(&self, kind: &SymbolKind, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| &symbol.kind == kind && symbol.name == name)
+    }
+
+    /// Gets the symbol defined or used by the token at byte `offset`, if there is one.
+    pub fn at(&self, offset: usize) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| {
+            symbol.definitions.iter().chain(&symbol.uses).any(|span| span.contains(&offset))
+        })
+    }
+
+    /// Warns about each use at the top level of the program of a variable, or a procedure defined with `TO`,
+    /// that comes before its first definition.
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        let mut warnings: Vec<Diagnostic> = self.symbols.iter()
+            .filter(|symbol| match symbol.kind {
+                SymbolKind::Variable => !is_argument_variable(&symbol.name),
+                SymbolKind::Procedure => !symbol.definitions.is_empty(),
+                SymbolKind::Parameter(_) => false,
+            })
+            .flat_map(|symbol| {
+                let first_definition = symbol.definitions.first().map_or(usize::MAX, |span| span.start);
+                symbol.uses.iter()
+                    .filter(move |span| span.start < first_definition)
+                    .filter(|span| !self.bodies.iter().any(|body| body.contains(&span.start)))
+                    .map(move |span| Diagnostic {
+                        severity: Severity::Warning,
+                        message: format!("`{}` is used before it is defined", symbol.name),
+                        span: Some(span.clone()),
+                        expected: Vec::new(),
+                        recoverable: false,
+                    })
+            })
+            .collect();
+        warnings.sort_by_key(|warning| warning.span.as_ref().map(|span| span.start));
+        warnings
+    }
+}
+
+/// Decides whether a variable named `name` refers to a parameter of the procedure being defined.
+fn variable_kind(procedure: &Option<(String, Vec<String>)>, name: &str) -> SymbolKind {
+    match procedure {
+        Some((procedure, parameters)) if parameters.iter().any(|parameter| parameter == name) => {
+            SymbolKind::Parameter(procedure.clone())
+        },
+        _ => SymbolKind::Variable,
+    }
+}
@@ -0,0 +1,238 @@
+//! # Testing
+//!
+//! This module, built with the `testing` feature, contains `proptest` strategies that generate Logo programs
+//! and token streams, so the parser and interpreter can be property tested here and in downstream crates.
+//!
+//! `instruction` and `program` generate instructions from a representative subset of the language, chosen so
+//! that unparsing them and parsing the result gives the same instructions back. Shrinking drops instructions,
+//! flattens nested blocks, and replaces expressions with their operands, so a failing case is reported as a
+//! short program. `tokens` generates streams of arbitrary tokens, most of which are not valid programs, for
+//! checking that the parser rejects bad input rather than panicking.
+//!
+//! `ASTNode`, `Procedure`, `Expression`, `Condition`, `Program`, and `Token` implement `Arbitrary` with these
+//! strategies, so they can be used with `any`.
+//!
+//! # Example
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use rslogo::parser::{parse_content, parse_tokens};
+//! use rslogo::primitive::Primitives;
+//! use rslogo::program::Program;
+//! use rslogo::testing::tokens;
+//!
+//! proptest!(|(program in any::<Program>())| {
+//!     let source: Vec<String> = program.iter().map(|instruction| instruction.to_string()).collect();
+//!     prop_assert_eq!(parse_content(&source.join("\n")).unwrap(), program);
+//! });
+//!
+//! proptest!(|(tokens in tokens())| {
+//!     let end = tokens.len();
+//!     let spanned = tokens.into_iter().enumerate().map(|(i, token)| (token, i..i + 1)).collect();
+//!     let _ = parse_tokens(spanned, end, &Primitives::new());
+//! });
+//! ```
+
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::ast::{ASTNode, Condition, ControlFlow, Expression, Math, Procedure, Query};
+use crate::keywords::keywords;
+use crate::primitive::Primitives;
+use crate::program::Program;
+use crate::tokenizer::{tokenize, Token};
+
+/// Generates names of variables, which all start with `v` so they never clash with a keyword.
+pub fn name() -> impl Strategy<Value = String> {
+    "v[a-z0-9]{0,5}"
+}
+
+/// Generates words that start with a letter, so they are never read as numbers.
+pub fn word() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9]{0,7}"
+}
+
+/// Generates the queries that take no arguments.
+pub fn query() -> impl Strategy<Value = Query> {
+    prop_oneof![
+        Just(Query::XCOR),
+        Just(Query::YCOR),
+        Just(Query::HEADING),
+        Just(Query::COLOR),
+        Just(Query::FILLCOLOR),
+        Just(Query::SCREENWIDTH),
+        Just(Query::SCREENHEIGHT),
+        Just(Query::REPCOUNT),
+    ]
+}
+
+/// Generates expressions, nesting math operations up to four deep.
+pub fn expression() -> impl Strategy<Value = Expression> {
+    let leaf = prop_oneof![
+        (-1000.0f32..1000.0).prop_map(Expression::Float),
+        name().prop_map(Expression::Variable),
+        word().prop_map(Expression::String),
+        query().prop_map(Expression::Query),
+    ];
+    leaf.prop_recursive(4, 32, 2, |inner| {
+        prop_oneof![
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| math(Math::Add(lhs, rhs))),
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| math(Math::Sub(lhs, rhs))),
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| math(Math::Mul(lhs, rhs))),
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| math(Math::Div(lhs, rhs))),
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| math(Math::Mod(lhs, rhs))),
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| math(Math::Power(lhs, rhs))),
+            inner.clone().prop_map(|expr| math(Math::Sqrt(expr))),
+            inner.clone().prop_map(|expr| math(Math::Abs(expr))),
+            inner.prop_map(|max| Expression::Query(Query::Random(Box::new(max)))),
+        ]
+    })
+}
+
+fn math(math: Math) -> Expression {
+    Expression::Math(Box::new(math))
+}
+
+/// Generates conditions, combining comparisons with `AND`, `OR`, and `NOT` up to two deep.
+pub fn condition() -> impl Strategy<Value = Condition> {
+    let leaf = prop_oneof![
+        (expression(), expression()).prop_map(|(lhs, rhs)| Condition::Equal(lhs, rhs)),
+        (expression(), expression()).prop_map(|(lhs, rhs)| Condition::NotEqual(lhs, rhs)),
+        (expression(), expression()).prop_map(|(lhs, rhs)| Condition::LessThan(lhs, rhs)),
+        (expression(), expression()).prop_map(|(lhs, rhs)| Condition::GreaterEqual(lhs, rhs)),
+        expression().prop_map(Condition::IsNumber),
+        expression().prop_map(Condition::IsEmpty),
+    ];
+    leaf.prop_recursive(2, 8, 2, |inner| {
+        prop_oneof![
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| Condition::And(Box::new(lhs), Box::new(rhs))),
+            (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| Condition::Or(Box::new(lhs), Box::new(rhs))),
+            inner.prop_map(|condition| Condition::Not(Box::new(condition))),
+        ]
+    })
+}
+
+/// Generates procedures, which move the turtle, change its pen, or set variables.
+pub fn procedure() -> impl Strategy<Value = Procedure> {
+    prop_oneof![
+        Just(Procedure::PenUp),
+        Just(Procedure::PenDown),
+        expression().prop_map(Procedure::Forward),
+        expression().prop_map(Procedure::Back),
+        expression().prop_map(Procedure::Turn),
+        expression().prop_map(Procedure::SetHeading),
+        expression().prop_map(Procedure::SetPenColor),
+        (name(), expression()).prop_map(|(name, value)| Procedure::Make(Expression::String(name), value)),
+        (name(), expression()).prop_map(|(name, value)| Procedure::AddAssign(Expression::String(name), value)),
+    ]
+}
+
+/// Generates instructions, nesting `IF`, `WHILE`, `REPEAT`, `DO.WHILE`, and `DOTIMES` blocks up to three deep.
+/// Spans are all empty, as equality ignores them.
+pub fn instruction() -> impl Strategy<Value = ASTNode> {
+    let leaf = procedure().prop_map(|procedure| ASTNode::Procedure(procedure, 0..0));
+    leaf.prop_recursive(3, 24, 4, |inner| {
+        let block = vec(inner, 1..4);
+        prop_oneof![
+            (condition(), block.clone()).prop_map(|(condition, block)| {
+                flow(ControlFlow::If { condition: Expression::Bool(Box::new(condition)), block })
+            }),
+            (condition(), block.clone()).prop_map(|(condition, block)| {
+                flow(ControlFlow::While { condition: Expression::Bool(Box::new(condition)), block })
+            }),
+            (expression(), block.clone()).prop_map(|(count, block)| flow(ControlFlow::Repeat { count, block })),
+            (block.clone(), condition()).prop_map(|(block, condition)| {
+                flow(ControlFlow::DoWhile { block, condition: Expression::Bool(Box::new(condition)) })
+            }),
+            (name(), expression(), block).prop_map(|(name, count, block)| {
+                flow(ControlFlow::DoTimes { variable: Expression::String(name), count, block })
+            }),
+        ]
+    })
+}
+
+fn flow(flow: ControlFlow) -> ASTNode {
+    ASTNode::ControlFlow(flow, 0..0)
+}
+
+/// Generates programs of up to eight top-level instructions.
+pub fn program() -> impl Strategy<Value = Program> {
+    vec(instruction(), 0..8).prop_map(Program::new)
+}
+
+/// Generates a single token: a keyword, a bracket, a number, a word, or a variable.
+pub fn token() -> impl Strategy<Value = Token> {
+    let mut symbols: Vec<Token> = keywords(&Primitives::new())
+        .iter()
+        .filter_map(|keyword| tokenize(&keyword.name).next())
+        .map(|(token, _)| token)
+        .collect();
+    symbols.extend([Token::LParen, Token::RParen, Token::OpenGroup, Token::CloseGroup]);
+    prop_oneof![
+        4 => select(symbols),
+        1 => (-1000.0f32..1000.0).prop_map(|value| Token::Value(value.to_string())),
+        1 => word().prop_map(Token::Value),
+        1 => name().prop_map(Token::Variable),
+    ]
+}
+
+/// Generates streams of up to 32 tokens in any order.
+pub fn tokens() -> impl Strategy<Value = Vec<Token>> {
+    vec(token(), 0..32)
+}
+
+impl Arbitrary for ASTNode {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        instruction().boxed()
+    }
+}
+
+impl Arbitrary for Procedure {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        procedure().boxed()
+    }
+}
+
+impl Arbitrary for Expression {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        expression().boxed()
+    }
+}
+
+impl Arbitrary for Condition {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        condition().boxed()
+    }
+}
+
+impl Arbitrary for Program {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        program().boxed()
+    }
+}
+
+impl Arbitrary for Token {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        token().boxed()
+    }
+}
@@ -31,6 +31,7 @@ use logos::Logos;
 /// The `#[token("...")]` attribute is used to define the keywords and symbols that the tokenizer should recognize.
 /// The `#[regex("...")]` attribute is used to define regular expressions that the tokenizer should match.
 /// The `#[regex(r"//.*\n", logos::skip)]` attribute is used to skip comments in the input string.
+/// The `#[regex(r";#[^\n]*", logos::skip)]` attribute is used to skip pragmas, which are read by the `pragma` module.
 /// The `#[regex(r"[ \t\n\f]+", logos::skip)]` attribute is used to skip whitespace characters in the input string.
 /// 
 #[derive(Logos, Debug, PartialEq, Clone, Hash, Eq, Ord, PartialOrd)]
@@ -46,6 +47,14 @@ pub enum Token {
 	#[token("PENDOWN")]
 	PenDown,
 
+	/// The `PushState` variant is used to represent the `PUSHSTATE` keyword in Logo code.
+	#[token("PUSHSTATE")]
+	PushState,
+
+	/// The `PopState` variant is used to represent the `POPSTATE` keyword in Logo code.
+	#[token("POPSTATE")]
+	PopState,
+
 	/// The `Forward` variant is used to represent the `FORWARD` keyword in Logo code.
 	#[token("FORWARD")]
 	Forward,
@@ -62,10 +71,18 @@ pub enum Token {
 	#[token("RIGHT")]
 	Right,
 
+	/// The `MovePolar` variant is used to represent the `MOVEPOLAR` keyword in Logo code.
+	#[token("MOVEPOLAR")]
+	MovePolar,
+
 	/// The `SetPenColor` variant is used to represent the `SETPENCOLOR` keyword in Logo code.
 	#[token("SETPENCOLOR")]
 	SetPenColor,
 
+	/// The `SetFillColor` variant is used to represent the `SETFILLCOLOR` keyword in Logo code.
+	#[token("SETFILLCOLOR")]
+	SetFillColor,
+
 	/// The `Turn` variant is used to represent the `TURN` keyword in Logo code.
 	#[token("TURN")]
 	Turn,
@@ -82,22 +99,87 @@ pub enum Token {
 	#[token("SETY")]
 	SetY,
 
+	/// The `SetSeed` variant is used to represent the `SETSEED` keyword in Logo code.
+	#[token("SETSEED")]
+	SetSeed,
+
+	/// The `SetSpeed` variant is used to represent the `SETSPEED` keyword in Logo code.
+	#[token("SETSPEED")]
+	SetSpeed,
+
+	/// The `SetPenSize` variant is used to represent the `SETPENSIZE` keyword in Logo code.
+	#[token("SETPENSIZE")]
+	SetPenSize,
+
+	/// The `FillAt` variant is used to represent the `FILLAT` keyword in Logo code.
+	#[token("FILLAT")]
+	FillAt,
+
+	/// The `Toot` variant is used to represent the `TOOT` keyword in Logo code.
+	#[token("TOOT")]
+	Toot,
+
+	/// The `Wait` variant is used to represent the `WAIT` keyword in Logo code.
+	#[token("WAIT")]
+	Wait,
+
+	/// The `Mark` variant is used to represent the `MARK` keyword in Logo code.
+	#[token("MARK")]
+	Mark,
+
+	/// The `Goto` variant is used to represent the `GOTO` keyword in Logo code.
+	#[token("GOTO")]
+	Goto,
+
+	/// The `Snapshot` variant is used to represent the `SNAPSHOT` keyword in Logo code.
+	#[token("SNAPSHOT")]
+	Snapshot,
+
+	/// The `RestoreImage` variant is used to represent the `RESTOREIMAGE` keyword in Logo code.
+	#[token("RESTOREIMAGE")]
+	RestoreImage,
+
 	/// The `Make` variant is used to represent the `MAKE` keyword in Logo code.
 	#[token("MAKE")]
 	Make,
 
+	/// The `Local` variant is used to represent the `LOCAL` keyword in Logo code.
+	#[token("LOCAL")]
+	Local,
+
+	/// The `LocalMake` variant is used to represent the `LOCALMAKE` keyword in Logo code.
+	#[token("LOCALMAKE")]
+	LocalMake,
+
 	/// The `AddAssign` variant is used to represent the `ADDASSIGN` keyword in Logo code.
 	#[token("ADDASSIGN")]
 	AddAssign,
 
-	/// The `Value` variant is used to represent a value in Logo code.
-	#[regex(r#""[^\s"]*"#, |lex| lex.slice()[1..].to_string())]
+	/// The `SubAssign` variant is used to represent the `SUBASSIGN` keyword in Logo code.
+	#[token("SUBASSIGN")]
+	SubAssign,
+
+	/// The `MulAssign` variant is used to represent the `MULASSIGN` keyword in Logo code.
+	#[token("MULASSIGN")]
+	MulAssign,
+
+	/// The `DivAssign` variant is used to represent the `DIVASSIGN` keyword in Logo code.
+	#[token("DIVASSIGN")]
+	DivAssign,
+
+	/// The `Value` variant is used to represent a value in Logo code, either quoted or a bare number.
+	#[regex(r#""[^\s"()]*"#, |lex| lex.slice()[1..].to_string())]
+	#[regex(r"[0-9]*\.?[0-9]+", |lex| lex.slice().to_string())]
     Value(String),
 
 	/// The `Variable` variant is used to represent a variable in Logo code.
-	#[regex(r#":[^\s"]*"#, |lex| lex.slice()[1..].to_string())]
+	#[regex(r#":[^\s"()]*"#, |lex| lex.slice()[1..].to_string())]
 	Variable(String),
 
+	/// The `Word` variant is used to represent a bare word that is not a keyword, such as the name of a registered primitive.
+	#[regex(r"[A-Za-z_][A-Za-z0-9_]*", |lex| lex.slice().to_string())]
+	Word(String),
+
 	/// The `XCor` variant is used to represent the `XCOR` Query in Logo code.
 	#[token("XCOR")]
 	XCOR,
@@ -113,6 +195,30 @@ pub enum Token {
 	/// The `Color` variant is used to represent the `COLOR` Query in Logo code.
 	#[token("COLOR")]
 	COLOR,
+
+	/// The `FillColor` variant is used to represent the `FILLCOLOR` Query in Logo code.
+	#[token("FILLCOLOR")]
+	FILLCOLOR,
+
+	/// The `ScreenWidth` variant is used to represent the `SCREENWIDTH` Query in Logo code.
+	#[token("SCREENWIDTH")]
+	SCREENWIDTH,
+
+	/// The `ScreenHeight` variant is used to represent the `SCREENHEIGHT` Query in Logo code.
+	#[token("SCREENHEIGHT")]
+	SCREENHEIGHT,
+
+	/// The `RepCount` variant is used to represent the `REPCOUNT` Query in Logo code.
+	#[token("REPCOUNT")]
+	REPCOUNT,
+
+	/// The `Random` variant is used to represent the `RANDOM` Query in Logo code.
+	#[token("RANDOM")]
+	Random,
+
+	/// The `PixelColor` variant is used to represent the `PIXELCOLOR` Query in Logo code.
+	#[token("PIXELCOLOR")]
+	PixelColor,
 	
 	/// The `If` variant is used to represent the `IF` keyword in Logo code.
 	#[token("IF")]
@@ -122,6 +228,46 @@ pub enum Token {
 	#[token("WHILE")]
 	While,
 
+	/// The `DoWhile` variant is used to represent the `DO.WHILE` keyword in Logo code.
+	#[token("DO.WHILE")]
+	DoWhile,
+
+	/// The `Until` variant is used to represent the `DO.UNTIL` keyword in Logo code.
+	#[token("DO.UNTIL")]
+	Until,
+
+	/// The `DoTimes` variant is used to represent the `DOTIMES` keyword in Logo code.
+	#[token("DOTIMES")]
+	DoTimes,
+
+	/// The `For` variant is used to represent the `FOR` keyword in Logo code.
+	#[token("FOR")]
+	For,
+
+	/// The `WhenKey` variant is used to represent the `WHENKEY` keyword in Logo code.
+	#[token("WHENKEY")]
+	WhenKey,
+
+	/// The `WhenClick` variant is used to represent the `WHENCLICK` keyword in Logo code.
+	#[token("WHENCLICK")]
+	WhenClick,
+
+	/// The `EveryFrame` variant is used to represent the `EVERYFRAME` keyword in Logo code.
+	#[token("EVERYFRAME")]
+	EveryFrame,
+
+	/// The `Repeat` variant is used to represent the `REPEAT` keyword in Logo code.
+	#[token("REPEAT")]
+	Repeat,
+
+	/// The `To` variant is used to represent the `TO` keyword in Logo code.
+	#[token("TO")]
+	To,
+
+	/// The `End` variant is used to represent the `END` keyword in Logo code.
+	#[token("END")]
+	End,
+
 	/// The `Equal` variant is used to represent the `EQ` keyword in Logo code.
 	#[token("EQ")]
 	Equal,
@@ -130,6 +276,10 @@ pub enum Token {
 	#[token("NE")]
 	NotEqual,
 
+	/// The `NearlyEqual` variant is used to represent the `NEARLYEQ` keyword in Logo code.
+	#[token("NEARLYEQ")]
+	NearlyEqual,
+
 	/// The `LessThan` variant is used to represent the `LT` keyword in Logo code.
 	#[token("LT")]
 	LessThan,
@@ -138,6 +288,14 @@ pub enum Token {
 	#[token("GT")]
 	GreaterThan,
 
+	/// The `LessEqual` variant is used to represent the `LE` keyword in Logo code.
+	#[token("LE")]
+	LessEqual,
+
+	/// The `GreaterEqual` variant is used to represent the `GE` keyword in Logo code.
+	#[token("GE")]
+	GreaterEqual,
+
 	/// The `And` variant is used to represent the `AND` keyword in Logo code.
 	#[token("AND")]
 	And,
@@ -146,6 +304,42 @@ pub enum Token {
 	#[token("OR")]
 	Or,
 
+	/// The `Not` variant is used to represent the `NOT` keyword in Logo code.
+	#[token("NOT")]
+	Not,
+
+	/// The `Catch` variant is used to represent the `CATCH` keyword in Logo code.
+	#[token("CATCH")]
+	Catch,
+
+	/// The `Throw` variant is used to represent the `THROW` keyword in Logo code.
+	#[token("THROW")]
+	Throw,
+
+	/// The `Inside` variant is used to represent the `INSIDE?` keyword in Logo code.
+	#[token("INSIDE?")]
+	Inside,
+
+	/// The `NumberP` variant is used to represent the `NUMBERP` keyword and its alias `NUMBER?` in Logo code.
+	#[token("NUMBERP")]
+	#[token("NUMBER?")]
+	NumberP,
+
+	/// The `WordP` variant is used to represent the `WORDP` keyword and its alias `WORD?` in Logo code.
+	#[token("WORDP")]
+	#[token("WORD?")]
+	WordP,
+
+	/// The `ListP` variant is used to represent the `LISTP` keyword and its alias `LIST?` in Logo code.
+	#[token("LISTP")]
+	#[token("LIST?")]
+	ListP,
+
+	/// The `EmptyP` variant is used to represent the `EMPTYP` keyword and its alias `EMPTY?` in Logo code.
+	#[token("EMPTYP")]
+	#[token("EMPTY?")]
+	EmptyP,
+
 	/// The `LParen` variant is used to represent the `[` symbol in Logo code.
 	#[token("[")]
 	LParen,
@@ -154,6 +348,14 @@ pub enum Token {
 	#[token("]")]
 	RParen,
 
+	/// The `OpenGroup` variant is used to represent the `(` symbol in Logo code.
+	#[token("(")]
+	OpenGroup,
+
+	/// The `CloseGroup` variant is used to represent the `)` symbol in Logo code.
+	#[token(")")]
+	CloseGroup,
+
 	/// The `Add` variant is used to represent the `+` symbol in Logo code.
 	#[token("+")]
 	Add,
@@ -170,12 +372,123 @@ pub enum Token {
 	#[token("/")]
 	Div,
 
+	/// The `Mod` variant is used to represent the `%` symbol and the `MOD` keyword in Logo code.
+	#[token("%")]
+	#[token("MOD")]
+	Mod,
+
+	/// The `Remainder` variant is used to represent the `REMAINDER` keyword in Logo code.
+	#[token("REMAINDER")]
+	Remainder,
+
+	/// The `Power` variant is used to represent the `POWER` keyword in Logo code.
+	#[token("POWER")]
+	Power,
+
+	/// The `Sin` variant is used to represent the `SIN` keyword in Logo code.
+	#[token("SIN")]
+	Sin,
+
+	/// The `Cos` variant is used to represent the `COS` keyword in Logo code.
+	#[token("COS")]
+	Cos,
+
+	/// The `Tan` variant is used to represent the `TAN` keyword in Logo code.
+	#[token("TAN")]
+	Tan,
+
+	/// The `Sqrt` variant is used to represent the `SQRT` keyword in Logo code.
+	#[token("SQRT")]
+	Sqrt,
+
+	/// The `Int` variant is used to represent the `INT` keyword in Logo code.
+	#[token("INT")]
+	Int,
+
+	/// The `Round` variant is used to represent the `ROUND` keyword in Logo code.
+	#[token("ROUND")]
+	Round,
+
+	/// The `Abs` variant is used to represent the `ABS` keyword in Logo code.
+	#[token("ABS")]
+	Abs,
+
+	/// The `Clamp` variant is used to represent the `CLAMP` keyword in Logo code.
+	#[token("CLAMP")]
+	Clamp,
+
+	/// The `Lerp` variant is used to represent the `LERP` keyword in Logo code.
+	#[token("LERP")]
+	Lerp,
+
+	/// The `MapRange` variant is used to represent the `MAP-RANGE` keyword in Logo code.
+	#[token("MAP-RANGE")]
+	MapRange,
+
+	/// The `First` variant is used to represent the `FIRST` keyword in Logo code.
+	#[token("FIRST")]
+	First,
+
+	/// The `ButFirst` variant is used to represent the `BUTFIRST` keyword and its abbreviation `BF` in Logo code.
+	#[token("BUTFIRST")]
+	#[token("BF")]
+	ButFirst,
+
+	/// The `Last` variant is used to represent the `LAST` keyword in Logo code.
+	#[token("LAST")]
+	Last,
+
+	/// The `Item` variant is used to represent the `ITEM` keyword in Logo code.
+	#[token("ITEM")]
+	Item,
+
+	/// The `Count` variant is used to represent the `COUNT` keyword in Logo code.
+	#[token("COUNT")]
+	Count,
+
+	/// The `Concat` variant is used to represent the `WORD` keyword in Logo code.
+	#[token("WORD")]
+	Concat,
+
+	/// The `Sentence` variant is used to represent the `SENTENCE` keyword and its abbreviation `SE` in Logo code.
+	#[token("SENTENCE")]
+	#[token("SE")]
+	Sentence,
+
+	/// The `Char` variant is used to represent the `CHAR` keyword in Logo code.
+	#[token("CHAR")]
+	Char,
+
+	/// The `ReadWord` variant is used to represent the `READWORD` keyword and its abbreviation `RW` in Logo code.
+	#[token("READWORD")]
+	#[token("RW")]
+	ReadWord,
+
 	/// The `Ignored` variant is used to represent whitespace, comments and newlines which are ignored in Logo code.
 	#[regex(r"//.*\n", logos::skip)]
+	#[regex(r";#[^\n]*", logos::skip)]
     #[regex(r"[ \t\n\f]+", logos::skip)]
     Ignored,
 }
 
+/// Text in Logo code that is not any token, as found by `try_tokenize`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidToken {
+	/// The text that is not a token.
+	pub text: String,
+
+	/// Where the text is in the code.
+	pub span: Range<usize>,
+}
+
+impl std::fmt::Display for InvalidToken {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "`{}` at {}..{} is not a valid token", self.text, self.span.start, self.span.end)
+	}
+}
+
+impl std::error::Error for InvalidToken {}
+
 /// The `tokenize` function takes a string slice as input and returns an iterator of tuples containing a `Token` and a `Range<usize>`.
 pub fn tokenize(content: &str) -> impl Iterator<Item = (Token, Range<usize>)> + '_{
 	let token_iter = Token::lexer(content)
@@ -185,4 +498,15 @@ pub fn tokenize(content: &str) -> impl Iterator<Item = (Token, Range<usize>)> +
 			Err(()) => (Token::Error, span),
 		});
 	token_iter
+}
+
+/// Tokenizes `content` like `tokenize`, but fails at the first text that is not a token instead of producing a
+/// `Token::Error` for it.
+pub fn try_tokenize(content: &str) -> Result<Vec<(Token, Range<usize>)>, InvalidToken> {
+	tokenize(content)
+		.map(|(token, span)| match token {
+			Token::Error => Err(InvalidToken { text: content[span.clone()].to_string(), span }),
+			token => Ok((token, span)),
+		})
+		.collect()
 }
\ No newline at end of file
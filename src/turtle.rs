@@ -2,11 +2,22 @@
 //! 
 //! This module contains the `Turtle` struct, which represents the state of the turtle in the Logo language.
 //! 
-//! The `Turtle` struct includes fields for the image being drawn on, the current variables, the turtle's position (`x`, `y`),
-//! heading, pen state, and pen color.
+//! The `Turtle` struct includes fields for the canvas size, the lines drawn so far, the current variables,
+//! the turtle's position (`x`, `y`), heading, pen state, pen color, and fill color.
 //! 
-//! The `new` method is used to create a new `Turtle` with a given image. The turtle starts at the center of the image,
+//! The `new` method is used to create a new `Turtle` for a canvas of the given size. The turtle starts at the center of the canvas,
 //! with a heading of 0.0, the pen up, and the pen color set to the 8th color in the `COLORS` array from the `unsvg` crate.
+//! Colors can also be chosen by the names in `COLOR_NAMES`, such as `SETPENCOLOR "red`.
+//! 
+//! `Turtle::builder` creates a turtle that starts somewhere else, such as in a corner with its pen down, without
+//! running Logo commands first.
+//! 
+//! Calling `set_heading_convention` chooses which way heading 0 points and whether positive turns are
+//! clockwise, so programs written for other Logo dialects are not drawn mirrored.
+//! 
+//! Calling `set_grid` enables snap-to-grid mode, where every endpoint is rounded to the nearest grid
+//! intersection before it is drawn. Calling `set_percentage_mode` makes distances and coordinates
+//! percentages of the canvas size, so the same program scales with the image.
 //! 
 //! # Example
 //! 
@@ -14,51 +25,915 @@
 //! use unsvg::Image;
 //! use rslogo::turtle::Turtle;
 //! 
-//! let mut image = Image::new(100, 100);
-//! let mut turtle = Turtle::new(&mut image);
+//! let mut turtle = Turtle::new(100, 100);
 //! 
 //! assert_eq!(turtle.get_x(), 50.0);
 //! assert_eq!(turtle.get_y(), 50.0);
+//! 
+//! let mut image = Image::new(100, 100);
+//! turtle.get_display_list().render(&mut image).unwrap();
 //! ```
 //! 
-//! This example creates a new `Image` and a new `Turtle` that will draw on the image.
+//! This example creates a new `Turtle` for a 100x100 canvas, and renders the lines it drew onto an `Image`.
+//! 
+//! The turtle also keeps a stack of saved states for `PUSHSTATE` and `POPSTATE`, so a subroutine can wander
+//! off and draw, then put the turtle back exactly where the caller left it:
+//! 
+//! ```
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//! 
+//! let mut turtle = Turtle::new(100, 100);
+//! run(&parse_content("PUSHSTATE PENDOWN TURN \"45 FORWARD \"20 POPSTATE").unwrap(), &mut turtle).unwrap();
+//! 
+//! assert_eq!((turtle.get_x(), turtle.get_y(), turtle.get_heading()), (50.0, 50.0, 0.0));
+//! assert_eq!(turtle.get_display_list().lines().len(), 1);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use unsvg::{get_end_coordinates, Color, COLORS};
+use crate::ast::{call_block, word, ASTNode, Definition, Procedure, Span};
+use crate::audio::AudioSink;
+use crate::audit::{AuditEntry, AuditSink};
+use crate::cancel::CancelToken;
+use crate::clock::{Clock, TICKS_PER_SECOND};
+use crate::display::{DisplayList, Line, LineSink, MAX_LINE_WIDTH};
+use crate::error::RuntimeError;
+use crate::input::{InputSource, StdinSource};
+use crate::keywords::is_builtin;
+use crate::limits::{Limits, Usage};
+use crate::primitive::Primitives;
+use crate::value::Value;
+
+/// The name of each color in the `COLORS` array, in the same order, as `SETPENCOLOR` accepts them.
+pub const COLOR_NAMES: [&str; 16] = [
+    "black", "blue", "cyan", "green", "red", "magenta", "yellow", "white",
+    "brown", "tan", "forest", "aqua", "salmon", "purple", "orange", "grey",
+];
+
+/// The name of the variable that holds the list of every argument passed to the program, as set by
+/// `Turtle::set_arguments`.
+pub const ARGUMENTS_VARIABLE: &str = "runargs";
+
+/// Whether `name` is one of the variables set by `Turtle::set_arguments`: `runargs`, `arg1`, `arg2`, and so on.
+pub fn is_argument_variable(name: &str) -> bool {
+    name == ARGUMENTS_VARIABLE
+        || name.strip_prefix("arg").is_some_and(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Finds the index in the `COLORS` array of the color called `name`, ignoring case.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::turtle::color_index;
+/// 
+/// assert_eq!(color_index("Red"), Some(4));
+/// assert_eq!(color_index("gray"), color_index("grey"));
+/// assert_eq!(color_index("mauve"), None);
+/// ```
+pub fn color_index(name: &str) -> Option<usize> {
+    let name = name.to_ascii_lowercase();
+    let name = match name.as_str() {
+        "gray" => "grey",
+        name => name,
+    };
+    COLOR_NAMES.iter().position(|&color| color == name)
+}
+
+/// How headings in a program map onto directions on the canvas, since Logo dialects disagree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeadingConvention {
+    /// Whether increasing the heading turns the turtle clockwise.
+    pub clockwise: bool,
+
+    /// The direction heading 0 points in, in degrees clockwise from straight up.
+    pub zero: f32,
+}
+
+impl HeadingConvention {
+    /// Heading 0 points up and positive turns are clockwise, as in most Logo dialects.
+    pub const LOGO: Self = Self { clockwise: true, zero: 0.0 };
+
+    /// Heading 0 points right and positive turns are counter-clockwise, as angles are measured in mathematics.
+    pub const MATHEMATICAL: Self = Self { clockwise: false, zero: 90.0 };
+
+    /// Converts a heading in this convention into a direction on the canvas, in degrees clockwise from straight up.
+    fn to_direction(self, heading: f32) -> f32 {
+        if self.clockwise {
+            self.zero + heading
+        } else {
+            self.zero - heading
+        }
+    }
+}
+
+impl Default for HeadingConvention {
+    fn default() -> Self {
+        Self::LOGO
+    }
+}
+
+/// The rules the names of variables created with `MAKE`, `LOCAL`, `LOCALMAKE`, `FOR`, and `DOTIMES` must follow.
+/// Names that break them raise `RuntimeError::InvalidVariableName`. Names are never allowed to be empty.
+/// 
+/// # Example
+/// 
+/// ```
+/// use rslogo::turtle::NamePolicy;
+/// 
+/// let policy = NamePolicy::default();
+/// assert!(policy.check("x2").is_ok());
+/// assert!(policy.check("2x").is_err());
+/// assert!(policy.check("COLOR").is_ok());
+/// 
+/// assert!(NamePolicy::STRICT.check("COLOR").is_err());
+/// assert!(NamePolicy::PERMISSIVE.check("2x").is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamePolicy {
+    /// Whether names may start with a digit, such as `2x`, which reads like a number.
+    pub allow_leading_digit: bool,
+
+    /// Whether names may be built in keywords, such as `FORWARD`.
+    pub allow_keywords: bool,
+}
 
-use std::collections::HashMap;
-use unsvg::{get_end_coordinates, Color, Image, COLORS};
-use crate::ast::Expression;
+impl NamePolicy {
+    /// Allows neither names starting with a digit nor keywords.
+    pub const STRICT: Self = Self { allow_leading_digit: false, allow_keywords: false };
 
+    /// Allows any name that is not empty.
+    pub const PERMISSIVE: Self = Self { allow_leading_digit: true, allow_keywords: true };
+
+    /// Checks that `name` follows the policy, returning an error that says which rule it breaks if not.
+    pub fn check(&self, name: &str) -> Result<(), RuntimeError> {
+        let reason = if name.is_empty() {
+            "it is empty"
+        } else if !self.allow_leading_digit && name.starts_with(|c: char| c.is_ascii_digit()) {
+            "it starts with a digit"
+        } else if !self.allow_keywords && is_builtin(name) {
+            "it is a keyword"
+        } else {
+            return Ok(());
+        };
+        Err(RuntimeError::InvalidVariableName { name: name.to_string(), reason })
+    }
+}
+
+impl Default for NamePolicy {
+    /// Allows keywords but not names starting with a digit, since many programs name a variable after the
+    /// query it holds, such as `:COLOR`.
+    fn default() -> Self {
+        Self { allow_leading_digit: false, allow_keywords: true }
+    }
+}
+
+/// The part of the turtle's state saved by `PUSHSTATE` and restored by `POPSTATE`.
+#[derive(Debug, Clone, Copy)]
+struct State {
+    x: f32,
+    y: f32,
+    heading: f32,
+    pen_down: bool,
+    pen_color: Color,
+    fill_color: Color,
+    pen_size: f32,
+}
+
+/// The variables of the global scope, or of a block or procedure call that is running.
+#[derive(Debug, Default)]
+struct Scope {
+    /// The variables, which have no value if they were declared with `LOCAL` and not given one yet.
+    variables: BTreeMap<String, Option<Value>>,
+
+    /// Whether this is the scope of a procedure call, which hides the local variables of its caller.
+    procedure: bool,
+}
 
 /// Represents the state of the turtle in the Logo language.
 /// 
-/// The `Turtle` struct includes fields for the image being drawn on, the current variables, the turtle's position (`x`, `y`),
-/// heading, pen state, and pen color.
+/// The `Turtle` struct includes fields for the canvas size, the lines drawn so far, the current variables,
+/// the turtle's position (`x`, `y`), heading, pen state, pen color, and fill color.
 /// 
-pub struct Turtle<'a> {
-    image: &'a mut Image,
-    variables: HashMap<String, Expression>,
+/// The turtle is `Send + Sync`, so a program can be executed on another thread and rendered later.
+/// 
+pub struct Turtle {
+    width: u32,
+    height: u32,
+    display_list: DisplayList,
+    front_buffer: Option<DisplayList>,
+    rewrites: u64,
+    scopes: Vec<Scope>,
     x: f32,
     y: f32,
     heading: f32,
     pen_down: bool,
     pen_color: Color,
+    fill_color: Color,
+    pen_size: f32,
+    states: Vec<State>,
+    marks: HashMap<String, (f32, f32, f32)>,
+    snapshots: HashMap<String, DisplayList>,
+    definitions: HashMap<String, Arc<Definition>>,
+    key_handlers: HashMap<String, Arc<Vec<ASTNode>>>,
+    click_handler: Option<Arc<Vec<ASTNode>>>,
+    frame_handler: Option<Arc<Vec<ASTNode>>>,
+    frame: u64,
+    repcount: Option<usize>,
+    grid: Option<f32>,
+    percentage_mode: bool,
+    heading_convention: HeadingConvention,
+    name_policy: NamePolicy,
+    primitives: Primitives,
+    limits: Limits,
+    usage: Usage,
+    fuel: Option<u64>,
+    cancel_token: Option<CancelToken>,
+    continue_on_error: bool,
+    integer_mode: bool,
+    epsilon: f32,
+    random_state: AtomicU64,
+    shared_values: Mutex<Vec<Option<Value>>>,
+    diagnostics: Vec<(RuntimeError, Span)>,
+    error_span: Option<Span>,
+    audit_sink: Option<Box<dyn AuditSink>>,
+    line_sink: Option<Box<dyn LineSink>>,
+    audio_sink: Option<Box<dyn AudioSink>>,
+    clock: Option<Box<dyn Clock>>,
+    input_source: Box<dyn InputSource>,
+    speed: Option<f32>,
+    delay: f32,
 }
 
-impl<'a> Turtle<'a> {
-    /// Creates a new `Turtle` with the given image.
-    pub fn new(image: &'a mut Image) -> Self {
-        let dimensions = image.get_dimensions();
-        let (x, y) = (dimensions.0 as f32 / 2.0, dimensions.1 as f32 / 2.0);
+impl Turtle {
+    /// Creates a new `Turtle` for a canvas of the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_limits(width, height, Limits::default())
+    }
+
+    /// Creates a new `Turtle` for a canvas of the given size, which will stop executing once any of `limits` is exceeded.
+    pub fn with_limits(width: u32, height: u32, limits: Limits) -> Self {
+        let (x, y) = (width as f32 / 2.0, height as f32 / 2.0);
         Self {
-            image,
-            variables: HashMap::new(),
+            width,
+            height,
+            display_list: DisplayList::new(),
+            front_buffer: None,
+            rewrites: 0,
+            scopes: vec![Scope::default()],
             x,
             y,
             heading: 0.0,
             pen_down: false,
             pen_color: COLORS[7],
+            fill_color: COLORS[7],
+            pen_size: 1.0,
+            states: Vec::new(),
+            marks: HashMap::new(),
+            snapshots: HashMap::new(),
+            definitions: HashMap::new(),
+            key_handlers: HashMap::new(),
+            click_handler: None,
+            frame_handler: None,
+            frame: 0,
+            repcount: None,
+            grid: None,
+            percentage_mode: false,
+            heading_convention: HeadingConvention::default(),
+            name_policy: NamePolicy::default(),
+            primitives: Primitives::new(),
+            limits,
+            usage: Usage::default(),
+            fuel: None,
+            cancel_token: None,
+            continue_on_error: false,
+            integer_mode: false,
+            epsilon: 0.0,
+            random_state: AtomicU64::new(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+            ),
+            shared_values: Mutex::new(Vec::new()),
+            diagnostics: Vec::new(),
+            error_span: None,
+            audit_sink: None,
+            line_sink: None,
+            audio_sink: None,
+            clock: None,
+            input_source: Box::new(StdinSource),
+            speed: None,
+            delay: 0.0,
         }
     }
 
+    /// Starts building a `Turtle` for a canvas of the given size, whose starting position, heading, and pen can
+    /// be chosen before it runs anything.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let mut turtle = Turtle::builder(100, 100)
+    ///     .position(0.0, 0.0)
+    ///     .heading(90.0)
+    ///     .pen_down(true)
+    ///     .pen_color(4.0)
+    ///     .build()
+    ///     .unwrap();
+    /// 
+    /// run(&parse_content("FORWARD \"10").unwrap(), &mut turtle).unwrap();
+    /// assert_eq!((turtle.get_x(), turtle.get_y()), (10.0, 0.0));
+    /// assert_eq!(turtle.get_display_list().lines().len(), 1);
+    /// assert_eq!(turtle.get_pen_color(), 4.0);
+    /// 
+    /// assert!(Turtle::builder(100, 100).pen_color(16.0).build().is_err());
+    /// ```
+    pub fn builder(width: u32, height: u32) -> TurtleBuilder {
+        TurtleBuilder {
+            width,
+            height,
+            limits: Limits::default(),
+            position: None,
+            heading: 0.0,
+            pen_down: false,
+            pen_color: 7.0,
+            fill_color: 7.0,
+            pen_size: 1.0,
+        }
+    }
+
+    /// Sets the sink that every executed command is recorded to. Passing `None` stops recording.
+    pub fn set_audit_sink(&mut self, sink: Option<Box<dyn AuditSink>>) {
+        self.audit_sink = sink;
+    }
+
+    /// Sets the sink that every line is sent to as soon as it is drawn. Passing `None` stops sending.
+    pub fn set_line_sink(&mut self, sink: Option<Box<dyn LineSink>>) {
+        self.line_sink = sink;
+    }
+
+    /// Takes the sink that lines are sent to, so it can be given to another turtle.
+    pub(crate) fn take_line_sink(&mut self) -> Option<Box<dyn LineSink>> {
+        self.line_sink.take()
+    }
+
+    /// Sets the sink that plays the tones of `TOOT`. Passing `None` makes `TOOT` silent.
+    pub fn set_audio_sink(&mut self, sink: Option<Box<dyn AudioSink>>) {
+        self.audio_sink = sink;
+    }
+
+    /// Plays a tone of `frequency` hertz for `duration` milliseconds on the audio sink, if there is one.
+    pub fn toot(&mut self, frequency: f32, duration: f32) -> Result<(), RuntimeError> {
+        if !(frequency > 0.0 && frequency.is_finite()) {
+            return Err(RuntimeError::Undefined { operation: "TOOT", value: frequency });
+        }
+        if !(duration >= 0.0 && duration.is_finite()) {
+            return Err(RuntimeError::Undefined { operation: "TOOT", value: duration });
+        }
+        match &mut self.audio_sink {
+            Some(sink) => sink.toot(frequency, duration).map_err(RuntimeError::Audio),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the clock that `WAIT` delays the program with. Passing `None` makes `WAIT` return at once.
+    pub fn set_clock(&mut self, clock: Option<Box<dyn Clock>>) {
+        self.clock = clock;
+    }
+
+    /// Waits for `ticks`, 60 to a second, on the clock, if there is one, and adds them to the delay recorded on
+    /// the next line drawn. Waiting for a negative time, or for longer than a `Duration` can hold, is an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use rslogo::error::RuntimeError;
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    ///
+    /// let mut turtle = Turtle::new(100, 100);
+    /// turtle.set_clock(Some(Box::new(|_: Duration| Ok(()))));
+    ///
+    /// run(&parse_content("WAIT \"30").unwrap(), &mut turtle).unwrap();
+    /// let result = run(&parse_content("WAIT \"99999999999999999999999").unwrap(), &mut turtle);
+    /// assert!(matches!(result, Err(RuntimeError::Undefined { operation: "WAIT", .. })));
+    /// ```
+    pub fn wait(&mut self, ticks: f32) -> Result<(), RuntimeError> {
+        let duration = Duration::try_from_secs_f32(ticks / TICKS_PER_SECOND)
+            .map_err(|_| RuntimeError::Undefined { operation: "WAIT", value: ticks })?;
+        self.delay += ticks;
+        match &mut self.clock {
+            Some(clock) => clock.wait(duration).map_err(RuntimeError::Clock),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the source of the words read by `READWORD`, which is standard input until this is called.
+    pub fn set_input_source(&mut self, source: Box<dyn InputSource>) {
+        self.input_source = source;
+    }
+
+    /// Reads the next word from the input source for `READWORD`, as a number if it is one.
+    pub(crate) fn read_word(&self) -> Result<Value, RuntimeError> {
+        let word = self.input_source.read_word().map_err(RuntimeError::Input)?;
+        Ok(crate::ast::word(word.unwrap_or_default()))
+    }
+
+    /// Gets the value kept in `slot` by a shared expression of the running instruction, if there is one.
+    pub(crate) fn shared_value(&self, slot: usize) -> Option<Value> {
+        let values = self.shared_values.lock().unwrap_or_else(PoisonError::into_inner);
+        values.get(slot).cloned().flatten()
+    }
+
+    /// Keeps `value` in `slot` for the later occurrences of a shared expression in the running instruction.
+    pub(crate) fn set_shared_value(&self, slot: usize, value: Value) {
+        let mut values = self.shared_values.lock().unwrap_or_else(PoisonError::into_inner);
+        if values.len() <= slot {
+            values.resize(slot + 1, None);
+        }
+        values[slot] = Some(value);
+    }
+
+    /// Forgets the values of shared expressions, as a new instruction starts.
+    pub(crate) fn clear_shared_values(&mut self) {
+        self.shared_values.get_mut().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// Records `procedure` and the turtle's current state to the audit sink, if there is one.
+    pub(crate) fn audit(&mut self, procedure: &Procedure) -> Result<(), RuntimeError> {
+        if self.audit_sink.is_none() {
+            return Ok(());
+        }
+        let entry = AuditEntry {
+            timestamp: std::time::SystemTime::now(),
+            step: self.usage.steps,
+            command: procedure.to_string(),
+            x: self.get_x(),
+            y: self.get_y(),
+            heading: self.heading,
+            pen_down: self.pen_down,
+            pen_color: self.get_pen_color(),
+        };
+        if let Some(sink) = &mut self.audit_sink {
+            sink.record(&entry).map_err(RuntimeError::Audit)?;
+        }
+        Ok(())
+    }
+
+    /// Enables or disables continue-on-error mode. In this mode, an instruction that raises a recoverable
+    /// `RuntimeError` is skipped and the error is collected as a diagnostic, so a partial drawing is still produced.
+    pub fn set_continue_on_error(&mut self, enabled: bool) {
+        self.continue_on_error = enabled;
+    }
+
+    /// Whether continue-on-error mode is enabled.
+    pub fn is_continue_on_error(&self) -> bool {
+        self.continue_on_error
+    }
+
+    /// Enables or disables integer mode. In this mode, whole numbers are kept as exact integers through
+    /// arithmetic and comparisons, and only become floats when a result is not a whole number.
+    pub fn set_integer_mode(&mut self, enabled: bool) {
+        self.integer_mode = enabled;
+    }
+
+    /// Whether integer mode is enabled.
+    pub fn is_integer_mode(&self) -> bool {
+        self.integer_mode
+    }
+
+    /// Sets the tolerance used when `EQ`, `NE`, `LT`, and `GT` compare floats, so values that differ by
+    /// at most `epsilon` are treated as equal. The default of 0 compares exactly.
+    pub fn set_epsilon(&mut self, epsilon: f32) {
+        self.epsilon = epsilon.abs();
+    }
+
+    /// Gets the tolerance used when comparing floats.
+    pub fn get_epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    /// Seeds the random number generator used by `RANDOM`, so the same seed always gives the same numbers.
+    /// Until this is called, the generator is seeded from the current time.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("SETSEED \"42 SETX RANDOM \"100 SETY RANDOM \"100").unwrap();
+    /// let mut first = Turtle::new(100, 100);
+    /// let mut second = Turtle::new(100, 100);
+    /// run(&asts, &mut first).unwrap();
+    /// run(&asts, &mut second).unwrap();
+    /// 
+    /// assert_eq!((first.get_x(), first.get_y()), (second.get_x(), second.get_y()));
+    /// assert!((0.0..100.0).contains(&first.get_x()) && first.get_x().fract() == 0.0);
+    /// ```
+    pub fn set_seed(&mut self, seed: u64) {
+        *self.random_state.get_mut() = seed;
+    }
+
+    /// Sets the speed recorded on the lines drawn from now on, in pixels per second, for animated exporters
+    /// to pace their playback with. It does not change the drawing itself.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("PENDOWN FORWARD \"10 SETSPEED \"50 FORWARD \"10").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// let speeds: Vec<_> = turtle.get_display_list().lines().iter().map(|line| line.speed).collect();
+    /// assert_eq!(speeds, [None, Some(50.0)]);
+    /// assert!(run(&parse_content("SETSPEED \"0").unwrap(), &mut turtle).is_err());
+    /// ```
+    pub fn set_speed(&mut self, speed: f32) -> Result<(), RuntimeError> {
+        if !(speed > 0.0 && speed.is_finite()) {
+            return Err(RuntimeError::Undefined { operation: "SETSPEED", value: speed });
+        }
+        self.speed = Some(speed);
+        Ok(())
+    }
+
+    /// Gets the speed set with `SETSPEED`, if one was set.
+    pub fn get_speed(&self) -> Option<f32> {
+        self.speed
+    }
+
+    /// Generates the next random number from 0 up to, but not including, 1.
+    /// 
+    /// The generator is SplitMix64, whose state is a single counter, so it can advance behind a shared reference.
+    pub fn next_random(&self) -> f32 {
+        const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut z = self.random_state.fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        // The top 24 bits fill an f32's mantissa exactly, so the result can never round up to 1.
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Records an error raised by a skipped instruction, with the span of the instruction that raised it.
+    pub(crate) fn add_diagnostic(&mut self, error: RuntimeError, span: Span) {
+        self.diagnostics.push((error, span));
+    }
+
+    /// Gets the errors raised by instructions skipped in continue-on-error mode, in the order they were raised,
+    /// each with the span of the instruction that raised it.
+    pub fn get_diagnostics(&self) -> &[(RuntimeError, Span)] {
+        &self.diagnostics
+    }
+
+    /// Records `span` as the location of the error being raised, unless a more deeply nested instruction already has.
+    pub(crate) fn locate_error(&mut self, span: &Span) {
+        self.error_span.get_or_insert_with(|| span.clone());
+    }
+
+    /// Takes the location of the error being raised, so the next error can be located afresh.
+    pub(crate) fn take_error_span(&mut self) -> Option<Span> {
+        self.error_span.take()
+    }
+
+    /// Gets the span of the instruction that raised the error that stopped the last `run`, if it failed.
+    pub fn get_error_span(&self) -> Option<&Span> {
+        self.error_span.as_ref()
+    }
+
+    /// Gets the number of instructions executed so far, including those inside blocks.
+    pub fn get_steps(&self) -> u64 {
+        self.usage.steps
+    }
+
+    /// Gets the largest number of variables that have been defined at once.
+    pub fn get_peak_variables(&self) -> usize {
+        self.usage.peak_variables
+    }
+
+    /// Records that an instruction is being executed, returning an error if the turtle has been cancelled, has
+    /// run out of fuel, or has exceeded the step or time limit.
+    pub(crate) fn step(&mut self) -> Result<(), RuntimeError> {
+        if self.cancel_token.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(RuntimeError::Cancelled);
+        }
+        if let Some(fuel) = &mut self.fuel {
+            *fuel = fuel.checked_sub(1).ok_or(RuntimeError::FuelExhausted)?;
+        }
+        Ok(self.limits.step(&mut self.usage)?)
+    }
+
+    /// Gives the turtle `fuel` instructions to execute before stopping with `RuntimeError::FuelExhausted`, or
+    /// lets it execute any number if `None`, which is the default.
+    /// 
+    /// Unlike the `max_steps` of the turtle's `Limits`, which counts every instruction the turtle ever executes,
+    /// fuel can be topped up, so a host can budget each run, or each frame of an animation, separately.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::error::RuntimeError;
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let mut turtle = Turtle::new(100, 100);
+    /// turtle.set_fuel(Some(100));
+    /// 
+    /// let asts = parse_content("WHILE EQ \"1 \"1 [ TURN \"1 ]").unwrap();
+    /// assert!(matches!(run(&asts, &mut turtle), Err(RuntimeError::FuelExhausted)));
+    /// assert_eq!(turtle.get_fuel(), Some(0));
+    /// 
+    /// turtle.set_fuel(Some(100));
+    /// run(&parse_content("FORWARD \"10").unwrap(), &mut turtle).unwrap();
+    /// assert_eq!(turtle.get_fuel(), Some(99));
+    /// ```
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// Sets the token that stops the turtle's programs once it is cancelled. Passing `None` means they can only
+    /// be stopped by the turtle's `Limits`.
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel_token = token;
+    }
+
+    /// Gets the number of instructions the turtle may still execute, or `None` if it has unlimited fuel.
+    pub fn get_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Records entering a nested block, returning an error if this exceeds the recursion depth limit.
+    pub(crate) fn enter_block(&mut self) -> Result<(), RuntimeError> {
+        Ok(self.limits.enter(&mut self.usage)?)
+    }
+
+    /// Records starting a procedure call, returning an error if this exceeds the call depth limit.
+    pub(crate) fn enter_call(&mut self) -> Result<(), RuntimeError> {
+        if self.limits.max_call_depth.is_some_and(|max| self.usage.calls >= max) {
+            return Err(RuntimeError::StackOverflow(self.usage.calls));
+        }
+        self.usage.calls += 1;
+        Ok(())
+    }
+
+    /// Checks that a `WHILE`, `DO.WHILE`, or `DO.UNTIL` loop, named by `keyword`, may start its `iteration`th run
+    /// of its block, counting from 1.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::limits::Limits;
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let limits = Limits { max_loop_iterations: Some(1000), ..Limits::default() };
+    /// let mut turtle = Turtle::with_limits(100, 100, limits);
+    /// 
+    /// let asts = parse_content("MAKE \"i \"0 WHILE LT :i \"1000 [ ADDASSIGN \"i \"1 ]").unwrap();
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// let asts = parse_content("MAKE \"i \"0\nWHILE GE :i \"0 [ ADDASSIGN \"i \"1 ]").unwrap();
+    /// let error = run(&asts, &mut turtle).unwrap_err();
+    /// assert_eq!(error.to_string(), "WHILE exceeded 1,000 iterations");
+    /// assert_eq!(turtle.get_error_span(), Some(&(11..45)));
+    /// ```
+    pub(crate) fn loop_iteration(&self, keyword: &'static str, iteration: u64) -> Result<(), RuntimeError> {
+        match self.limits.max_loop_iterations {
+            Some(max) if iteration > max => Err(RuntimeError::TooManyIterations { keyword, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records finishing a procedure call.
+    pub(crate) fn exit_call(&mut self) {
+        self.usage.calls -= 1;
+    }
+
+    /// Records leaving a nested block.
+    pub(crate) fn exit_block(&mut self) {
+        self.usage.depth -= 1;
+    }
+
+    /// Gets the lines the turtle has drawn so far.
+    pub fn get_display_list(&self) -> &DisplayList {
+        &self.display_list
+    }
+
+    /// Gets how many times the lines drawn so far were replaced rather than added to, as by `RESTOREIMAGE`
+    /// or a double buffered frame, so a host drawing only the lines added since it last looked knows to
+    /// start over.
+    pub fn get_rewrites(&self) -> u64 {
+        self.rewrites
+    }
+
+    /// Enables or disables double buffering of the frames drawn by `tick`. When double buffered, each frame
+    /// starts with no lines, and the lines of the last complete frame are kept apart, in `get_frame`, so a host
+    /// can draw them while the next frame is being drawn.
+    pub fn set_double_buffered(&mut self, enabled: bool) {
+        self.front_buffer = enabled.then(DisplayList::new);
+    }
+
+    /// Gets the lines of the last frame `tick` completed when double buffered, or every line drawn so far otherwise.
+    pub fn get_frame(&self) -> &DisplayList {
+        self.front_buffer.as_ref().unwrap_or(&self.display_list)
+    }
+
+    /// Sets the primitives that `Procedure::Call` and `Expression::Call` nodes are run against.
+    pub fn set_primitives(&mut self, primitives: Primitives) {
+        self.primitives = primitives;
+    }
+
+    /// Gets the primitives registered on the turtle.
+    pub fn get_primitives(&self) -> &Primitives {
+        &self.primitives
+    }
+
+    /// Enables or disables percentage mode. In percentage mode, distances and coordinates are
+    /// percentages of the canvas: `SETX` and `XCOR` use the width, `SETY` and `YCOR` use the height,
+    /// and movement distances use the smaller of the two.
+    pub fn set_percentage_mode(&mut self, enabled: bool) {
+        self.percentage_mode = enabled;
+    }
+
+    /// Converts `value` from the current units into pixels along an axis of length `extent`.
+    fn units_to_pixels(&self, value: f32, extent: u32) -> f32 {
+        if self.percentage_mode {
+            value * extent as f32 / 100.0
+        } else {
+            value
+        }
+    }
+
+    /// Converts `value` from pixels into the current units along an axis of length `extent`.
+    fn pixels_to_units(&self, value: f32, extent: u32) -> f32 {
+        if self.percentage_mode {
+            value * 100.0 / extent as f32
+        } else {
+            value
+        }
+    }
+
+    /// Converts a movement distance from the current units into pixels.
+    fn distance_to_pixels(&self, value: f32) -> f32 {
+        self.units_to_pixels(value, self.width.min(self.height))
+    }
+
+    /// Sets how headings map onto directions on the canvas. Headings already set are kept as numbers,
+    /// so the turtle may face a different direction afterwards.
+    pub fn set_heading_convention(&mut self, convention: HeadingConvention) {
+        self.heading_convention = convention;
+    }
+
+    /// Sets the rules the names of new variables must follow.
+    pub fn set_name_policy(&mut self, policy: NamePolicy) {
+        self.name_policy = policy;
+    }
+
+    /// Gets the rules the names of new variables must follow.
+    pub fn get_name_policy(&self) -> NamePolicy {
+        self.name_policy
+    }
+
+    /// Sets the size of the grid that endpoints are rounded to before drawing.
+    /// Passing `None`, or a size that is not positive, disables snapping.
+    /// 
+    /// Only the lines are snapped: each runs exactly between the grid points nearest to where it starts and
+    /// ends, however far it goes, while the turtle keeps its exact position. Turning the grid on therefore
+    /// never changes where a program moves the turtle or what `XCOR` and `YCOR` report.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let mut turtle = Turtle::new(98, 98);
+    /// turtle.set_grid(Some(7.0));
+    /// run(&parse_content("PENDOWN SETHEADING \"10 FORWARD \"100").unwrap(), &mut turtle).unwrap();
+    /// 
+    /// let line = turtle.get_display_list().lines()[0];
+    /// assert_eq!(line.end(), (63.0, -49.0));
+    /// assert!((turtle.get_x() - 66.36).abs() < 0.01);
+    /// 
+    /// let svg = turtle.get_display_list().to_svg(98, 98).unwrap();
+    /// assert!(svg.contains("L 63 -49"));
+    /// 
+    /// let mut turtle = Turtle::new(100, 100);
+    /// turtle.set_grid(Some(10.0));
+    /// run(&parse_content("PENDOWN REPEAT \"100 [ FORWARD \"1 ]").unwrap(), &mut turtle).unwrap();
+    /// assert_eq!(turtle.get_y(), -50.0);
+    /// assert_eq!(turtle.get_display_list().lines().len(), 100);
+    /// assert_eq!(turtle.get_display_list().lines()[99].end(), (50.0, -50.0));
+    /// ```
+    pub fn set_grid(&mut self, grid: Option<f32>) {
+        self.grid = grid.filter(|&size| size > 0.0);
+    }
+
+    /// Rounds `point` to the nearest grid intersection, if snapping is enabled.
+    fn snap(&self, point: (f32, f32)) -> (f32, f32) {
+        match self.grid {
+            Some(size) => ((point.0 / size).round() * size, (point.1 / size).round() * size),
+            None => point,
+        }
+    }
+
+    /// Moves the turtle `length` units in the direction of `heading`. If the pen is down, it will draw a line.
+    fn move_towards(&mut self, heading: i32, length: f32) -> Result<(), RuntimeError> {
+        let end = get_end_coordinates(self.x, self.y, heading, length);
+        if self.pen_down {
+            self.limits.image_operation(&mut self.usage)?;
+            let delay = std::mem::take(&mut self.delay);
+            let line = if self.grid.is_some() {
+                // The snapped endpoints may not lie on the original heading, so draw between them directly. Its
+                // direction is only the nearest whole degree, so the line keeps the exact endpoint too.
+                let (start, end) = (self.snap((self.x, self.y)), self.snap(end));
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let direction = (dy.atan2(dx).to_degrees() + 90.0).round() as i32;
+                Line {
+                    x: start.0,
+                    y: start.1,
+                    direction,
+                    length: dx.hypot(dy),
+                    color: self.pen_color,
+                    width: self.pen_size,
+                    speed: self.speed,
+                    delay,
+                    exact_end: Some(end),
+                }
+            } else {
+                Line {
+                    x: self.x,
+                    y: self.y,
+                    direction: heading,
+                    length,
+                    color: self.pen_color,
+                    width: self.pen_size,
+                    speed: self.speed,
+                    delay,
+                    exact_end: None,
+                }
+            };
+            if let Some(sink) = &mut self.line_sink {
+                sink.draw(&line).map_err(RuntimeError::LineSink)?;
+            }
+            self.display_list.push(line);
+        }
+        (self.x, self.y) = end;
+        Ok(())
+    }
+
+    /// Fills the region enclosed by lines around `x` and `y`, in the current units, with the fill color, drawing
+    /// nothing if a line passes through that point or it is off the canvas.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("PENDOWN REPEAT \"4 [ FORWARD \"20 TURN \"90 ] SETFILLCOLOR \"4 FILLAT \"60 \"40").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// assert_eq!(turtle.get_pixel_color(60.0, 40.0), 4.0);
+    /// assert_eq!(turtle.get_pixel_color(40.0, 40.0), -1.0);
+    /// ```
+    pub fn fill_at(&mut self, x: f32, y: f32) -> Result<(), RuntimeError> {
+        let (x, y) = (self.units_to_pixels(x, self.width), self.units_to_pixels(y, self.height));
+        self.limits.image_operation(&mut self.usage)?;
+        let rows = self.display_list.flood_fill(x, y, self.width, self.height, self.fill_color);
+        for mut row in rows {
+            row.speed = self.speed;
+            row.delay = std::mem::take(&mut self.delay);
+            if let Some(sink) = &mut self.line_sink {
+                sink.draw(&row).map_err(RuntimeError::LineSink)?;
+            }
+            self.display_list.push(row);
+        }
+        Ok(())
+    }
+
     /// Lifts the pen off the image. When the turtle moves, it will not draw anything.
     pub fn pen_up (&mut self) {
         self.pen_down = false;
@@ -70,38 +945,37 @@ impl<'a> Turtle<'a> {
     }
 
     /// Moves the turtle forward by `expr` units. If the pen is down, it will draw a line.
-    pub fn forward (&mut self, expr: f32) -> Result<(), unsvg::Error> {
-        let end = get_end_coordinates(self.x, self.y, self.heading as i32, expr);
-        if self.pen_down {
-            self.image.draw_simple_line(self.x, self.y, self.heading as i32, expr, self.pen_color)?;
-        }
-        (self.x, self.y) = end;
-        Ok(())
+    pub fn forward (&mut self, expr: f32) -> Result<(), RuntimeError> {
+        let length = self.distance_to_pixels(expr);
+        self.move_towards(self.heading_convention.to_direction(self.heading) as i32, length)
     }
 
     /// Moves the turtle backward by `expr` units. If the pen is down, it will draw a line.
-    pub fn back (&mut self, expr: f32) -> Result<(), unsvg::Error> {
+    pub fn back (&mut self, expr: f32) -> Result<(), RuntimeError> {
         self.forward(-expr)
     }
 
     /// Moves the turtle to the left by `expr` units. If the pen is down, it will draw a line.
-    pub fn left (&mut self, expr: f32) -> Result<(), unsvg::Error> {
-        let heading = (self.heading - 90.0) as i32;
-        let end = get_end_coordinates(self.x, self.y, heading, expr);
-        if self.pen_down {
-            self.image.draw_simple_line(self.x, self.y, heading, expr, self.pen_color)?;
-        } 
-        (self.x, self.y) = end;
-        Ok(())
+    pub fn left (&mut self, expr: f32) -> Result<(), RuntimeError> {
+        let heading = (self.heading_convention.to_direction(self.heading) - 90.0) as i32;
+        let length = self.distance_to_pixels(expr);
+        self.move_towards(heading, length)
     }
 
 
     /// Moves the turtle to the right by `expr` units. If the pen is down, it will draw a line.
-    pub fn right (&mut self, expr: f32) -> Result<(), unsvg::Error> {
+    pub fn right (&mut self, expr: f32) -> Result<(), RuntimeError> {
         self.left(-expr)
     }
 
 
+    /// Moves the turtle `distance` units towards the absolute `angle`, measured like a heading, regardless of
+    /// its heading. The heading is left unchanged. If the pen is down, it will draw a line.
+    pub fn move_polar(&mut self, angle: f32, distance: f32) -> Result<(), RuntimeError> {
+        let length = self.distance_to_pixels(distance);
+        self.move_towards(self.heading_convention.to_direction(angle) as i32, length)
+    }
+
     /// Turns the turtle by `expr` degrees.
     pub fn turn (&mut self, expr: f32) {
         self.heading += expr;
@@ -113,48 +987,630 @@ impl<'a> Turtle<'a> {
         self.heading = expr;
     }
 
-    /// Sets the pen color to the color at index `expr` in the `COLORS` array.
-    pub fn set_pen_color (&mut self, expr: f32) {
-        self.pen_color = COLORS[expr as usize];
+    /// Sets the pen color to the color at index `expr` in the `COLORS` array, which must be a whole number.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let mut turtle = Turtle::new(100, 100);
+    /// let error = run(&parse_content("PENDOWN\nSETPENCOLOR \"2.5").unwrap(), &mut turtle).unwrap_err();
+    /// assert_eq!(error.to_string(), "2.5 is not a valid color, which must be a whole number from 0 to 15");
+    /// assert_eq!(turtle.get_error_span(), Some(&(8..24)));
+    /// ```
+    pub fn set_pen_color (&mut self, expr: f32) -> Result<(), RuntimeError> {
+        self.pen_color = color(expr)?;
+        Ok(())
+    }
+
+    /// Sets the color that filled shapes are filled with to `expr`. The pen color is left unchanged.
+    pub fn set_fill_color(&mut self, expr: f32) -> Result<(), RuntimeError> {
+        self.fill_color = color(expr)?;
+        Ok(())
+    }
+
+    /// Sets the width, in pixels, of the lines drawn from now on. Lines are 1 pixel wide until this is called,
+    /// and may be at most `MAX_LINE_WIDTH` wide.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("PENDOWN FORWARD \"10 SETPENSIZE \"5 FORWARD \"10").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// let widths: Vec<_> = turtle.get_display_list().lines().iter().map(|line| line.width).collect();
+    /// assert_eq!(widths, [1.0, 5.0]);
+    /// assert_eq!(turtle.get_pen_size(), 5.0);
+    /// assert!(run(&parse_content("SETPENSIZE \"0").unwrap(), &mut turtle).is_err());
+    /// assert!(run(&parse_content("SETPENSIZE \"1000000000").unwrap(), &mut turtle).is_err());
+    /// assert_eq!(turtle.get_pen_size(), 5.0);
+    /// ```
+    pub fn set_pen_size(&mut self, size: f32) -> Result<(), RuntimeError> {
+        if !(size > 0.0 && size <= MAX_LINE_WIDTH) {
+            return Err(RuntimeError::Undefined { operation: "SETPENSIZE", value: size });
+        }
+        self.pen_size = size;
+        Ok(())
+    }
+
+    /// Gets the width, in pixels, of the lines the turtle draws.
+    pub fn get_pen_size(&self) -> f32 {
+        self.pen_size
     }
 
     /// Sets the x-coordinate of the turtle to `expr`.
     pub fn set_x (&mut self, expr: f32) {
-        self.x = expr;
+        self.x = self.units_to_pixels(expr, self.width);
     }
 
     /// Sets the y-coordinate of the turtle to `expr`.
     pub fn set_y (&mut self, expr: f32) {
-        self.y = expr;
+        self.y = self.units_to_pixels(expr, self.height);
+    }
+
+    /// Moves the turtle to `(x, y)` in pixels without drawing, ignoring the grid and percentage mode.
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        (self.x, self.y) = (x, y);
+    }
+
+    /// Sets the variable with the given name to `value`, returning an error if this exceeds the memory limit.
+    /// 
+    /// The innermost local variable with the name that can be seen is set, or else a global variable.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::error::RuntimeError;
+    /// use rslogo::limits::{Limit, Limits};
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let mut turtle = Turtle::with_limits(100, 100, Limits { max_memory: Some(1 << 20), ..Limits::default() });
+    /// let doubling = parse_content("MAKE \"x \"ab REPEAT \"24 [ MAKE \"x WORD :x :x ]").unwrap();
+    /// let result = run(&doubling, &mut turtle);
+    /// assert!(matches!(result, Err(RuntimeError::LimitExceeded(Limit::Memory))));
+    /// ```
+    pub fn add_variable (&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        let scope = self.scope_of(name).unwrap_or(0);
+        self.scopes[scope].variables.insert(name.to_string(), Some(value));
+        self.count_variables();
+        self.check_memory()
+    }
+
+    /// Declares a local variable with the given name and no value in the innermost scope, as `LOCAL` does,
+    /// unless it already has one, returning an error if this exceeds the memory limit.
+    pub fn add_local_variable(&mut self, name: &str) -> Result<(), RuntimeError> {
+        let scope = self.scopes.last_mut().expect("the global scope is never popped");
+        scope.variables.entry(name.to_string()).or_insert(None);
+        self.count_variables();
+        self.check_memory()
+    }
+
+    /// Removes the innermost variable with the given name that can be seen, returning its value if it had one.
+    pub fn remove_variable(&mut self, name: &str) -> Option<Value> {
+        let scope = self.scope_of(name)?;
+        self.scopes[scope].variables.remove(name).flatten()
+    }
+
+    /// Passes arguments to the program, so one program can draw a family of images chosen by its host. Each
+    /// argument is set as a global variable, `arg1` for the first, `arg2` for the second, and so on, and the
+    /// list of them all as `runargs`. Arguments that are numbers are set as numbers, and the rest as words.
+    /// Returns an error if they exceed the memory limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    ///
+    /// let mut turtle = Turtle::new(100, 100);
+    /// turtle.set_arguments(&["25", "red"]).unwrap();
+    ///
+    /// let asts = parse_content("SETPENCOLOR :arg2 PENDOWN FORWARD :arg1 FORWARD COUNT :runargs").unwrap();
+    /// run(&asts, &mut turtle).unwrap();
+    /// assert_eq!(turtle.get_y(), 50.0 - 27.0);
+    /// assert_eq!(turtle.get_pen_color(), 4.0);
+    /// ```
+    pub fn set_arguments<S: AsRef<str>>(&mut self, arguments: &[S]) -> Result<(), RuntimeError> {
+        let values: Vec<Value> = arguments.iter().map(|argument| word(argument.as_ref().to_string())).collect();
+        for (i, value) in values.iter().enumerate() {
+            self.add_variable(&format!("arg{}", i + 1), value.clone())?;
+        }
+        self.add_variable(ARGUMENTS_VARIABLE, Value::List(values))
+    }
+
+    /// Starts a scope for the local variables of a block, or of a procedure call if `procedure` is set.
+    pub(crate) fn push_scope(&mut self, procedure: bool) {
+        self.scopes.push(Scope { variables: BTreeMap::new(), procedure });
+    }
+
+    /// Ends the innermost scope, forgetting its local variables.
+    pub(crate) fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Gets the indices of the scopes whose variables can be seen, innermost first. A procedure call sees
+    /// its own local variables and the global ones, but not those of the procedure that called it.
+    fn visible_scopes(&self) -> impl Iterator<Item = usize> {
+        let start = self.scopes.iter().rposition(|scope| scope.procedure).unwrap_or(0);
+        (start..self.scopes.len()).rev().chain((start > 0).then_some(0))
+    }
+
+    /// Gets the index of the innermost scope that can be seen with a variable of the given name.
+    fn scope_of(&self, name: &str) -> Option<usize> {
+        self.visible_scopes().find(|&scope| self.scopes[scope].variables.contains_key(name))
+    }
+
+    /// Records the number of variables defined now, if it is the most there have been at once.
+    fn count_variables(&mut self) {
+        let count = self.scopes.iter().map(|scope| scope.variables.len()).sum();
+        self.usage.peak_variables = self.usage.peak_variables.max(count);
+    }
+
+    /// Defines the procedure `name`, replacing any earlier definition, so calls to it run `definition`.
+    pub fn define(&mut self, name: &str, definition: Definition) {
+        self.definitions.insert(name.to_string(), Arc::new(definition));
+    }
+
+    /// Gets the procedure defined with the given name, if any.
+    pub fn get_definition(&self, name: &str) -> Option<Arc<Definition>> {
+        self.definitions.get(name).cloned()
     }
 
-    /// Sets the x and y coordinates of the turtle to `(x, y)`.
-    pub fn add_variable (&mut self, name: &str, value: Expression) {
-        self.variables.insert(name.to_string(), value);
+    /// Gets the number of parameters of each procedure defined so far, by name, so source run later that calls
+    /// them can be parsed with `parse_tokens_with_definitions`.
+    pub fn get_definition_arities(&self) -> HashMap<String, usize> {
+        self.definitions.iter().map(|(name, definition)| (name.clone(), definition.parameters.len())).collect()
     }
 
-    /// Gets the value of the variable with the given name.
-    pub fn get_variable (&self, name: &String) -> &Expression {
-        self.variables.get(name).unwrap_or_else(|| panic!("{} Variable not found", name)) 
+    /// Runs `block` each time `press_key` is called with `key`, replacing any earlier handler for it, as `WHENKEY` does.
+    pub fn on_key(&mut self, key: &str, block: Arc<Vec<ASTNode>>) {
+        self.key_handlers.insert(key.to_string(), block);
+    }
+
+    /// Runs `block` each time `click` is called, replacing any earlier handler, as `WHENCLICK` does.
+    pub fn on_click(&mut self, block: Arc<Vec<ASTNode>>) {
+        self.click_handler = Some(block);
+    }
+
+    /// Runs `block` each time `tick` is called, replacing any earlier handler, as `EVERYFRAME` does.
+    pub fn on_frame(&mut self, block: Arc<Vec<ASTNode>>) {
+        self.frame_handler = Some(block);
+    }
+
+    /// Runs the `WHENKEY` handler for `key`, as a host does when the key is pressed, returning whether there was one.
+    /// 
+    /// Keys are matched exactly, so the host decides what each key is called, such as `"a` or `"up`.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let source = "WHENKEY \"right [ TURN \"90 ] WHENCLICK [ SETX :clickx SETY :clicky ]";
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&parse_content(source).unwrap(), &mut turtle).unwrap();
+    /// 
+    /// assert!(turtle.press_key("right").unwrap());
+    /// assert!(!turtle.press_key("left").unwrap());
+    /// assert_eq!(turtle.get_heading(), 90.0);
+    /// 
+    /// assert!(turtle.click(10.0, 20.0).unwrap());
+    /// assert_eq!((turtle.get_x(), turtle.get_y()), (10.0, 20.0));
+    /// ```
+    pub fn press_key(&mut self, key: &str) -> Result<bool, RuntimeError> {
+        let Some(block) = self.key_handlers.get(key).cloned() else {
+            return Ok(false);
+        };
+        call_block(&block, [], self)?;
+        Ok(true)
+    }
+
+    /// Runs the `WHENCLICK` handler, as a host does when the canvas is clicked at `(x, y)` in pixels, returning
+    /// whether there was one. The handler sees the position, in the current units, as `:clickx` and `:clicky`.
+    pub fn click(&mut self, x: f32, y: f32) -> Result<bool, RuntimeError> {
+        let Some(block) = self.click_handler.clone() else {
+            return Ok(false);
+        };
+        let position = [
+            ("clickx", Value::Float(self.pixels_to_units(x, self.width))),
+            ("clicky", Value::Float(self.pixels_to_units(y, self.height))),
+        ];
+        call_block(&block, position, self)?;
+        Ok(true)
+    }
+
+    /// Runs the `EVERYFRAME` handler, as a host does before drawing each frame of an animation, returning whether
+    /// there was one. The handler sees the number of the frame, counting from 1, as `:frame`.
+    /// 
+    /// Everything but the handler's local variables persists from one frame to the next, so each frame carries
+    /// on from the last. The host renders the lines drawn so far after each call, or only the new ones with a `Session`.
+    /// When double buffered, the frame is instead drawn from no lines, and swapped into `get_frame` once complete.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&parse_content("PENDOWN EVERYFRAME [ FORWARD \"5 TURN :frame ]").unwrap(), &mut turtle).unwrap();
+    /// assert!(turtle.get_display_list().lines().is_empty());
+    /// 
+    /// for _ in 0..3 {
+    ///     assert!(turtle.tick().unwrap());
+    /// }
+    /// assert_eq!(turtle.get_display_list().lines().len(), 3);
+    /// assert_eq!(turtle.get_heading(), 6.0);
+    /// 
+    /// turtle.set_double_buffered(true);
+    /// assert!(turtle.get_frame().lines().is_empty());
+    /// turtle.tick().unwrap();
+    /// assert_eq!(turtle.get_frame().lines().len(), 1);
+    /// ```
+    pub fn tick(&mut self) -> Result<bool, RuntimeError> {
+        let Some(block) = self.frame_handler.clone() else {
+            return Ok(false);
+        };
+        self.frame += 1;
+        if self.front_buffer.is_some() {
+            self.display_list.clear();
+            self.rewrites += 1;
+        }
+        call_block(&block, [("frame", Value::Float(self.frame as f32))], self)?;
+        if let Some(front) = &mut self.front_buffer {
+            std::mem::swap(front, &mut self.display_list);
+        }
+        Ok(true)
+    }
+
+    /// Saves the position, heading, and pen configuration of the turtle onto its state stack.
+    pub fn push_state(&mut self) -> Result<(), RuntimeError> {
+        self.states.push(State {
+            x: self.x,
+            y: self.y,
+            heading: self.heading,
+            pen_down: self.pen_down,
+            pen_color: self.pen_color,
+            fill_color: self.fill_color,
+            pen_size: self.pen_size,
+        });
+        self.check_memory()
+    }
+
+    /// Restores the position, heading, and pen configuration most recently saved with `push_state`.
+    /// The turtle moves back without drawing.
+    pub fn pop_state(&mut self) -> Result<(), RuntimeError> {
+        let state = self.states.pop().ok_or(RuntimeError::EmptyStateStack)?;
+        (self.x, self.y) = (state.x, state.y);
+        self.heading = state.heading;
+        self.pen_down = state.pen_down;
+        self.pen_color = state.pen_color;
+        self.fill_color = state.fill_color;
+        self.pen_size = state.pen_size;
+        Ok(())
+    }
+
+    /// Remembers the position and heading of the turtle under `name`, replacing any earlier mark with that name.
+    pub fn mark(&mut self, name: &str) -> Result<(), RuntimeError> {
+        self.marks.insert(name.to_string(), (self.x, self.y, self.heading));
+        self.check_memory()
+    }
+
+    /// Moves the turtle to the position and heading remembered under `name`. It does not draw a line.
+    pub fn goto(&mut self, name: &str) -> Result<(), RuntimeError> {
+        let (x, y, heading) = *self.marks.get(name).ok_or_else(|| RuntimeError::UndefinedMark(name.to_string()))?;
+        (self.x, self.y, self.heading) = (x, y, heading);
+        Ok(())
+    }
+
+    /// Saves a copy of the lines drawn so far under `name`, replacing any earlier snapshot with that name.
+    pub fn snapshot(&mut self, name: &str) -> Result<(), RuntimeError> {
+        self.snapshots.insert(name.to_string(), self.display_list.clone());
+        self.check_memory()
+    }
+
+    /// Replaces the lines drawn so far with those saved under `name` by `snapshot`, leaving the turtle where
+    /// it is. Lines already sent to the line sink are not taken back.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("PENDOWN FORWARD \"10 SNAPSHOT \"one FORWARD \"10 FORWARD \"10 RESTOREIMAGE \"one").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// assert_eq!(turtle.get_display_list().lines().len(), 1);
+    /// assert_eq!(turtle.get_y(), 20.0);
+    /// assert!(turtle.restore_image("two").is_err());
+    /// ```
+    pub fn restore_image(&mut self, name: &str) -> Result<(), RuntimeError> {
+        let snapshot = self.snapshots.get(name).ok_or_else(|| RuntimeError::UndefinedSnapshot(name.to_string()))?;
+        self.display_list.clone_from(snapshot);
+        self.rewrites += 1;
+        Ok(())
+    }
+
+    /// Checks that the variables, saved states, marks, and snapshots are within the memory limit.
+    fn check_memory(&self) -> Result<(), RuntimeError> {
+        let variables: usize = self.scopes.iter()
+            .flat_map(|scope| &scope.variables)
+            .map(|(name, value)| name.len() + value.as_ref().map_or(0, size_of_value))
+            .sum();
+        let states = self.states.len() * std::mem::size_of::<State>();
+        let marks: usize = self.marks.keys()
+            .map(|name| name.len() + std::mem::size_of::<(f32, f32, f32)>())
+            .sum();
+        let snapshots: usize = self.snapshots.iter()
+            .map(|(name, snapshot)| name.len() + std::mem::size_of_val(snapshot.lines()))
+            .sum();
+        Ok(self.limits.memory(variables + states + marks + snapshots)?)
+    }
+
+    /// Gets the value of the innermost variable with the given name that can be seen.
+    /// 
+    /// If there is none, the error suggests the variable with the closest name, in case the name was mistyped.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&parse_content("MAKE \"length \"10 MAKE \"angle \"90").unwrap(), &mut turtle).unwrap();
+    /// 
+    /// let error = turtle.get_variable("lenght").unwrap_err();
+    /// assert_eq!(error.to_string(), "Variable :lenght is not defined, did you mean :length?");
+    /// 
+    /// let error = turtle.get_variable("size").unwrap_err();
+    /// assert_eq!(error.to_string(), "Variable :size is not defined");
+    /// ```
+    pub fn get_variable (&self, name: &str) -> Result<&Value, RuntimeError> {
+        self.scope_of(name)
+            .and_then(|scope| self.scopes[scope].variables[name].as_ref())
+            .ok_or_else(|| RuntimeError::UndefinedVariable {
+                name: name.to_string(),
+                suggestion: self.suggest_variable(name),
+            })
+    }
+
+    /// Finds the variable that can be seen whose name is closest to `name`, if it is close enough to be a likely typo.
+    fn suggest_variable(&self, name: &str) -> Option<String> {
+        let tolerance = (name.chars().count() / 3).max(1);
+        self.get_variables()
+            .map(|(candidate, _)| (edit_distance(name, candidate), candidate))
+            .filter(|&(distance, _)| distance <= tolerance)
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, candidate)| candidate.to_string())
+    }
+
+    /// Gets every variable that can be seen with its value, ordered by name so the listing is the same on every run.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("MAKE \"y \"2 MAKE \"x \"1 MAKE \"z \"3").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// let names: Vec<&str> = turtle.get_variables().map(|(name, _)| name).collect();
+    /// assert_eq!(names, ["x", "y", "z"]);
+    /// ```
+    pub fn get_variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let mut variables = BTreeMap::new();
+        let scopes: Vec<usize> = self.visible_scopes().collect();
+        // Inner scopes are visited last, so their variables hide outer ones with the same name.
+        for scope in scopes.into_iter().rev() {
+            for (name, value) in &self.scopes[scope].variables {
+                variables.insert(name.as_str(), value.as_ref());
+            }
+        }
+        variables.into_iter().filter_map(|(name, value)| Some((name, value?)))
     }
     
-    /// Gets the x-coordinate of the turtle.
+    /// Gets the x-coordinate of the turtle, in the current units.
 	pub fn get_x(&self) -> f32 {
-		self.x
+		self.pixels_to_units(self.x, self.width)
 	}
 
-    /// Gets the y-coordinate of the turtle.
+    /// Gets the y-coordinate of the turtle, in the current units.
 	pub fn get_y(&self) -> f32 {
-		self.y
+		self.pixels_to_units(self.y, self.height)
 	}
 	
+    /// Gets the width and height of the canvas in pixels.
+	pub fn get_dimensions(&self) -> (u32, u32) {
+		(self.width, self.height)
+	}
+
+    /// Gets the width of the canvas, in the current units.
+	pub fn get_screen_width(&self) -> f32 {
+		self.pixels_to_units(self.width as f32, self.width)
+	}
+
+    /// Gets the height of the canvas, in the current units.
+	pub fn get_screen_height(&self) -> f32 {
+		self.pixels_to_units(self.height as f32, self.height)
+	}
+
+    /// Gets the iteration of the innermost `REPEAT` being executed, counting from 1, or -1 outside of any `REPEAT`.
+	pub fn get_repcount(&self) -> f32 {
+		self.repcount.map_or(-1.0, |count| count as f32)
+	}
+
+    /// Sets the iteration of the innermost `REPEAT` being executed, or `None` outside of any `REPEAT`,
+    /// returning the previous one.
+	pub fn set_repcount(&mut self, repcount: Option<usize>) -> Option<usize> {
+		std::mem::replace(&mut self.repcount, repcount)
+	}
+
     /// Gets the pen color of the turtle.
 	pub fn get_pen_color(&self) -> f32 {
 		COLORS.iter().position(|&x| x == self.pen_color).unwrap() as f32
 	}
 
+    /// Gets the fill color of the turtle.
+	pub fn get_fill_color(&self) -> f32 {
+		COLORS.iter().position(|&x| x == self.fill_color).unwrap() as f32
+	}
+
+    /// Gets the index in the `COLORS` array of the color drawn at `x` and `y`, in the current units, or -1 if
+    /// nothing has been drawn there.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use rslogo::parser::parse_content;
+    /// use rslogo::runner::run;
+    /// use rslogo::turtle::Turtle;
+    /// 
+    /// let asts = parse_content("PENDOWN SETPENCOLOR \"2 FORWARD \"10 MAKE \"hit PIXELCOLOR XCOR + YCOR \"5").unwrap();
+    /// let mut turtle = Turtle::new(100, 100);
+    /// run(&asts, &mut turtle).unwrap();
+    /// 
+    /// assert_eq!(turtle.get_variable("hit").unwrap().to_float(), Some(2.0));
+    /// assert_eq!(turtle.get_pixel_color(60.0, 45.0), -1.0);
+    /// ```
+    pub fn get_pixel_color(&self, x: f32, y: f32) -> f32 {
+        let (x, y) = (self.units_to_pixels(x, self.width), self.units_to_pixels(y, self.height));
+        self.display_list.color_at(x, y)
+            .and_then(|color| COLORS.iter().position(|&x| x == color))
+            .map_or(-1.0, |index| index as f32)
+    }
+
     /// Gets the heading of the turtle.
 	pub fn get_heading(&self) -> f32 {
 		self.heading
 	}
-}
\ No newline at end of file
+}
+
+/// Chooses the state a `Turtle` starts in. Created by `Turtle::builder`.
+#[derive(Debug, Clone)]
+pub struct TurtleBuilder {
+    width: u32,
+    height: u32,
+    limits: Limits,
+    position: Option<(f32, f32)>,
+    heading: f32,
+    pen_down: bool,
+    pen_color: f32,
+    fill_color: f32,
+    pen_size: f32,
+}
+
+impl TurtleBuilder {
+    /// Sets the limits the turtle stops executing at. Defaults to no limits.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the position the turtle starts at, in pixels. Defaults to the center of the canvas.
+    pub fn position(mut self, x: f32, y: f32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    /// Sets the heading the turtle starts with, in degrees. Defaults to 0.
+    pub fn heading(mut self, heading: f32) -> Self {
+        self.heading = heading;
+        self
+    }
+
+    /// Sets whether the pen starts down. Defaults to up.
+    pub fn pen_down(mut self, pen_down: bool) -> Self {
+        self.pen_down = pen_down;
+        self
+    }
+
+    /// Sets the index in the `COLORS` array of the pen color. Defaults to 7.
+    pub fn pen_color(mut self, index: f32) -> Self {
+        self.pen_color = index;
+        self
+    }
+
+    /// Sets the index in the `COLORS` array of the fill color. Defaults to 7.
+    pub fn fill_color(mut self, index: f32) -> Self {
+        self.fill_color = index;
+        self
+    }
+
+    /// Sets the width of the lines drawn, in pixels. Defaults to 1.
+    pub fn pen_size(mut self, size: f32) -> Self {
+        self.pen_size = size;
+        self
+    }
+
+    /// Creates the turtle, returning the error `SETPENCOLOR`, `SETFILLCOLOR`, or `SETPENSIZE` would if one of
+    /// the pen settings is invalid.
+    pub fn build(self) -> Result<Turtle, RuntimeError> {
+        let mut turtle = Turtle::with_limits(self.width, self.height, self.limits);
+        if let Some((x, y)) = self.position {
+            turtle.set_position(x, y);
+        }
+        turtle.set_heading(self.heading);
+        turtle.pen_down = self.pen_down;
+        turtle.set_pen_color(self.pen_color)?;
+        turtle.set_fill_color(self.fill_color)?;
+        turtle.set_pen_size(self.pen_size)?;
+        Ok(turtle)
+    }
+}
+
+/// Gets the color at index `expr` in the `COLORS` array, if it is a whole number within its bounds.
+fn color(expr: f32) -> Result<Color, RuntimeError> {
+    if expr.fract() != 0.0 || !(0.0..COLORS.len() as f32).contains(&expr) {
+        return Err(RuntimeError::InvalidColor(expr));
+    }
+    Ok(COLORS[expr as usize])
+}
+
+/// Estimates the memory held by a variable's value, counting every item of a list and the text of every word.
+fn size_of_value(value: &Value) -> usize {
+    let heap = match value {
+        Value::List(items) => items.iter().map(size_of_value).sum(),
+        Value::String(word) => word.len(),
+        _ => 0,
+    };
+    std::mem::size_of::<Value>() + heap
+}
+
+/// Counts the fewest characters that must be inserted, deleted, or replaced to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &b) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != b);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -14,6 +14,21 @@ impl UncertainBool {
     }
 }
 
+/// Compares two floats, treating them as equal if they differ by at most `epsilon`.
+pub fn is_option_near(a: Option<f32>, b: Option<f32>, epsilon: f32) -> UncertainBool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            if a == b || (a - b).abs() <= epsilon {
+                UncertainBool::True
+            } else {
+                UncertainBool::False
+            }
+        },
+        (None, None) => UncertainBool::Unknown,
+        _ => UncertainBool::False,
+    }
+}
+
 pub fn is_option_eq<T: PartialEq>(a: Option<T>, b: Option<T>) -> UncertainBool {
     match (a, b) {
         (Some(a), Some(b)) => {
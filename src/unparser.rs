@@ -0,0 +1,232 @@
+//! # Unparser
+//!
+//! This module implements `Display` for the AST, turning nodes back into Logo source code.
+//! The output uses the same prefix notation as the parser accepts, so it can be parsed again.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::parser::parse_content;
+//!
+//! let asts = parse_content("MAKE   \"x + XCOR  \"10").unwrap();
+//! assert_eq!(asts[0].to_string(), "MAKE \"x + XCOR \"10");
+//! ```
+
+use std::fmt;
+
+use crate::ast::{ASTNode, Condition, ControlFlow, Expression, ListOp, Math, Procedure, Query};
+
+impl fmt::Display for ASTNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ASTNode::Procedure(procedure, _) => write!(f, "{procedure}"),
+            ASTNode::ControlFlow(flow, _) => write!(f, "{flow}"),
+        }
+    }
+}
+
+impl fmt::Display for ControlFlow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (keyword, condition, block) = match self {
+            ControlFlow::If { condition, block } => ("IF", condition, block),
+            ControlFlow::While { condition, block } => ("WHILE", condition, block),
+            ControlFlow::Repeat { count, block } => ("REPEAT", count, block),
+            ControlFlow::WhenKey { key, block } => ("WHENKEY", key, block),
+            ControlFlow::Catch { tag, block } => ("CATCH", tag, block),
+            ControlFlow::DoWhile { block, condition } | ControlFlow::Until { block, condition } => {
+                let keyword = if matches!(self, ControlFlow::DoWhile { .. }) { "DO.WHILE" } else { "DO.UNTIL" };
+                write!(f, "{keyword} [")?;
+                for instruction in block {
+                    write!(f, " {instruction}")?;
+                }
+                return write!(f, " ] {condition}");
+            },
+            ControlFlow::DoTimes { variable, count, block } => {
+                write!(f, "DOTIMES {variable} {count} [")?;
+                for instruction in block {
+                    write!(f, " {instruction}")?;
+                }
+                return write!(f, " ]");
+            },
+            ControlFlow::For { variable, start, end, step, block } => {
+                write!(f, "FOR {variable} {start} {end} {step} [")?;
+                for instruction in block {
+                    write!(f, " {instruction}")?;
+                }
+                return write!(f, " ]");
+            },
+            ControlFlow::WhenClick { block } | ControlFlow::EveryFrame { block } => {
+                let keyword = if matches!(self, ControlFlow::WhenClick { .. }) { "WHENCLICK" } else { "EVERYFRAME" };
+                write!(f, "{keyword} [")?;
+                for instruction in block {
+                    write!(f, " {instruction}")?;
+                }
+                return write!(f, " ]");
+            },
+            ControlFlow::To { name, parameters, block } => {
+                write!(f, "TO {name}")?;
+                for parameter in parameters {
+                    write!(f, " :{parameter}")?;
+                }
+                for instruction in block {
+                    write!(f, " {instruction}")?;
+                }
+                return write!(f, " END");
+            },
+        };
+        write!(f, "{keyword} {condition} [")?;
+        for instruction in block {
+            write!(f, " {instruction}")?;
+        }
+        write!(f, " ]")
+    }
+}
+
+impl fmt::Display for Procedure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Procedure::PenUp => write!(f, "PENUP"),
+            Procedure::PenDown => write!(f, "PENDOWN"),
+            Procedure::PushState => write!(f, "PUSHSTATE"),
+            Procedure::PopState => write!(f, "POPSTATE"),
+            Procedure::Forward(expr) => write!(f, "FORWARD {expr}"),
+            Procedure::Back(expr) => write!(f, "BACK {expr}"),
+            Procedure::Left(expr) => write!(f, "LEFT {expr}"),
+            Procedure::Right(expr) => write!(f, "RIGHT {expr}"),
+            Procedure::MovePolar(angle, distance) => write!(f, "MOVEPOLAR {angle} {distance}"),
+            Procedure::SetPenColor(expr) => write!(f, "SETPENCOLOR {expr}"),
+            Procedure::SetFillColor(expr) => write!(f, "SETFILLCOLOR {expr}"),
+            Procedure::Turn(expr) => write!(f, "TURN {expr}"),
+            Procedure::SetHeading(expr) => write!(f, "SETHEADING {expr}"),
+            Procedure::SetX(expr) => write!(f, "SETX {expr}"),
+            Procedure::SetY(expr) => write!(f, "SETY {expr}"),
+            Procedure::SetSeed(expr) => write!(f, "SETSEED {expr}"),
+            Procedure::SetSpeed(expr) => write!(f, "SETSPEED {expr}"),
+            Procedure::Wait(expr) => write!(f, "WAIT {expr}"),
+            Procedure::SetPenSize(expr) => write!(f, "SETPENSIZE {expr}"),
+            Procedure::Throw(tag) => write!(f, "THROW {tag}"),
+            Procedure::Toot(frequency, duration) => write!(f, "TOOT {frequency} {duration}"),
+            Procedure::FillAt(x, y) => write!(f, "FILLAT {x} {y}"),
+            Procedure::Mark(name) => write!(f, "MARK \"{name}"),
+            Procedure::Goto(name) => write!(f, "GOTO \"{name}"),
+            Procedure::Snapshot(name) => write!(f, "SNAPSHOT \"{name}"),
+            Procedure::RestoreImage(name) => write!(f, "RESTOREIMAGE \"{name}"),
+            Procedure::Make(name, value) => write!(f, "MAKE {name} {value}"),
+            Procedure::Local(name) => write!(f, "LOCAL {name}"),
+            Procedure::LocalMake(name, value) => write!(f, "LOCALMAKE {name} {value}"),
+            Procedure::AddAssign(name, value) => write!(f, "ADDASSIGN {name} {value}"),
+            Procedure::SubAssign(name, value) => write!(f, "SUBASSIGN {name} {value}"),
+            Procedure::MulAssign(name, value) => write!(f, "MULASSIGN {name} {value}"),
+            Procedure::DivAssign(name, value) => write!(f, "DIVASSIGN {name} {value}"),
+            Procedure::Call(name, args) => {
+                write!(f, "{name}")?;
+                args.iter().try_for_each(|arg| write!(f, " {arg}"))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Float(val) => write!(f, "\"{val}"),
+            Expression::Query(query) => write!(f, "{query}"),
+            Expression::Variable(name) => write!(f, ":{name}"),
+            Expression::String(val) => write!(f, "\"{val}"),
+            Expression::Math(math) => write!(f, "{math}"),
+            Expression::Bool(condition) => write!(f, "{condition}"),
+            Expression::Call(name, args) => {
+                write!(f, "{name}")?;
+                args.iter().try_for_each(|arg| write!(f, " {arg}"))
+            },
+            Expression::List(items) => {
+                write!(f, "[")?;
+                items.iter().try_for_each(|item| write!(f, " {item}"))?;
+                write!(f, " ]")
+            },
+            Expression::ListOp(op) => write!(f, "{op}"),
+            Expression::ReadWord => write!(f, "READWORD"),
+            Expression::Shared(shared) => write!(f, "{}", shared.expression),
+        }
+    }
+}
+
+impl fmt::Display for ListOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListOp::First(list) => write!(f, "FIRST {list}"),
+            ListOp::ButFirst(list) => write!(f, "BUTFIRST {list}"),
+            ListOp::Last(list) => write!(f, "LAST {list}"),
+            ListOp::Item(index, list) => write!(f, "ITEM {index} {list}"),
+            ListOp::Count(list) => write!(f, "COUNT {list}"),
+            ListOp::Word(first, second) => write!(f, "WORD {first} {second}"),
+            ListOp::Sentence(first, second) => write!(f, "SENTENCE {first} {second}"),
+            ListOp::Char(code) => write!(f, "CHAR {code}"),
+        }
+    }
+}
+
+impl fmt::Display for Math {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Math::Add(lhs, rhs) => write!(f, "+ {lhs} {rhs}"),
+            Math::Sub(lhs, rhs) => write!(f, "- {lhs} {rhs}"),
+            Math::Mul(lhs, rhs) => write!(f, "* {lhs} {rhs}"),
+            Math::Div(lhs, rhs) => write!(f, "/ {lhs} {rhs}"),
+            Math::Mod(lhs, rhs) => write!(f, "MOD {lhs} {rhs}"),
+            Math::Remainder(lhs, rhs) => write!(f, "REMAINDER {lhs} {rhs}"),
+            Math::Power(lhs, rhs) => write!(f, "POWER {lhs} {rhs}"),
+            Math::Sin(expr) => write!(f, "SIN {expr}"),
+            Math::Cos(expr) => write!(f, "COS {expr}"),
+            Math::Tan(expr) => write!(f, "TAN {expr}"),
+            Math::Sqrt(expr) => write!(f, "SQRT {expr}"),
+            Math::Int(expr) => write!(f, "INT {expr}"),
+            Math::Round(expr) => write!(f, "ROUND {expr}"),
+            Math::Abs(expr) => write!(f, "ABS {expr}"),
+            Math::Clamp(value, low, high) => write!(f, "CLAMP {value} {low} {high}"),
+            Math::Lerp(from, to, fraction) => write!(f, "LERP {from} {to} {fraction}"),
+            Math::MapRange(value, in_low, in_high, out_low, out_high) => {
+                write!(f, "MAP-RANGE {value} {in_low} {in_high} {out_low} {out_high}")
+            },
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Equal(lhs, rhs) => write!(f, "EQ {lhs} {rhs}"),
+            Condition::NotEqual(lhs, rhs) => write!(f, "NE {lhs} {rhs}"),
+            Condition::NearlyEqual(lhs, rhs) => write!(f, "NEARLYEQ {lhs} {rhs}"),
+            Condition::LessThan(lhs, rhs) => write!(f, "LT {lhs} {rhs}"),
+            Condition::GreaterThan(lhs, rhs) => write!(f, "GT {lhs} {rhs}"),
+            Condition::LessEqual(lhs, rhs) => write!(f, "LE {lhs} {rhs}"),
+            Condition::GreaterEqual(lhs, rhs) => write!(f, "GE {lhs} {rhs}"),
+            Condition::And(lhs, rhs) => write!(f, "AND {lhs} {rhs}"),
+            Condition::Or(lhs, rhs) => write!(f, "OR {lhs} {rhs}"),
+            Condition::Not(condition) => write!(f, "NOT {condition}"),
+            Condition::Inside(rect) => write!(f, "INSIDE? {rect}"),
+            Condition::IsNumber(value) => write!(f, "NUMBERP {value}"),
+            Condition::IsWord(value) => write!(f, "WORDP {value}"),
+            Condition::IsList(value) => write!(f, "LISTP {value}"),
+            Condition::IsEmpty(value) => write!(f, "EMPTYP {value}"),
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::XCOR => write!(f, "XCOR"),
+            Query::YCOR => write!(f, "YCOR"),
+            Query::HEADING => write!(f, "HEADING"),
+            Query::COLOR => write!(f, "COLOR"),
+            Query::FILLCOLOR => write!(f, "FILLCOLOR"),
+            Query::SCREENWIDTH => write!(f, "SCREENWIDTH"),
+            Query::SCREENHEIGHT => write!(f, "SCREENHEIGHT"),
+            Query::REPCOUNT => write!(f, "REPCOUNT"),
+            Query::Random(max) => write!(f, "RANDOM {max}"),
+            Query::PixelColor(x, y) => write!(f, "PIXELCOLOR {x} {y}"),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! # Value
+//!
+//! This module contains the `Value` enum, which is what an `Expression` evaluates to while a program runs.
+//!
+//! Variables hold a `Value`, so `MAKE` evaluates its expression once, when it runs, and using the variable
+//! afterwards gives the same value however the state the expression read has changed since. Procedure
+//! arguments are evaluated the same way before they are bound to the parameters.
+//!
+//! # Example
+//!
+//! ```
+//! use rslogo::parser::parse_content;
+//! use rslogo::runner::run;
+//! use rslogo::turtle::Turtle;
+//! use rslogo::value::Value;
+//!
+//! let asts = parse_content("MAKE \"start XCOR MAKE \"moved GT XCOR \"50 SETX \"80 MAKE \"sides [3 4]").unwrap();
+//! let mut turtle = Turtle::new(100, 100);
+//! run(&asts, &mut turtle).unwrap();
+//!
+//! assert_eq!(turtle.get_variable("start").unwrap(), &Value::Float(50.0));
+//! assert_eq!(turtle.get_variable("moved").unwrap(), &Value::Bool(false));
+//! assert_eq!(turtle.get_variable("sides").unwrap().to_string(), "[ \"3 \"4 ]");
+//! ```
+
+use std::fmt;
+
+use crate::error::RuntimeError;
+
+/// A value computed while a program runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A floating point number.
+    Float(f32),
+
+    /// An exact whole number, produced by arithmetic in integer mode.
+    Int(i64),
+
+    /// The result of a condition.
+    Bool(bool),
+
+    /// A word.
+    String(String),
+
+    /// A list of values.
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// Wraps `val` as an `Int` if `integer` is set and it is a whole number an `f32` holds exactly, or as a `Float` otherwise.
+    pub(crate) fn number(val: f32, integer: bool) -> Value {
+        const EXACT: f32 = 16777216.0;
+        if integer && val.fract() == 0.0 && val.abs() <= EXACT {
+            Value::Int(val as i64)
+        } else {
+            Value::Float(val)
+        }
+    }
+
+    /// Gets the value as a float, if it is a number.
+    pub fn to_float(&self) -> Option<f32> {
+        match self {
+            Value::Float(val) => Some(*val),
+            Value::Int(val) => Some(*val as f32),
+            _ => None,
+        }
+    }
+
+    /// Gets the value as an `Int` or `Float`, if it is a number, making whole numbers `Int`s if `integer` is set.
+    pub fn to_number(&self, integer: bool) -> Option<Value> {
+        match self {
+            Value::Int(_) => Some(self.clone()),
+            Value::Float(val) => Some(Value::number(*val, integer)),
+            _ => None,
+        }
+    }
+
+    /// Gets the value as a boolean, if it is the result of a condition.
+    pub fn to_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Gets the value as a string, if it is a word that is not a number.
+    pub fn as_string(&self) -> Option<&str> {
+        match self {
+            Value::String(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the `TypeMismatch` error for the value, which is not of the type `expected`.
+    pub(crate) fn mismatch(self, expected: &'static str) -> RuntimeError {
+        RuntimeError::TypeMismatch { expected, found: self }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Float(val) => write!(f, "\"{val}"),
+            Value::Int(val) => write!(f, "\"{val}"),
+            Value::Bool(true) => write!(f, "\"TRUE"),
+            Value::Bool(false) => write!(f, "\"FALSE"),
+            Value::String(val) => write!(f, "\"{val}"),
+            Value::List(items) => {
+                write!(f, "[")?;
+                items.iter().try_for_each(|item| write!(f, " {item}"))?;
+                write!(f, " ]")
+            },
+        }
+    }
+}
+
+/// Operator overloading for math operations on `Value`.
+/// Note: Only supports math operations on `Value::Float` and `Value::Int`.
+/// Two `Int`s give an `Int` unless the result overflows or, for division, is not a whole number,
+/// in which case they give a `Float`, as does mixing an `Int` with a `Float`.
+/// Any other operand gives a `TypeMismatch` error, and dividing by zero gives a `DivisionByZero` error.
+impl std::ops::Add for Value {
+    type Output = Result<Self, RuntimeError>;
+    fn add(self, other: Self) -> Self::Output {
+        match (self, other) {
+            (Value::Float(val1), Value::Float(val2)) => Ok(Value::Float(val1 + val2)),
+            (Value::Int(val1), Value::Int(val2)) => match val1.checked_add(val2) {
+                Some(val) => Ok(Value::Int(val)),
+                None => Ok(Value::Float(val1 as f32 + val2 as f32)),
+            },
+            (Value::Int(val1), other) => Value::Float(val1 as f32) + other,
+            (this, Value::Int(val2)) => this + Value::Float(val2 as f32),
+            (this, other) => Err(non_numeric(this, other)),
+        }
+    }
+}
+
+impl std::ops::Sub for Value {
+    type Output = Result<Self, RuntimeError>;
+    fn sub(self, other: Self) -> Self::Output {
+        match (self, other) {
+            (Value::Float(val1), Value::Float(val2)) => Ok(Value::Float(val1 - val2)),
+            (Value::Int(val1), Value::Int(val2)) => match val1.checked_sub(val2) {
+                Some(val) => Ok(Value::Int(val)),
+                None => Ok(Value::Float(val1 as f32 - val2 as f32)),
+            },
+            (Value::Int(val1), other) => Value::Float(val1 as f32) - other,
+            (this, Value::Int(val2)) => this - Value::Float(val2 as f32),
+            (this, other) => Err(non_numeric(this, other)),
+        }
+    }
+}
+
+impl std::ops::Mul for Value {
+    type Output = Result<Self, RuntimeError>;
+    fn mul(self, other: Self) -> Self::Output {
+        match (self, other) {
+            (Value::Float(val1), Value::Float(val2)) => Ok(Value::Float(val1 * val2)),
+            (Value::Int(val1), Value::Int(val2)) => match val1.checked_mul(val2) {
+                Some(val) => Ok(Value::Int(val)),
+                None => Ok(Value::Float(val1 as f32 * val2 as f32)),
+            },
+            (Value::Int(val1), other) => Value::Float(val1 as f32) * other,
+            (this, Value::Int(val2)) => this * Value::Float(val2 as f32),
+            (this, other) => Err(non_numeric(this, other)),
+        }
+    }
+}
+
+impl std::ops::Div for Value {
+    type Output = Result<Self, RuntimeError>;
+    fn div(self, other: Self) -> Self::Output {
+        match (self, other) {
+            (Value::Float(val1), Value::Float(val2)) => {
+                if val2 == 0.0 {
+                    return Err(RuntimeError::DivisionByZero);
+                }
+                Ok(Value::Float(val1 / val2))
+            },
+            (Value::Int(val1), Value::Int(val2)) => match val1.checked_rem(val2) {
+                Some(0) => Ok(Value::Int(val1 / val2)),
+                _ => Value::Float(val1 as f32) / Value::Float(val2 as f32),
+            },
+            (Value::Int(val1), other) => Value::Float(val1 as f32) / other,
+            (this, Value::Int(val2)) => this / Value::Float(val2 as f32),
+            (this, other) => Err(non_numeric(this, other)),
+        }
+    }
+}
+
+/// Finds the remainder of dividing `dividend` by `divisor`. It has the sign of the divisor if `modulo` is
+/// set, as `MOD` does, and of the dividend otherwise, as `REMAINDER` does.
+pub(crate) fn remainder(dividend: Value, divisor: Value, modulo: bool) -> Result<Value, RuntimeError> {
+    match (dividend, divisor) {
+        (Value::Int(_), Value::Int(0)) => Err(RuntimeError::DivisionByZero),
+        (Value::Int(val1), Value::Int(val2)) => {
+            let val = val1.checked_rem(val2).unwrap_or(0);
+            if modulo && val != 0 && (val < 0) != (val2 < 0) {
+                Ok(Value::Int(val + val2))
+            } else {
+                Ok(Value::Int(val))
+            }
+        },
+        (Value::Int(val1), divisor) => remainder(Value::Float(val1 as f32), divisor, modulo),
+        (dividend, Value::Int(val2)) => remainder(dividend, Value::Float(val2 as f32), modulo),
+        (Value::Float(val1), Value::Float(val2)) => {
+            if val2 == 0.0 {
+                return Err(RuntimeError::DivisionByZero);
+            }
+            let val = val1 % val2;
+            if modulo && val != 0.0 && (val < 0.0) != (val2 < 0.0) {
+                Ok(Value::Float(val + val2))
+            } else {
+                Ok(Value::Float(val))
+            }
+        },
+        (dividend, divisor) => Err(non_numeric(dividend, divisor)),
+    }
+}
+
+/// Raises `base` to the power of `exponent`, giving an `Int` when both are `Int`s, the exponent is not
+/// negative, and the result does not overflow.
+pub(crate) fn power(base: Value, exponent: Value) -> Result<Value, RuntimeError> {
+    match (base, exponent) {
+        (Value::Int(base), Value::Int(exponent)) => {
+            match u32::try_from(exponent).ok().and_then(|exponent| base.checked_pow(exponent)) {
+                Some(val) => Ok(Value::Int(val)),
+                None => power(Value::Float(base as f32), Value::Float(exponent as f32)),
+            }
+        },
+        (Value::Int(base), exponent) => power(Value::Float(base as f32), exponent),
+        (base, Value::Int(exponent)) => power(base, Value::Float(exponent as f32)),
+        (Value::Float(base), Value::Float(exponent)) => {
+            let val = base.powf(exponent);
+            if val.is_nan() {
+                return Err(RuntimeError::Undefined { operation: "POWER", value: base });
+            }
+            Ok(Value::Float(val))
+        },
+        (base, exponent) => Err(non_numeric(base, exponent)),
+    }
+}
+
+/// Returns the `TypeMismatch` error for whichever of two math operands is not a number.
+fn non_numeric(lhs: Value, rhs: Value) -> RuntimeError {
+    let found = match lhs {
+        Value::Float(_) | Value::Int(_) => rhs,
+        _ => lhs,
+    };
+    found.mismatch("number")
+}